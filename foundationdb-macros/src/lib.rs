@@ -3,7 +3,9 @@ use proc_macro::TokenStream;
 use quote::quote;
 use std::collections::HashMap;
 use syn::__private::TokenStream2;
-use syn::{AttributeArgs, Item, Lit, Meta, NestedMeta};
+use syn::{AttributeArgs, DeriveInput, Item, Lit, Meta, NestedMeta};
+
+mod tuple;
 
 /// Allow to compute the range of supported api versions for a functionality.
 ///
@@ -26,6 +28,41 @@ pub fn cfg_api_versions(attr: TokenStream, item: TokenStream) -> TokenStream {
     generate_feature_range(&input, minimum_version, maximum_version)
 }
 
+/// Derive `TuplePack` and `FlatTuplePack`.
+///
+/// Structs are packed as a tuple of their fields, in declaration order. Enums are packed as a
+/// tuple starting with the tag of the variant followed by its fields, except unit variants
+/// which are packed as their bare tag.
+///
+/// The tag of a variant is its discriminant, which can be overridden with
+/// `#[tuple(tag = 42)]`.
+///
+/// Fields support the following attributes:
+/// - `#[tuple(nested)]`, the default, packs the field as a single element, a tuple-like field
+///   becomes a nested tuple,
+/// - `#[tuple(flatten)]` packs the elements of the field inline, among the other fields, the
+///   field must implement `FlatTuplePack`,
+/// - `#[tuple(skip)]` does not pack the field.
+#[proc_macro_derive(TuplePack, attributes(tuple))]
+pub fn derive_tuple_pack(item: TokenStream) -> TokenStream {
+    let input = syn::parse_macro_input!(item as DeriveInput);
+    tuple::derive_tuple_pack(&input)
+        .unwrap_or_else(|err| err.to_compile_error())
+        .into()
+}
+
+/// Derive `TupleUnpack` and `FlatTupleUnpack`.
+///
+/// See `TuplePack` for the layout and the supported attributes, skipped fields are unpacked
+/// using `Default::default()`.
+#[proc_macro_derive(TupleUnpack, attributes(tuple))]
+pub fn derive_tuple_unpack(item: TokenStream) -> TokenStream {
+    let input = syn::parse_macro_input!(item as DeriveInput);
+    tuple::derive_tuple_unpack(&input)
+        .unwrap_or_else(|err| err.to_compile_error())
+        .into()
+}
+
 /// Search for a required min and an optional max in the attributes
 fn parse_version_arguments(attributes_args: &AttributeArgs) -> (i32, Option<i32>) {
     let min = attributes_args
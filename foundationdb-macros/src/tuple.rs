@@ -0,0 +1,440 @@
+//! Implementation of `#[derive(TuplePack)]` and `#[derive(TupleUnpack)]`
+use quote::{format_ident, quote};
+use std::collections::HashMap;
+use syn::__private::TokenStream2;
+use syn::spanned::Spanned;
+use syn::{
+    parse_quote, Attribute, Data, DeriveInput, Error, Expr, ExprLit, Fields, Generics, Ident,
+    Index, Lifetime, LifetimeDef, Lit, Member, Meta, NestedMeta, Result, Type,
+};
+
+/// How a field takes part in the tuple of its parent
+#[derive(Clone, Copy, PartialEq)]
+enum FieldMode {
+    /// The field is packed as a single element, a tuple-like field becomes a nested tuple
+    Nested,
+    /// The elements of the field are packed inline, among the ones of its parent
+    Flatten,
+    /// The field is not packed, and is unpacked with `Default::default()`
+    Skip,
+}
+
+struct Field {
+    member: Member,
+    binding: Ident,
+    ty: Type,
+    mode: FieldMode,
+}
+
+struct Variant {
+    ident: Ident,
+    tag: i64,
+    fields: Vec<Field>,
+}
+
+/// Type being derived, structs are handled as an enum without any tag
+enum Shape {
+    Struct(Vec<Field>),
+    Enum(Vec<Variant>),
+}
+
+pub(crate) fn derive_tuple_pack(input: &DeriveInput) -> Result<TokenStream2> {
+    let shape = parse_shape(input)?;
+    let name = &input.ident;
+    let generics = add_type_bounds(&input.generics, quote!(::foundationdb::tuple::TuplePack));
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+
+    let (pack_flat, pack) = match &shape {
+        Shape::Struct(fields) => {
+            let pack_fields = pack_fields(fields, |field| {
+                let member = &field.member;
+                quote!(&self.#member)
+            });
+            let pack_flat = quote! {
+                let mut offset = ::foundationdb::tuple::VersionstampOffset::None { size: 0 };
+                #pack_fields
+                ::std::result::Result::Ok(offset)
+            };
+            let pack = quote! {
+                ::foundationdb::tuple::FlatTuplePack::pack_nested(self, w, tuple_depth)
+            };
+            (pack_flat, pack)
+        }
+        Shape::Enum(variants) => {
+            let flat_arms = variants.iter().map(|variant| {
+                let pattern = variant_pattern(variant);
+                let tag = variant.tag;
+                let pack_fields = pack_fields(&variant.fields, |field| {
+                    let binding = &field.binding;
+                    quote!(#binding)
+                });
+                quote! {
+                    #pattern => {
+                        offset += ::foundationdb::tuple::TuplePack::pack(&#tag, w, tuple_depth)?;
+                        #pack_fields
+                    }
+                }
+            });
+            let pack_flat = quote! {
+                let mut offset = ::foundationdb::tuple::VersionstampOffset::None { size: 0 };
+                match self {
+                    #(#flat_arms)*
+                }
+                ::std::result::Result::Ok(offset)
+            };
+
+            // unit variants are packed as their bare tag, without being nested
+            let arms = variants.iter().map(|variant| {
+                let ident = &variant.ident;
+                let tag = variant.tag;
+                if variant.fields.is_empty() {
+                    quote! {
+                        Self::#ident { .. } => ::foundationdb::tuple::TuplePack::pack(&#tag, w, tuple_depth),
+                    }
+                } else {
+                    quote! {
+                        Self::#ident { .. } => ::foundationdb::tuple::FlatTuplePack::pack_nested(self, w, tuple_depth),
+                    }
+                }
+            });
+            let pack = quote! {
+                match self {
+                    #(#arms)*
+                }
+            };
+            (pack_flat, pack)
+        }
+    };
+
+    Ok(quote! {
+        impl #impl_generics ::foundationdb::tuple::FlatTuplePack for #name #ty_generics #where_clause {
+            #[allow(unused_mut, unused_variables)]
+            fn pack_flat<__W: ::std::io::Write>(
+                &self,
+                w: &mut __W,
+                tuple_depth: ::foundationdb::tuple::TupleDepth,
+            ) -> ::std::io::Result<::foundationdb::tuple::VersionstampOffset> {
+                #pack_flat
+            }
+        }
+
+        impl #impl_generics ::foundationdb::tuple::TuplePack for #name #ty_generics #where_clause {
+            fn pack<__W: ::std::io::Write>(
+                &self,
+                w: &mut __W,
+                tuple_depth: ::foundationdb::tuple::TupleDepth,
+            ) -> ::std::io::Result<::foundationdb::tuple::VersionstampOffset> {
+                #pack
+            }
+        }
+    })
+}
+
+pub(crate) fn derive_tuple_unpack(input: &DeriveInput) -> Result<TokenStream2> {
+    let shape = parse_shape(input)?;
+    let name = &input.ident;
+    let (_, ty_generics, _) = input.generics.split_for_impl();
+
+    // A type borrowing with a single lifetime borrows from the input, otherwise the input must
+    // outlive all of them.
+    let mut generics = input.generics.clone();
+    let lifetimes: Vec<Lifetime> = generics.lifetimes().map(|l| l.lifetime.clone()).collect();
+    let de = if lifetimes.len() == 1 {
+        lifetimes[0].clone()
+    } else {
+        let de = Lifetime::new("'de", name.span());
+        let mut de_def = LifetimeDef::new(de.clone());
+        de_def.bounds.extend(lifetimes);
+        generics.params.insert(0, de_def.into());
+        de
+    };
+    let generics = add_type_bounds(&generics, quote!(::foundationdb::tuple::TupleUnpack<#de>));
+    let (impl_generics, _, where_clause) = generics.split_for_impl();
+
+    let name_str = name.to_string();
+    let unknown_tag = quote! {
+        ::std::result::Result::Err(::foundationdb::tuple::PackError::Message(
+            ::std::format!("unknown tag {} for `{}`", tag, #name_str).into_boxed_str(),
+        ))
+    };
+
+    let (unpack_flat, unpack) = match &shape {
+        Shape::Struct(fields) => {
+            let unpack_fields = unpack_fields(fields, &de);
+            let construct = construct(quote!(Self), fields);
+            let unpack_flat = quote! {
+                #unpack_fields
+                ::std::result::Result::Ok((input, #construct))
+            };
+            let unpack = quote! {
+                ::foundationdb::tuple::FlatTupleUnpack::unpack_nested(input, tuple_depth)
+            };
+            (unpack_flat, unpack)
+        }
+        Shape::Enum(variants) => {
+            let flat_arms = variants.iter().map(|variant| {
+                let ident = &variant.ident;
+                let tag = variant.tag;
+                let unpack_fields = unpack_fields(&variant.fields, &de);
+                let construct = construct(quote!(Self::#ident), &variant.fields);
+                quote! {
+                    #tag => {
+                        #unpack_fields
+                        ::std::result::Result::Ok((input, #construct))
+                    }
+                }
+            });
+            let unpack_flat = quote! {
+                let (input, tag) = <i64 as ::foundationdb::tuple::TupleUnpack<#de>>::unpack(input, tuple_depth)?;
+                match tag {
+                    #(#flat_arms)*
+                    tag => #unknown_tag,
+                }
+            };
+
+            let unit_arms = variants
+                .iter()
+                .filter(|variant| variant.fields.is_empty())
+                .map(|variant| {
+                    let ident = &variant.ident;
+                    let tag = variant.tag;
+                    quote!(#tag => ::std::result::Result::Ok((input, Self::#ident {})),)
+                });
+            let unpack = quote! {
+                if tuple_depth.depth() > 0 {
+                    if let ::std::result::Result::Ok((input, tag)) =
+                        <i64 as ::foundationdb::tuple::TupleUnpack<#de>>::unpack(input, tuple_depth)
+                    {
+                        return match tag {
+                            #(#unit_arms)*
+                            tag => #unknown_tag,
+                        };
+                    }
+                }
+                ::foundationdb::tuple::FlatTupleUnpack::unpack_nested(input, tuple_depth)
+            };
+            (unpack_flat, unpack)
+        }
+    };
+
+    Ok(quote! {
+        impl #impl_generics ::foundationdb::tuple::FlatTupleUnpack<#de> for #name #ty_generics #where_clause {
+            #[allow(unused_variables)]
+            fn unpack_flat(
+                input: &#de [u8],
+                tuple_depth: ::foundationdb::tuple::TupleDepth,
+            ) -> ::foundationdb::tuple::PackResult<(&#de [u8], Self)> {
+                #unpack_flat
+            }
+        }
+
+        impl #impl_generics ::foundationdb::tuple::TupleUnpack<#de> for #name #ty_generics #where_clause {
+            fn unpack(
+                input: &#de [u8],
+                tuple_depth: ::foundationdb::tuple::TupleDepth,
+            ) -> ::foundationdb::tuple::PackResult<(&#de [u8], Self)> {
+                #unpack
+            }
+        }
+    })
+}
+
+fn parse_shape(input: &DeriveInput) -> Result<Shape> {
+    match &input.data {
+        Data::Struct(data) => {
+            if let Some(attribute) = tuple_attributes(&input.attrs)?.first() {
+                return Err(Error::new(
+                    attribute.span(),
+                    "unknown tuple attribute for a struct",
+                ));
+            }
+            Ok(Shape::Struct(parse_fields(&data.fields)?))
+        }
+        Data::Enum(data) if data.variants.is_empty() => Err(Error::new(
+            input.ident.span(),
+            "enums without variants are not supported",
+        )),
+        Data::Enum(data) => {
+            let mut variants = Vec::with_capacity(data.variants.len());
+            let mut tags = HashMap::new();
+            let mut next_tag = 0i64;
+            for variant in &data.variants {
+                let mut tag = match &variant.discriminant {
+                    Some((
+                        _,
+                        Expr::Lit(ExprLit {
+                            lit: Lit::Int(lit), ..
+                        }),
+                    )) => lit.base10_parse()?,
+                    Some((_, expr)) => {
+                        return Err(Error::new(
+                            expr.span(),
+                            "only integer literals are supported as discriminant, use #[tuple(tag = ...)]",
+                        ))
+                    }
+                    None => next_tag,
+                };
+                for attribute in tuple_attributes(&variant.attrs)? {
+                    match &attribute {
+                        NestedMeta::Meta(Meta::NameValue(name_value))
+                            if name_value.path.is_ident("tag") =>
+                        {
+                            match &name_value.lit {
+                                Lit::Int(lit) => tag = lit.base10_parse()?,
+                                lit => return Err(Error::new(lit.span(), "expected an integer")),
+                            }
+                        }
+                        _ => {
+                            return Err(Error::new(
+                                attribute.span(),
+                                "unknown tuple attribute for a variant",
+                            ))
+                        }
+                    }
+                }
+                if let Some(other) = tags.insert(tag, &variant.ident) {
+                    return Err(Error::new(
+                        variant.ident.span(),
+                        format!("tag {} is already used by `{}`", tag, other),
+                    ));
+                }
+                next_tag = tag.wrapping_add(1);
+
+                variants.push(Variant {
+                    ident: variant.ident.clone(),
+                    tag,
+                    fields: parse_fields(&variant.fields)?,
+                });
+            }
+            Ok(Shape::Enum(variants))
+        }
+        Data::Union(data) => Err(Error::new(
+            data.union_token.span(),
+            "unions are not supported",
+        )),
+    }
+}
+
+fn parse_fields(fields: &Fields) -> Result<Vec<Field>> {
+    fields
+        .iter()
+        .enumerate()
+        .map(|(i, field)| {
+            let mut mode = FieldMode::Nested;
+            for attribute in tuple_attributes(&field.attrs)? {
+                mode = match &attribute {
+                    NestedMeta::Meta(Meta::Path(path)) if path.is_ident("nested") => {
+                        FieldMode::Nested
+                    }
+                    NestedMeta::Meta(Meta::Path(path)) if path.is_ident("flatten") => {
+                        FieldMode::Flatten
+                    }
+                    NestedMeta::Meta(Meta::Path(path)) if path.is_ident("skip") => FieldMode::Skip,
+                    _ => {
+                        return Err(Error::new(
+                            attribute.span(),
+                            "unknown tuple attribute for a field",
+                        ))
+                    }
+                };
+            }
+
+            Ok(Field {
+                member: match &field.ident {
+                    Some(ident) => Member::Named(ident.clone()),
+                    None => Member::Unnamed(Index::from(i)),
+                },
+                binding: format_ident!("__field{}", i),
+                ty: field.ty.clone(),
+                mode,
+            })
+        })
+        .collect()
+}
+
+/// Collect the content of every `#[tuple(...)]` attribute
+fn tuple_attributes(attributes: &[Attribute]) -> Result<Vec<NestedMeta>> {
+    let mut nested = Vec::new();
+    for attribute in attributes {
+        if !attribute.path.is_ident("tuple") {
+            continue;
+        }
+        match attribute.parse_meta()? {
+            Meta::List(list) => nested.extend(list.nested),
+            meta => return Err(Error::new(meta.span(), "expected #[tuple(...)]")),
+        }
+    }
+    Ok(nested)
+}
+
+fn add_type_bounds(generics: &Generics, bound: TokenStream2) -> Generics {
+    let mut generics = generics.clone();
+    let idents: Vec<Ident> = generics.type_params().map(|p| p.ident.clone()).collect();
+    let where_clause = generics.make_where_clause();
+    for ident in idents {
+        where_clause.predicates.push(parse_quote!(#ident: #bound));
+    }
+    generics
+}
+
+/// Match a variant, binding the fields that are not skipped
+fn variant_pattern(variant: &Variant) -> TokenStream2 {
+    let ident = &variant.ident;
+    let bindings = variant
+        .fields
+        .iter()
+        .filter(|field| field.mode != FieldMode::Skip)
+        .map(|field| {
+            let member = &field.member;
+            let binding = &field.binding;
+            quote!(#member: #binding,)
+        });
+    quote!(Self::#ident { #(#bindings)* .. })
+}
+
+fn pack_fields(fields: &[Field], access: impl Fn(&Field) -> TokenStream2) -> TokenStream2 {
+    fields
+        .iter()
+        .map(|field| {
+            let value = access(field);
+            match field.mode {
+                FieldMode::Nested => quote! {
+                    offset += ::foundationdb::tuple::TuplePack::pack(#value, w, tuple_depth)?;
+                },
+                FieldMode::Flatten => quote! {
+                    offset += ::foundationdb::tuple::FlatTuplePack::pack_flat(#value, w, tuple_depth)?;
+                },
+                FieldMode::Skip => quote!(),
+            }
+        })
+        .collect()
+}
+
+fn unpack_fields(fields: &[Field], de: &Lifetime) -> TokenStream2 {
+    fields
+        .iter()
+        .map(|field| {
+            let binding = &field.binding;
+            let ty = &field.ty;
+            match field.mode {
+                FieldMode::Nested => quote! {
+                    let (input, #binding) =
+                        <#ty as ::foundationdb::tuple::TupleUnpack<#de>>::unpack(input, tuple_depth)?;
+                },
+                FieldMode::Flatten => quote! {
+                    let (input, #binding) =
+                        <#ty as ::foundationdb::tuple::FlatTupleUnpack<#de>>::unpack_flat(input, tuple_depth)?;
+                },
+                FieldMode::Skip => quote! {
+                    let #binding: #ty = ::std::default::Default::default();
+                },
+            }
+        })
+        .collect()
+}
+
+fn construct(path: TokenStream2, fields: &[Field]) -> TokenStream2 {
+    let members = fields.iter().map(|field| &field.member);
+    let bindings = fields.iter().map(|field| &field.binding);
+    quote!(#path { #(#members: #bindings),* })
+}
@@ -12,14 +12,25 @@
 //!
 //! - [API versioning](https://apple.github.io/foundationdb/api-c.html#api-versioning)
 //! - [Network](https://apple.github.io/foundationdb/api-c.html#network)
+//!
+//! The [multi-version client](https://apple.github.io/foundationdb/api-general.html#multi-version-client-api)
+//! can be configured with a [`MultiVersionConfig`].
 
+use std::ffi::CStr;
+use std::fs;
+use std::io;
 use std::panic;
-use std::sync::atomic::{AtomicBool, Ordering};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, AtomicPtr, Ordering};
 use std::sync::{Arc, Condvar, Mutex};
 use std::thread;
 
 use crate::options::NetworkOption;
 use crate::{error, FdbResult};
+#[cfg_api_versions(min = 700)]
+use crate::{Database, ProtocolVersion};
+use foundationdb_macros::cfg_api_versions;
 use foundationdb_sys as fdb_sys;
 
 /// Returns the max api version of the underlying Fdb C API Client
@@ -27,6 +38,13 @@ pub fn get_max_api_version() -> i32 {
     unsafe { fdb_sys::fdb_get_max_api_version() }
 }
 
+/// Returns the version of the local client library, such as `"7.1.25,<commit>,<protocol>"`
+pub fn get_client_version() -> String {
+    unsafe { CStr::from_ptr(fdb_sys::fdb_get_client_version()) }
+        .to_string_lossy()
+        .into_owned()
+}
+
 static VERSION_SELECTED: AtomicBool = AtomicBool::new(false);
 
 /// A Builder with which different versions of the Fdb C API can be initialized
@@ -108,6 +126,44 @@ impl NetworkBuilder {
         Ok(self)
     }
 
+    /// Set the network options of a multi-version client configuration.
+    ///
+    /// The configuration should be checked with `MultiVersionConfig::validate` first.
+    pub fn set_multi_version_config(mut self, config: &MultiVersionConfig) -> FdbResult<Self> {
+        for option in config.network_options() {
+            self = self.set_option(option)?;
+        }
+        Ok(self)
+    }
+
+    /// Starts the FoundationDB run loop in a dedicated thread, like `boot()`, without requiring
+    /// the caller to stop it before the program exits.
+    ///
+    /// The run loop is stopped and its thread joined when the returned `NetworkGuard` is dropped,
+    /// including while unwinding from a panic. If the guard is leaked, or the program exits with
+    /// `std::process::exit` while it is alive, this is done by an `atexit` hook.
+    ///
+    /// The network cannot be restarted once stopped.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use foundationdb::api::FdbApiBuilder;
+    ///
+    /// let network_builder = FdbApiBuilder::default().build().expect("fdb api initialized");
+    /// let network = network_builder.start().expect("fdb network running");
+    /// // do some interesting things with the API...
+    /// drop(network);
+    /// ```
+    pub fn start(self) -> FdbResult<NetworkGuard> {
+        // safe: the network is stopped before the program exits, by the guard or the hook
+        let network = unsafe { self.boot()? };
+        let running = Box::into_raw(Box::new(Mutex::new(Some(network))));
+        RUNNING_NETWORK.store(running, Ordering::Release);
+        unsafe { libc::atexit(stop_network_at_exit) };
+        Ok(NetworkGuard { _private: () })
+    }
+
     /// Finalizes the initialization of the Network and returns a way to run/wait/stop the
     /// FoundationDB run loop.
     ///
@@ -207,6 +263,296 @@ impl NetworkBuilder {
     }
 }
 
+/// Configuration of the multi-version client
+///
+/// The multi-version client loads client libraries of other versions of FoundationDB, and uses
+/// the one compatible with the cluster, so the cluster can be upgraded without restarting the
+/// clients. The configuration can be built in code, or read from the environment or a file:
+///
+/// ```
+/// use foundationdb::api::MultiVersionConfig;
+///
+/// let config: MultiVersionConfig = "
+///     ## the client of the previous version
+///     external_client_library = /usr/lib/foundationdb/libfdb_c_7.0.0.so
+///     max_api_version = 700
+/// "
+/// .parse()
+/// .expect("valid configuration");
+/// assert_eq!(config.external_client_libraries.len(), 1);
+/// ```
+///
+/// The libraries must then be checked with `validate`, the api version selected with
+/// `FdbApiBuilder::set_runtime_version(config.runtime_version())`, and the network options set
+/// with `NetworkBuilder::set_multi_version_config`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct MultiVersionConfig {
+    /// The client libraries to load, `NetworkOption::ExternalClientLibrary`
+    pub external_client_libraries: Vec<PathBuf>,
+    /// The directories whose client libraries are all loaded,
+    /// `NetworkOption::ExternalClientDirectory`
+    pub external_client_directories: Vec<PathBuf>,
+    /// Only use the external client libraries, `NetworkOption::DisableLocalClient`
+    pub disable_local_client: bool,
+    /// Caps the selected api version, see `runtime_version`. The versions of the external
+    /// libraries are not checked, this should be the lowest maximum api version among them.
+    pub max_api_version: Option<i32>,
+}
+
+impl MultiVersionConfig {
+    /// Reads the configuration from the environment.
+    ///
+    /// The variables are `FDB_EXTERNAL_CLIENT_LIBRARIES` and `FDB_EXTERNAL_CLIENT_DIRECTORIES`, as
+    /// lists of paths separated like in `PATH`, `FDB_DISABLE_LOCAL_CLIENT` and
+    /// `FDB_MAX_API_VERSION`.
+    pub fn from_env() -> io::Result<Self> {
+        let paths = |name| {
+            std::env::var_os(name)
+                .map(|paths| std::env::split_paths(&paths).collect())
+                .unwrap_or_default()
+        };
+        let mut config = MultiVersionConfig {
+            external_client_libraries: paths("FDB_EXTERNAL_CLIENT_LIBRARIES"),
+            external_client_directories: paths("FDB_EXTERNAL_CLIENT_DIRECTORIES"),
+            ..MultiVersionConfig::default()
+        };
+        if let Ok(value) = std::env::var("FDB_DISABLE_LOCAL_CLIENT") {
+            config.set("disable_local_client", &value)?;
+        }
+        if let Ok(value) = std::env::var("FDB_MAX_API_VERSION") {
+            config.set("max_api_version", &value)?;
+        }
+        Ok(config)
+    }
+
+    /// Reads the configuration from a file.
+    ///
+    /// The file has a `key = value` per line, the keys being `external_client_library` and
+    /// `external_client_directory`, which can be repeated, `disable_local_client` and
+    /// `max_api_version`. Lines starting with `#` are ignored.
+    pub fn from_file(path: impl AsRef<Path>) -> io::Result<Self> {
+        fs::read_to_string(path)?.parse()
+    }
+
+    fn set(&mut self, key: &str, value: &str) -> io::Result<()> {
+        let invalid = |what: &str| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("invalid {} {:?}", what, value),
+            )
+        };
+        match key {
+            "external_client_library" => self.external_client_libraries.push(value.into()),
+            "external_client_directory" => self.external_client_directories.push(value.into()),
+            "disable_local_client" => {
+                self.disable_local_client = match value {
+                    "true" | "1" => true,
+                    "false" | "0" => false,
+                    _ => return Err(invalid("disable_local_client")),
+                }
+            }
+            "max_api_version" => {
+                self.max_api_version = Some(value.parse().map_err(|_| invalid("max_api_version"))?)
+            }
+            _ => return Err(invalid("key")),
+        }
+        Ok(())
+    }
+
+    /// Checks that the libraries and the directories exist, and that a client is left if the
+    /// local one is disabled.
+    pub fn validate(&self) -> io::Result<()> {
+        for library in &self.external_client_libraries {
+            if !fs::metadata(library)?.is_file() {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("{} is not a file", library.display()),
+                ));
+            }
+        }
+        for directory in &self.external_client_directories {
+            if !fs::metadata(directory)?.is_dir() {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("{} is not a directory", directory.display()),
+                ));
+            }
+        }
+        if self.disable_local_client
+            && self.external_client_libraries.is_empty()
+            && self.external_client_directories.is_empty()
+        {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "the local client is disabled without any external client",
+            ));
+        }
+        Ok(())
+    }
+
+    /// The api version to select: the highest one supported by both the bindings and the local
+    /// client library, capped by `max_api_version`.
+    ///
+    /// The external libraries are not inspected, they are only loaded once the network is set
+    /// up, after the api version is selected.
+    pub fn runtime_version(&self) -> i32 {
+        let version = std::cmp::min(fdb_sys::FDB_API_VERSION as i32, get_max_api_version());
+        self.max_api_version
+            .map_or(version, |max_api_version| version.min(max_api_version))
+    }
+
+    /// The network options applying this configuration
+    pub fn network_options(&self) -> Vec<NetworkOption> {
+        let path = |path: &PathBuf| path.to_string_lossy().into_owned();
+        let mut options: Vec<NetworkOption> = self
+            .external_client_libraries
+            .iter()
+            .map(|library| NetworkOption::ExternalClientLibrary(path(library)))
+            .chain(
+                self.external_client_directories
+                    .iter()
+                    .map(|directory| NetworkOption::ExternalClientDirectory(path(directory))),
+            )
+            .collect();
+        if self.disable_local_client {
+            options.push(NetworkOption::DisableLocalClient);
+        }
+        options
+    }
+
+    /// Returns the client library used to connect to the database.
+    ///
+    /// The local client is used if it is enabled and compatible with the protocol version of the
+    /// cluster. Otherwise, the client library does not report which external library it uses, so
+    /// the library is `ClientLibrary::Unknown`.
+    #[cfg_api_versions(min = 700)]
+    pub async fn connected_client(&self, db: &Database) -> FdbResult<ConnectedClient> {
+        let server_protocol = db.get_server_protocol(None).await?;
+        let local_protocol = get_client_version()
+            .split(',')
+            .nth(2)
+            .and_then(|protocol| u64::from_str_radix(protocol, 16).ok())
+            .map(ProtocolVersion::from);
+
+        let library = if !self.disable_local_client
+            && local_protocol.map_or(false, |local| local.is_compatible(server_protocol))
+        {
+            ClientLibrary::Local
+        } else {
+            ClientLibrary::Unknown
+        };
+        Ok(ConnectedClient {
+            server_protocol,
+            library,
+        })
+    }
+}
+
+impl FromStr for MultiVersionConfig {
+    type Err = io::Error;
+
+    fn from_str(s: &str) -> io::Result<Self> {
+        let mut config = MultiVersionConfig::default();
+        for line in s.lines().map(str::trim) {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (key, value) = line.split_once('=').ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("expected `key = value`, got {:?}", line),
+                )
+            })?;
+            config.set(key.trim(), value.trim())?;
+        }
+        Ok(config)
+    }
+}
+
+/// The client library used to connect to a database, see `MultiVersionConfig::connected_client`
+#[cfg_api_versions(min = 700)]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ConnectedClient {
+    /// The protocol version of the cluster
+    pub server_protocol: ProtocolVersion,
+    /// The client library compatible with the cluster
+    pub library: ClientLibrary,
+}
+
+/// A client library of the multi-version client
+#[cfg_api_versions(min = 700)]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ClientLibrary {
+    /// The library linked to the process
+    Local,
+    /// One of the external libraries, if any is compatible with the cluster
+    Unknown,
+}
+
+/// The network started by `NetworkBuilder::start`, leaked so the `atexit` hook can stop it
+static RUNNING_NETWORK: AtomicPtr<Mutex<Option<NetworkAutoStop>>> =
+    AtomicPtr::new(std::ptr::null_mut());
+
+/// Stops the network started by `NetworkBuilder::start`, if it is still running
+///
+/// Returns the panic of the network thread if it could not be joined.
+fn stop_running_network() -> thread::Result<()> {
+    let running = RUNNING_NETWORK.load(Ordering::Acquire);
+    if running.is_null() {
+        return Ok(());
+    }
+    let network = unsafe { &*running }
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .take();
+    match network {
+        Some(mut network) => network.stop(),
+        None => Ok(()),
+    }
+}
+
+/// The `atexit` hook of `NetworkBuilder::start`, a panic must not unwind out of it
+extern "C" fn stop_network_at_exit() {
+    match panic::catch_unwind(stop_running_network) {
+        Ok(Ok(())) => {}
+        Ok(Err(_)) => eprintln!("failed to join fdb thread at exit"),
+        Err(_) => eprintln!("failed to stop network at exit"),
+    }
+}
+
+/// Returns `true` while the network started by `NetworkBuilder::start` is running, `false` before
+/// it is started and once it is stopped by its `NetworkGuard` or the `atexit` hook.
+pub fn is_network_running() -> bool {
+    let running = RUNNING_NETWORK.load(Ordering::Acquire);
+    !running.is_null()
+        && unsafe { &*running }
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .is_some()
+}
+
+/// Stops the FoundationDB run loop started by `NetworkBuilder::start` when dropped
+///
+/// # Panics
+///
+/// Panics if the network thread cannot be joined, unless the guard is dropped while unwinding
+/// from another panic, in which case the failure is printed in `stderr` instead.
+pub struct NetworkGuard {
+    _private: (),
+}
+
+impl Drop for NetworkGuard {
+    fn drop(&mut self) {
+        if stop_running_network().is_err() {
+            if thread::panicking() {
+                eprintln!("failed to join fdb thread while panicking");
+            } else {
+                panic!("failed to join fdb thread");
+            }
+        }
+    }
+}
+
 /// A foundationDB network event loop runner
 ///
 /// Most of the time you should never need to use this directly and use `boot()`.
@@ -300,18 +646,26 @@ pub struct NetworkAutoStop {
     network: Option<NetworkStop>,
     handle: Option<std::thread::JoinHandle<()>>,
 }
+impl NetworkAutoStop {
+    /// Stops the run loop and joins its thread, returning the panic of the thread if any
+    fn stop(&mut self) -> thread::Result<()> {
+        if let Some(network) = self.network.take() {
+            if let Err(err) = network.stop() {
+                eprintln!("failed to stop network: {}", err);
+                // Not aborting can probably cause undefined behavior
+                std::process::abort();
+            }
+        }
+        match self.handle.take() {
+            Some(handle) => handle.join(),
+            None => Ok(()),
+        }
+    }
+}
+
 impl Drop for NetworkAutoStop {
     fn drop(&mut self) {
-        if let Err(err) = self.network.take().unwrap().stop() {
-            eprintln!("failed to stop network: {}", err);
-            // Not aborting can probably cause undefined behavior
-            std::process::abort();
-        }
-        self.handle
-            .take()
-            .unwrap()
-            .join()
-            .expect("failed to join fdb thread");
+        self.stop().expect("failed to join fdb thread");
     }
 }
 
@@ -323,4 +677,47 @@ mod tests {
     fn test_max_api() {
         assert!(get_max_api_version() > 0);
     }
+
+    #[test]
+    fn test_multi_version_config() {
+        let config: MultiVersionConfig = "
+            # comment
+            external_client_library = /lib/libfdb_c_7.0.0.so
+            external_client_library=/lib/libfdb_c_6.3.24.so
+            external_client_directory = /lib/fdb
+            disable_local_client = true
+            max_api_version = 630
+        "
+        .parse()
+        .unwrap();
+        assert_eq!(
+            config,
+            MultiVersionConfig {
+                external_client_libraries: vec![
+                    "/lib/libfdb_c_7.0.0.so".into(),
+                    "/lib/libfdb_c_6.3.24.so".into()
+                ],
+                external_client_directories: vec!["/lib/fdb".into()],
+                disable_local_client: true,
+                max_api_version: Some(630),
+            }
+        );
+        assert_eq!(config.network_options().len(), 4);
+        assert!(config.runtime_version() <= 630);
+        assert!(config.validate().is_err());
+
+        assert!("unknown = 1".parse::<MultiVersionConfig>().is_err());
+        assert!("max_api_version = seven"
+            .parse::<MultiVersionConfig>()
+            .is_err());
+        assert!("disable_local_client"
+            .parse::<MultiVersionConfig>()
+            .is_err());
+        let config = MultiVersionConfig {
+            disable_local_client: true,
+            ..MultiVersionConfig::default()
+        };
+        assert!(config.validate().is_err());
+        assert!(MultiVersionConfig::default().validate().is_ok());
+    }
 }
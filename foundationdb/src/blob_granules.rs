@@ -0,0 +1,499 @@
+// Copyright 2022 foundationdb-rs developers, https://github.com/Clikengo/foundationdb-rs/graphs/contributors
+// Copyright 2013-2018 Apple, Inc and the FoundationDB project authors.
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+//! Definitions of blob granules types, used in api version 710 and more.
+//!
+//! Blob granules are an experimental feature of FDB 7.1, storing snapshots and deltas of ranges
+//! in files, so old versions can be read without going through the storage servers.
+//!
+//! `Transaction::read_blob_granules` reads the granule files through a [`GranuleLoader`], such as
+//! [`LocalGranuleLoader`] when the files are in a local directory.
+
+use crate::future::{FdbFuture, FdbFutureHandle, FdbKeyValue, FdbSlice};
+use crate::transaction::{fdb_bool, fdb_len};
+use crate::{error, Database, FdbError, FdbResult};
+use foundationdb_sys as fdb_sys;
+use futures::Future;
+use std::any::Any;
+use std::collections::HashMap;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, Read, Seek, SeekFrom};
+use std::ops::Deref;
+use std::os::raw::{c_char, c_int, c_void};
+use std::panic::{self, AssertUnwindSafe};
+use std::path::PathBuf;
+use std::ptr::NonNull;
+use std::sync::Mutex;
+
+/// An slice of key ranges owned by a FoundationDB future
+pub struct FdbKeyRanges {
+    _f: FdbFutureHandle,
+    ranges: *const fdb_sys::FDBKeyRange,
+    len: i32,
+}
+unsafe impl Sync for FdbKeyRanges {}
+unsafe impl Send for FdbKeyRanges {}
+
+impl TryFrom<FdbFutureHandle> for FdbKeyRanges {
+    type Error = FdbError;
+
+    fn try_from(f: FdbFutureHandle) -> FdbResult<Self> {
+        let mut ranges = std::ptr::null();
+        let mut len = 0;
+
+        error::eval(unsafe {
+            fdb_sys::fdb_future_get_keyrange_array(f.as_ptr(), &mut ranges, &mut len)
+        })?;
+
+        Ok(FdbKeyRanges { _f: f, ranges, len })
+    }
+}
+
+impl Deref for FdbKeyRanges {
+    type Target = [FdbKeyRange];
+    fn deref(&self) -> &Self::Target {
+        assert_eq_size!(FdbKeyRange, fdb_sys::FDBKeyRange);
+        assert_eq_align!(FdbKeyRange, fdb_sys::FDBKeyRange);
+        unsafe {
+            &*(std::ptr::slice_from_raw_parts(self.ranges, self.len as usize)
+                as *const [FdbKeyRange])
+        }
+    }
+}
+
+impl AsRef<[FdbKeyRange]> for FdbKeyRanges {
+    fn as_ref(&self) -> &[FdbKeyRange] {
+        self.deref()
+    }
+}
+
+impl<'a> IntoIterator for &'a FdbKeyRanges {
+    type Item = &'a FdbKeyRange;
+    type IntoIter = std::slice::Iter<'a, FdbKeyRange>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.deref().iter()
+    }
+}
+
+#[repr(transparent)]
+/// A key range, owned by a FoundationDB Future
+pub struct FdbKeyRange(fdb_sys::FDBKeyRange);
+
+impl FdbKeyRange {
+    /// retrieves the first key of the range
+    pub fn begin_key(&self) -> &[u8] {
+        unsafe { std::slice::from_raw_parts(self.0.begin_key, self.0.begin_key_length as usize) }
+    }
+
+    /// retrieves the key after the range
+    pub fn end_key(&self) -> &[u8] {
+        unsafe { std::slice::from_raw_parts(self.0.end_key, self.0.end_key_length as usize) }
+    }
+}
+
+impl PartialEq for FdbKeyRange {
+    fn eq(&self, other: &Self) -> bool {
+        (self.begin_key(), self.end_key()) == (other.begin_key(), other.end_key())
+    }
+}
+
+impl Eq for FdbKeyRange {}
+
+impl fmt::Debug for FdbKeyRange {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "({:?}, {:?})",
+            crate::tuple::Bytes::from(self.begin_key()),
+            crate::tuple::Bytes::from(self.end_key())
+        )
+    }
+}
+
+/// The key-value pairs read by `Transaction::read_blob_granules`
+pub struct GranuleValues {
+    result: NonNull<fdb_sys::FDBResult>,
+    keyvalues: *const fdb_sys::FDBKeyValue,
+    len: i32,
+    more: bool,
+}
+unsafe impl Sync for GranuleValues {}
+unsafe impl Send for GranuleValues {}
+
+impl Drop for GranuleValues {
+    fn drop(&mut self) {
+        unsafe { fdb_sys::fdb_result_destroy(self.result.as_ptr()) }
+    }
+}
+
+impl GranuleValues {
+    pub(crate) fn new(result: NonNull<fdb_sys::FDBResult>) -> FdbResult<Self> {
+        let mut values = GranuleValues {
+            result,
+            keyvalues: std::ptr::null(),
+            len: 0,
+            more: false,
+        };
+        let mut more = 0;
+        // on error, the result is destroyed by dropping `values`
+        error::eval(unsafe {
+            fdb_sys::fdb_result_get_keyvalue_array(
+                result.as_ptr(),
+                &mut values.keyvalues,
+                &mut values.len,
+                &mut more,
+            )
+        })?;
+        values.more = more != 0;
+        Ok(values)
+    }
+
+    /// `true` if there is another range after this one
+    pub fn more(&self) -> bool {
+        self.more
+    }
+}
+
+impl Deref for GranuleValues {
+    type Target = [FdbKeyValue];
+    fn deref(&self) -> &Self::Target {
+        assert_eq_size!(FdbKeyValue, fdb_sys::FDBKeyValue);
+        assert_eq_align!(FdbKeyValue, fdb_sys::FDBKeyValue);
+        if self.keyvalues.is_null() {
+            return &[];
+        }
+        unsafe {
+            &*(std::ptr::slice_from_raw_parts(self.keyvalues, self.len as usize)
+                as *const [FdbKeyValue])
+        }
+    }
+}
+
+impl AsRef<[FdbKeyValue]> for GranuleValues {
+    fn as_ref(&self) -> &[FdbKeyValue] {
+        self.deref()
+    }
+}
+
+impl<'a> IntoIterator for &'a GranuleValues {
+    type Item = &'a FdbKeyValue;
+    type IntoIter = std::slice::Iter<'a, FdbKeyValue>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.deref().iter()
+    }
+}
+
+impl Database {
+    /// Purges the blob granules of the range `[begin, end)` at `purge_version`.
+    ///
+    /// The granule files only needed to read the versions before `purge_version` are deleted.
+    /// With `force`, all the granule files of the range are deleted, and the range can no longer
+    /// be read from the blob granules.
+    ///
+    /// Returns the key to give to `Database::wait_purge_granules_complete`, the purge being done
+    /// in the background.
+    pub fn purge_blob_granules(
+        &self,
+        begin: &[u8],
+        end: &[u8],
+        purge_version: i64,
+        force: bool,
+    ) -> impl Future<Output = FdbResult<FdbSlice>> + Send + Sync + Unpin {
+        FdbFuture::<FdbSlice>::new(unsafe {
+            fdb_sys::fdb_database_purge_blob_granules(
+                self.inner.as_ptr(),
+                begin.as_ptr(),
+                fdb_len(begin.len(), "begin"),
+                end.as_ptr(),
+                fdb_len(end.len(), "end"),
+                purge_version,
+                fdb_bool(force),
+            )
+        })
+    }
+
+    /// Waits for the purge started by `Database::purge_blob_granules` to be done.
+    pub fn wait_purge_granules_complete(
+        &self,
+        purge_key: &[u8],
+    ) -> impl Future<Output = FdbResult<()>> + Send + Sync + Unpin {
+        FdbFuture::<()>::new(unsafe {
+            fdb_sys::fdb_database_wait_purge_granules_complete(
+                self.inner.as_ptr(),
+                purge_key.as_ptr(),
+                fdb_len(purge_key.len(), "purge_key"),
+            )
+        })
+    }
+}
+
+/// Reads the parts of the granule files needed by `Transaction::read_blob_granules`
+pub trait GranuleLoader {
+    /// Reads `length` bytes at `offset` of the file `filename`, whose size is `full_file_length`.
+    fn load(
+        &self,
+        filename: &str,
+        offset: i64,
+        length: i64,
+        full_file_length: i64,
+    ) -> io::Result<Vec<u8>>;
+
+    /// Number of granules to load in parallel
+    fn granule_parallelism(&self) -> usize {
+        1
+    }
+}
+
+/// Loads the granule files from a local directory
+#[derive(Clone, Debug)]
+pub struct LocalGranuleLoader {
+    directory: PathBuf,
+    granule_parallelism: usize,
+}
+
+impl LocalGranuleLoader {
+    /// Loads the granule files from the given directory, one granule at a time.
+    pub fn new(directory: impl Into<PathBuf>) -> Self {
+        Self {
+            directory: directory.into(),
+            granule_parallelism: 1,
+        }
+    }
+
+    /// Loads up to `granule_parallelism` granules in parallel.
+    pub fn with_granule_parallelism(mut self, granule_parallelism: usize) -> Self {
+        self.granule_parallelism = granule_parallelism.max(1);
+        self
+    }
+}
+
+impl GranuleLoader for LocalGranuleLoader {
+    fn load(
+        &self,
+        filename: &str,
+        offset: i64,
+        length: i64,
+        _full_file_length: i64,
+    ) -> io::Result<Vec<u8>> {
+        let invalid = |_| io::Error::new(io::ErrorKind::InvalidInput, "invalid granule file part");
+        let mut file = File::open(self.directory.join(filename))?;
+        file.seek(SeekFrom::Start(u64::try_from(offset).map_err(invalid)?))?;
+        let mut data = vec![0; usize::try_from(length).map_err(invalid)?];
+        file.read_exact(&mut data)?;
+        Ok(data)
+    }
+
+    fn granule_parallelism(&self) -> usize {
+        self.granule_parallelism
+    }
+}
+
+/// The state shared with the callbacks of a `FDBReadBlobGranuleContext`
+pub(crate) struct GranuleContext<'a, L: GranuleLoader> {
+    loader: &'a L,
+    loads: Mutex<Loads>,
+}
+
+#[derive(Default)]
+struct Loads {
+    next_id: i64,
+    data: HashMap<i64, Vec<u8>>,
+    /// The first failed load, the read is failed with it
+    error: Option<io::Error>,
+    /// The first panic of the loader, resumed once the read returns
+    panic: Option<Box<dyn Any + Send>>,
+}
+
+impl<'a, L: GranuleLoader> GranuleContext<'a, L> {
+    pub(crate) fn new(loader: &'a L) -> Self {
+        Self {
+            loader,
+            loads: Mutex::new(Loads::default()),
+        }
+    }
+
+    /// The context given to `fdb_transaction_read_blob_granules`, valid while `self` is
+    pub(crate) fn as_raw(&self) -> fdb_sys::FDBReadBlobGranuleContext {
+        fdb_sys::FDBReadBlobGranuleContext {
+            userContext: self as *const Self as *mut c_void,
+            start_load_f: Some(start_load::<L>),
+            get_load_f: Some(get_load::<L>),
+            free_load_f: Some(free_load::<L>),
+            debugNoMaterialize: 0,
+            granuleParallelism: self.loader.granule_parallelism().min(c_int::MAX as usize) as c_int,
+        }
+    }
+
+    /// The error of the first failed load, if any
+    pub(crate) fn take_error(&self) -> Option<io::Error> {
+        self.loads
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .error
+            .take()
+    }
+
+    /// The payload of the first panic of the loader, if any
+    pub(crate) fn take_panic(&self) -> Option<Box<dyn Any + Send>> {
+        self.loads
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .panic
+            .take()
+    }
+}
+
+/// Loads a part of a granule file, without unwinding across the FFI boundary.
+///
+/// A failed load is not given any data, so `get_load` returns null and the client fails the read,
+/// instead of materializing the range from made-up data.
+unsafe extern "C" fn start_load<L: GranuleLoader>(
+    filename: *const c_char,
+    filename_length: c_int,
+    offset: i64,
+    length: i64,
+    full_file_length: i64,
+    context: *mut c_void,
+) -> i64 {
+    let context = &*(context as *const GranuleContext<L>);
+    let filename = std::slice::from_raw_parts(filename as *const u8, filename_length as usize);
+    let loaded = panic::catch_unwind(AssertUnwindSafe(|| match std::str::from_utf8(filename) {
+        Ok(filename) => context
+            .loader
+            .load(filename, offset, length, full_file_length),
+        Err(err) => Err(io::Error::new(io::ErrorKind::InvalidData, err)),
+    }));
+
+    let mut loads = context.loads.lock().unwrap_or_else(|e| e.into_inner());
+    loads.next_id += 1;
+    let id = loads.next_id;
+    match loaded {
+        Ok(Ok(data)) => {
+            loads.data.insert(id, data);
+        }
+        Ok(Err(err)) => {
+            loads.error.get_or_insert(err);
+        }
+        Err(payload) => {
+            loads.panic.get_or_insert(payload);
+        }
+    }
+    id
+}
+
+unsafe extern "C" fn get_load<L: GranuleLoader>(load_id: i64, context: *mut c_void) -> *mut u8 {
+    let context = &*(context as *const GranuleContext<L>);
+    let mut loads = context.loads.lock().unwrap_or_else(|e| e.into_inner());
+    loads
+        .data
+        .get_mut(&load_id)
+        .map_or(std::ptr::null_mut(), |data| data.as_mut_ptr())
+}
+
+unsafe extern "C" fn free_load<L: GranuleLoader>(load_id: i64, context: *mut c_void) {
+    let context = &*(context as *const GranuleContext<L>);
+    let mut loads = context.loads.lock().unwrap_or_else(|e| e.into_inner());
+    loads.data.remove(&load_id);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Numbers;
+
+    impl GranuleLoader for Numbers {
+        fn load(&self, filename: &str, offset: i64, length: i64, _: i64) -> io::Result<Vec<u8>> {
+            if filename != "numbers" {
+                return Err(io::Error::new(io::ErrorKind::NotFound, "no such file"));
+            }
+            Ok((offset..offset + length).map(|i| i as u8).collect())
+        }
+    }
+
+    #[test]
+    fn test_granule_context_callbacks() {
+        let context = GranuleContext::new(&Numbers);
+        let raw = context.as_raw();
+        assert_eq!(raw.granuleParallelism, 1);
+        let (start, get, free) = (
+            raw.start_load_f.unwrap(),
+            raw.get_load_f.unwrap(),
+            raw.free_load_f.unwrap(),
+        );
+
+        unsafe {
+            let name = b"numbers";
+            let id = start(name.as_ptr() as *const c_char, 7, 2, 3, 10, raw.userContext);
+            let data = get(id, raw.userContext);
+            assert_eq!(std::slice::from_raw_parts(data, 3), &[2, 3, 4]);
+            free(id, raw.userContext);
+            assert!(get(id, raw.userContext).is_null());
+            assert!(context.take_error().is_none());
+
+            // a failed load has no data, so the client fails the read
+            let name = b"missing";
+            let id = start(name.as_ptr() as *const c_char, 7, 0, 4, 4, raw.userContext);
+            assert!(get(id, raw.userContext).is_null());
+            free(id, raw.userContext);
+        }
+        assert_eq!(
+            context.take_error().map(|err| err.kind()),
+            Some(io::ErrorKind::NotFound)
+        );
+        assert!(context.take_panic().is_none());
+    }
+
+    struct Panicking;
+
+    impl GranuleLoader for Panicking {
+        fn load(&self, _: &str, _: i64, _: i64, _: i64) -> io::Result<Vec<u8>> {
+            panic!("loader panicked")
+        }
+    }
+
+    #[test]
+    fn test_granule_context_panicking_loader() {
+        let context = GranuleContext::new(&Panicking);
+        let raw = context.as_raw();
+        let (start, get, free) = (
+            raw.start_load_f.unwrap(),
+            raw.get_load_f.unwrap(),
+            raw.free_load_f.unwrap(),
+        );
+
+        // the panic does not unwind out of the callback
+        unsafe {
+            let name = b"numbers";
+            let id = start(name.as_ptr() as *const c_char, 7, 0, 4, 4, raw.userContext);
+            assert!(get(id, raw.userContext).is_null());
+            free(id, raw.userContext);
+        }
+        assert!(context.take_error().is_none());
+        let payload = context.take_panic().expect("the panic to be caught");
+        assert_eq!(payload.downcast_ref::<&str>(), Some(&"loader panicked"));
+    }
+
+    #[test]
+    fn test_local_granule_loader() {
+        let directory = std::env::temp_dir().join(format!("fdb-granules-{}", std::process::id()));
+        std::fs::create_dir_all(&directory).unwrap();
+        std::fs::write(directory.join("snapshot"), b"0123456789").unwrap();
+
+        let loader = LocalGranuleLoader::new(&directory).with_granule_parallelism(4);
+        assert_eq!(loader.granule_parallelism(), 4);
+        assert_eq!(loader.load("snapshot", 3, 4, 10).unwrap(), b"3456");
+        assert!(loader.load("snapshot", 8, 4, 10).is_err());
+        assert!(loader.load("missing", 0, 1, 1).is_err());
+
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+}
@@ -0,0 +1,326 @@
+// Copyright 2018 foundationdb-rs developers, https://github.com/Clikengo/foundationdb-rs/graphs/contributors
+// Copyright 2013-2018 Apple, Inc and the FoundationDB project authors.
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+//! Blocking (synchronous) client API.
+//!
+//! The [`Database`] and [`Transaction`] of this module are thin wrappers around the asynchronous
+//! ones, whose methods block the calling thread on the FDB future with
+//! `fdb_future_block_until_ready` instead of returning it. They are intended for code which does
+//! not run an async executor, like scripts or command line tools.
+//!
+//! The blocking methods must not be called from the network thread, nor from a callback of the
+//! network thread, where they fail with `blocked_from_network_thread`.
+//!
+//! ```no_run
+//! use foundationdb::blocking::Database;
+//!
+//! let network = unsafe { foundationdb::boot() };
+//! let db = Database::default().expect("failed to open the database");
+//! db.run(|trx, _maybe_committed| {
+//!     trx.set(b"hello", b"world");
+//!     Ok(())
+//! })
+//! .expect("failed to set hello");
+//! drop(network);
+//! ```
+
+use crate::future::{FdbSlice, FdbValue, FdbValues, FdbValuesIter};
+use crate::options;
+use crate::{FdbBindingError, FdbError, FdbResult, KeySelector, RangeOption};
+use foundationdb_macros::cfg_api_versions;
+
+/// A blocking handle to a FoundationDB database.
+///
+/// It can be created from an asynchronous [`crate::Database`], both sharing the same connection.
+pub struct Database {
+    inner: crate::Database,
+}
+
+#[cfg_api_versions(min = 610)]
+impl Database {
+    /// Create a database for the given configuration path if any, or the default one.
+    pub fn new(path: Option<&str>) -> FdbResult<Database> {
+        crate::Database::new(path).map(Self::from)
+    }
+
+    /// Create a database for the given configuration path
+    pub fn from_path(path: &str) -> FdbResult<Database> {
+        Self::new(Some(path))
+    }
+
+    /// Create a database for the default configuration path
+    #[allow(clippy::should_implement_trait)]
+    pub fn default() -> FdbResult<Database> {
+        Self::new(None)
+    }
+}
+
+impl Database {
+    /// Called to set an option an on `Database`.
+    pub fn set_option(&self, opt: options::DatabaseOption) -> FdbResult<()> {
+        self.inner.set_option(opt)
+    }
+
+    /// Creates a new transaction on the given database.
+    pub fn create_trx(&self) -> FdbResult<Transaction> {
+        self.inner.create_trx().map(Transaction::from)
+    }
+
+    /// Runs a transactional function against this database with retry logic, blocking until it
+    /// is committed.
+    ///
+    /// The closure is given the transaction and whether a previous attempt may have been
+    /// committed, like `crate::Database::run`. The transaction is committed once the closure
+    /// returns `Ok`, and retryable errors of both the closure and the commit are handled with
+    /// `Transaction::on_error` before calling the closure again.
+    ///
+    /// # Warning: retry
+    ///
+    /// The closure may be called several times, so it must be idempotent or check
+    /// `maybe_committed` to be retried safely.
+    pub fn run<F, T>(&self, mut closure: F) -> Result<T, FdbBindingError>
+    where
+        F: FnMut(&Transaction, bool) -> Result<T, FdbBindingError>,
+    {
+        let trx = self.create_trx()?;
+        let mut maybe_committed = false;
+
+        loop {
+            let result = closure(&trx, maybe_committed).and_then(|value| {
+                trx.commit()?;
+                Ok(value)
+            });
+
+            match result {
+                Ok(value) => return Ok(value),
+                Err(err) => match err.get_fdb_error() {
+                    Some(err) => {
+                        maybe_committed = err.is_maybe_committed();
+                        trx.on_error(err)?;
+                    }
+                    // Otherwise, it cannot be retried
+                    None => return Err(err),
+                },
+            }
+        }
+    }
+
+    /// The asynchronous database this one is wrapping.
+    pub fn as_async(&self) -> &crate::Database {
+        &self.inner
+    }
+}
+
+impl From<crate::Database> for Database {
+    fn from(inner: crate::Database) -> Self {
+        Self { inner }
+    }
+}
+
+/// A blocking transaction.
+///
+/// Unlike the asynchronous [`crate::Transaction`], `commit` and `on_error` borrow the
+/// transaction: after `on_error` succeeds, it is reset and can be used for the next attempt.
+pub struct Transaction {
+    inner: crate::Transaction,
+}
+
+impl Transaction {
+    /// Called to set an option on an FDBTransaction.
+    pub fn set_option(&self, opt: options::TransactionOption) -> FdbResult<()> {
+        self.inner.set_option(opt)
+    }
+
+    /// Modify the database snapshot represented by transaction to change the given
+    /// key to have the given value.
+    ///
+    /// See `crate::Transaction::set`.
+    pub fn set(&self, key: &[u8], value: &[u8]) {
+        self.inner.set(key, value)
+    }
+
+    /// Modify the database snapshot represented by transaction to remove the given key from the
+    /// database.
+    pub fn clear(&self, key: &[u8]) {
+        self.inner.clear(key)
+    }
+
+    /// Modify the database snapshot represented by transaction to remove all keys (if any) which
+    /// are lexicographically greater than or equal to the given begin key and lexicographically
+    /// less than the given end_key.
+    pub fn clear_range(&self, begin: &[u8], end: &[u8]) {
+        self.inner.clear_range(begin, end)
+    }
+
+    /// Modify the database snapshot represented by transaction to perform the operation indicated
+    /// by operationType with operand param to the value stored by the given key.
+    ///
+    /// See `crate::Transaction::atomic_op`.
+    pub fn atomic_op(&self, key: &[u8], param: &[u8], op_type: options::MutationType) {
+        self.inner.atomic_op(key, param, op_type)
+    }
+
+    /// Reads a value from the database snapshot represented by transaction.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - the name of the key to be looked up in the database
+    /// * `snapshot` - `true` if this is a [snapshot read](https://apple.github.io/foundationdb/api-c.html#snapshots)
+    pub fn get(&self, key: &[u8], snapshot: bool) -> FdbResult<Option<FdbSlice>> {
+        self.inner.get_future(key, snapshot).wait()
+    }
+
+    /// Resolves a key selector against the keys in the database snapshot represented by
+    /// transaction.
+    ///
+    /// # Arguments
+    ///
+    /// * `selector`: the key selector
+    /// * `snapshot`: `true` if this is a [snapshot read](https://apple.github.io/foundationdb/api-c.html#snapshots)
+    pub fn get_key(&self, selector: &KeySelector, snapshot: bool) -> FdbResult<FdbSlice> {
+        self.inner.get_key_future(selector, snapshot).wait()
+    }
+
+    /// Reads one batch of the key-value pairs of a range.
+    ///
+    /// See `crate::Transaction::get_range` for the meaning of the arguments, and
+    /// [`Transaction::get_ranges`] to read the whole range.
+    pub fn get_range(
+        &self,
+        opt: &RangeOption,
+        iteration: usize,
+        snapshot: bool,
+    ) -> FdbResult<FdbValues> {
+        self.inner.get_range_future(opt, iteration, snapshot).wait()
+    }
+
+    /// Reads all key-value pairs of a range, batch per batch.
+    ///
+    /// The next batch is only requested once the previous one has been consumed from the
+    /// iterator, which stops after the first error.
+    pub fn get_ranges<'a>(&'a self, opt: RangeOption<'a>, snapshot: bool) -> RangeIter<'a> {
+        RangeIter {
+            trx: self,
+            opt: Some(opt),
+            iteration: 1,
+            snapshot,
+        }
+    }
+
+    /// Reads all key-value pairs of a range, one pair at a time.
+    ///
+    /// This is a flattened [`Transaction::get_ranges`].
+    pub fn get_ranges_keyvalues<'a>(
+        &'a self,
+        opt: RangeOption<'a>,
+        snapshot: bool,
+    ) -> KeyValuesIter<'a> {
+        KeyValuesIter {
+            ranges: self.get_ranges(opt, snapshot),
+            values: None,
+        }
+    }
+
+    /// Gets the snapshot read version of the transaction.
+    ///
+    /// See `crate::Transaction::get_read_version`.
+    pub fn get_read_version(&self) -> FdbResult<i64> {
+        self.inner.get_read_version_future().wait()
+    }
+
+    /// Sets the snapshot read version used by a transaction.
+    ///
+    /// See `crate::Transaction::set_read_version`.
+    pub fn set_read_version(&self, version: i64) {
+        self.inner.set_read_version(version)
+    }
+
+    /// Attempts to commit the sets and clears previously applied to the database snapshot
+    /// represented by transaction to the actual database.
+    ///
+    /// See `crate::Transaction::commit`.
+    pub fn commit(&self) -> FdbResult<()> {
+        self.inner.commit_future().wait()
+    }
+
+    /// Implements the recommended retry and backoff behavior for a transaction, blocking during
+    /// the backoff.
+    ///
+    /// The transaction is reset if the error is retryable, the error is returned otherwise.
+    pub fn on_error(&self, err: FdbError) -> FdbResult<()> {
+        self.inner.on_error_future(err).wait()
+    }
+
+    /// Reset transaction to its initial state.
+    pub fn reset(&mut self) {
+        self.inner.reset()
+    }
+
+    /// The asynchronous transaction this one is wrapping.
+    pub fn as_async(&self) -> &crate::Transaction {
+        &self.inner
+    }
+}
+
+impl From<crate::Transaction> for Transaction {
+    fn from(inner: crate::Transaction) -> Self {
+        Self { inner }
+    }
+}
+
+impl From<Transaction> for crate::Transaction {
+    fn from(trx: Transaction) -> Self {
+        trx.inner
+    }
+}
+
+/// An iterator over the batches of a range read, returned by [`Transaction::get_ranges`].
+pub struct RangeIter<'a> {
+    trx: &'a Transaction,
+    opt: Option<RangeOption<'a>>,
+    iteration: usize,
+    snapshot: bool,
+}
+
+impl<'a> Iterator for RangeIter<'a> {
+    type Item = FdbResult<FdbValues>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let opt = self.opt.take()?;
+        let values = match self.trx.get_range(&opt, self.iteration, self.snapshot) {
+            Ok(values) => values,
+            Err(err) => return Some(Err(err)),
+        };
+        self.iteration += 1;
+        self.opt = opt.next_range(&values);
+        Some(Ok(values))
+    }
+}
+
+/// An iterator over the key-value pairs of a range read, returned by
+/// [`Transaction::get_ranges_keyvalues`].
+pub struct KeyValuesIter<'a> {
+    ranges: RangeIter<'a>,
+    values: Option<FdbValuesIter>,
+}
+
+impl<'a> Iterator for KeyValuesIter<'a> {
+    type Item = FdbResult<FdbValue>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(value) = self.values.as_mut().and_then(Iterator::next) {
+                return Some(Ok(value));
+            }
+            match self.ranges.next()? {
+                Ok(values) => self.values = Some(values.into_iter()),
+                Err(err) => return Some(Err(err)),
+            }
+        }
+    }
+}
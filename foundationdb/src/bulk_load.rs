@@ -0,0 +1,247 @@
+// Copyright 2018 foundationdb-rs developers, https://github.com/Clikengo/foundationdb-rs/graphs/contributors
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+//! Writing a stream of mutations across as many transactions as needed
+//!
+//! A single transaction is limited to 10MB of mutations, and should be kept well under 1MB.
+//! [`Database::bulk_load`] applies the mutations of a stream in batches, committing a batch once
+//! the approximate size of its transaction reaches a target.
+//!
+//! ```no_run
+//! use foundationdb::bulk_load::{BulkLoadOptions, Mutation};
+//! use futures::{stream, StreamExt};
+//!
+//! async fn load(db: &foundationdb::Database) -> Result<(), foundationdb::FdbBindingError> {
+//!     let mutations = stream::iter(0..100_000u32).map(|i| {
+//!         Mutation::Set(format!("key-{:06}", i).into_bytes(), i.to_le_bytes().to_vec())
+//!     });
+//!
+//!     let progress = db
+//!         .bulk_load(mutations, BulkLoadOptions::default(), |progress| {
+//!             // persisting `progress` allows to resume the load with `BulkLoadOptions::resume_from`
+//!             println!("{} mutations committed", progress.mutations);
+//!         })
+//!         .await?;
+//!     assert_eq!(progress.mutations, 100_000);
+//!     Ok(())
+//! }
+//! ```
+
+use std::convert::TryFrom;
+use std::time::{Duration, Instant};
+
+use futures::{Stream, StreamExt};
+
+use crate::options::MutationType;
+use crate::{Database, FdbBindingError, FdbErrorKind, Transaction};
+
+/// A write applied by `Database::bulk_load`
+#[derive(Clone, Debug)]
+pub enum Mutation {
+    /// Sets a key to a value, see `Transaction::set`
+    Set(Vec<u8>, Vec<u8>),
+    /// Clears a key, see `Transaction::clear`
+    Clear(Vec<u8>),
+    /// Clears the keys from begin (included) to end (excluded), see `Transaction::clear_range`
+    ClearRange(Vec<u8>, Vec<u8>),
+    /// Performs an atomic operation on a key, see `Transaction::atomic_op`
+    AtomicOp(Vec<u8>, Vec<u8>, MutationType),
+}
+
+impl Mutation {
+    fn apply(&self, trx: &Transaction) {
+        match self {
+            Mutation::Set(key, value) => trx.set(key, value),
+            Mutation::Clear(key) => trx.clear(key),
+            Mutation::ClearRange(begin, end) => trx.clear_range(begin, end),
+            Mutation::AtomicOp(key, param, op_type) => trx.atomic_op(key, param, *op_type),
+        }
+    }
+
+    /// Number of bytes sent to the database, without the overhead of the mutation
+    fn len(&self) -> usize {
+        match self {
+            Mutation::Set(key, value) => key.len() + value.len(),
+            Mutation::Clear(key) => key.len(),
+            Mutation::ClearRange(begin, end) => begin.len() + end.len(),
+            Mutation::AtomicOp(key, param, _) => key.len() + param.len(),
+        }
+    }
+}
+
+/// A set of options that controls the behavior of `Database::bulk_load`.
+#[derive(Clone, Debug)]
+pub struct BulkLoadOptions {
+    /// A batch is committed once the approximate size of its transaction reaches this number of
+    /// bytes.
+    pub target_bytes: i64,
+    /// The approximate size of the transaction is checked every `size_check_interval` mutations.
+    pub size_check_interval: usize,
+    /// A batch is committed after this number of mutations, whatever its size.
+    pub max_batch_mutations: Option<usize>,
+    /// A batch is committed once it has been built for this long, whatever its size.
+    pub max_batch_duration: Duration,
+    /// A checkpoint returned by a previous load of the same stream. The mutations it committed
+    /// are skipped, and the counters of the returned progress start from it.
+    pub resume_from: Option<BulkLoadProgress>,
+}
+
+impl Default for BulkLoadOptions {
+    fn default() -> Self {
+        Self {
+            target_bytes: 1_000_000,
+            size_check_interval: 64,
+            max_batch_mutations: None,
+            max_batch_duration: Duration::from_secs(2),
+            resume_from: None,
+        }
+    }
+}
+
+/// The progress of `Database::bulk_load`, reported after each committed batch
+///
+/// It is also a checkpoint: a load interrupted by an error can be resumed by giving the last
+/// reported progress to `BulkLoadOptions::resume_from`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct BulkLoadProgress {
+    /// Number of committed transactions
+    pub batches: u64,
+    /// Number of mutations committed, from the start of the stream
+    pub mutations: u64,
+    /// Number of bytes of keys, values and parameters committed
+    pub bytes: u64,
+    /// The version of the last commit, -1 if nothing was committed yet
+    pub committed_version: i64,
+}
+
+impl Database {
+    /// Applies a stream of mutations, committing them across as many transactions as needed.
+    ///
+    /// The mutations are applied in order, a batch is committed once the approximate size of its
+    /// transaction reaches `BulkLoadOptions::target_bytes`, or when one of the other limits of
+    /// `options` is reached. A batch failing to commit is retried using `on_error`, a batch too
+    /// large for a transaction is split in halves. `on_progress` is called after each committed
+    /// batch.
+    ///
+    /// # Warning
+    ///
+    /// The mutations of a batch are applied again when its commit result is unknown, see
+    /// `FdbError::is_maybe_committed`. Atomic operations which are not idempotent may then be
+    /// applied twice.
+    ///
+    /// A load is not atomic, on error the mutations of the previously reported progress are
+    /// committed, and those of the failing batch are not.
+    pub async fn bulk_load<S, P>(
+        &self,
+        mutations: S,
+        options: BulkLoadOptions,
+        mut on_progress: P,
+    ) -> Result<BulkLoadProgress, FdbBindingError>
+    where
+        S: Stream<Item = Mutation>,
+        P: FnMut(&BulkLoadProgress),
+    {
+        let mut progress = options.resume_from.clone().unwrap_or(BulkLoadProgress {
+            committed_version: -1,
+            ..BulkLoadProgress::default()
+        });
+        let skipped = usize::try_from(progress.mutations).unwrap_or(usize::MAX);
+        let mutations = mutations.skip(skipped);
+        futures::pin_mut!(mutations);
+
+        let size_check_interval = options.size_check_interval.max(1);
+        let mut trx = self.create_trx()?;
+        let mut batch: Vec<Mutation> = Vec::new();
+        let mut batch_start = Instant::now();
+
+        loop {
+            let mutation = mutations.next().await;
+            let is_last = mutation.is_none();
+            if let Some(mutation) = mutation {
+                if batch.is_empty() {
+                    batch_start = Instant::now();
+                }
+                mutation.apply(&trx);
+                batch.push(mutation);
+            }
+
+            let is_full = is_last
+                || options
+                    .max_batch_mutations
+                    .map_or(false, |max| batch.len() >= max)
+                || batch_start.elapsed() >= options.max_batch_duration
+                || (batch.len() % size_check_interval == 0
+                    && match trx.get_approximate_size().await {
+                        Ok(size) => size >= options.target_bytes,
+                        // the error is also returned by the commit, which handles it
+                        Err(_) => true,
+                    });
+
+            if is_full && !batch.is_empty() {
+                trx = commit_batch(trx, &batch, &mut progress, &mut on_progress).await?;
+                batch.clear();
+            }
+
+            if is_last {
+                return Ok(progress);
+            }
+        }
+    }
+}
+
+/// Commits a batch, applying it again on a reset transaction until it succeeds.
+///
+/// A batch exceeding the size limit of a transaction is split in halves, which are committed one
+/// after the other. `progress` is updated and reported after each commit.
+///
+/// Returns the transaction, reset.
+async fn commit_batch<P>(
+    mut trx: Transaction,
+    batch: &[Mutation],
+    progress: &mut BulkLoadProgress,
+    on_progress: &mut P,
+) -> Result<Transaction, FdbBindingError>
+where
+    P: FnMut(&BulkLoadProgress),
+{
+    // the parts of the batch left to commit, in reverse order
+    let mut parts = vec![batch];
+    // the last part is already applied to the transaction
+    let mut applied = true;
+    while let Some(&part) = parts.last() {
+        if !applied {
+            for mutation in part {
+                mutation.apply(&trx);
+            }
+        }
+        match trx.commit().await {
+            Ok(committed) => {
+                let version = committed.committed_version()?;
+                trx = committed.reset();
+                parts.pop();
+
+                progress.batches += 1;
+                progress.mutations += part.len() as u64;
+                progress.bytes += part.iter().map(|m| m.len() as u64).sum::<u64>();
+                progress.committed_version = version;
+                on_progress(progress);
+            }
+            Err(err) if err.kind() == FdbErrorKind::TransactionTooLarge && part.len() > 1 => {
+                trx = err.reset();
+                let (first, second) = part.split_at(part.len() / 2);
+                parts.pop();
+                parts.push(second);
+                parts.push(first);
+            }
+            Err(err) => {
+                trx = err.on_error().await?;
+            }
+        }
+        applied = false;
+    }
+    Ok(trx)
+}
@@ -11,21 +11,39 @@
 //! <https://apple.github.io/foundationdb/api-c.html#database>
 
 use std::convert::TryInto;
+#[cfg_api_versions(min = 700)]
+use std::fmt;
 use std::marker::PhantomData;
+use std::ops::Range;
 use std::pin::Pin;
 use std::ptr::NonNull;
+use std::sync::Arc;
 use std::time::{Duration, Instant};
 
 use foundationdb_macros::cfg_api_versions;
 use foundationdb_sys as fdb_sys;
 
+#[cfg_api_versions(min = 700)]
+use crate::future::FdbFuture;
+use crate::future::{FdbValue, FdbValues};
 use crate::options;
 use crate::transaction::*;
-use crate::{error, FdbError, FdbResult};
+use crate::{error, FdbError, FdbErrorKind, FdbResult};
 
 use crate::error::FdbBindingError;
+use crate::idempotency::{Idempotency, TransactionId};
+use crate::retry::{MaybeCommittedPolicy, Reconcile, Retrier, RetryPolicy};
+use crate::timer::delay;
+use crate::trace::Span;
+use crate::tuple::Versionstamp;
 use futures::prelude::*;
 
+const WATCH_MIN_BACKOFF: Duration = Duration::from_millis(10);
+const WATCH_MAX_BACKOFF: Duration = Duration::from_secs(1);
+
+/// A watch set by `Database::watch_stream`
+type Watch = future::BoxFuture<'static, FdbResult<()>>;
+
 /// Represents a FoundationDB database
 ///
 /// A mutable, lexicographically ordered mapping from binary keys to binary values.
@@ -239,6 +257,7 @@ impl Database {
     /// It might retry indefinitely if the transaction is highly contentious. It is recommended to
     /// set [`options::TransactionOption::RetryLimit`] or [`options::TransactionOption::Timeout`] on the transaction
     /// if the task need to be guaranteed to finish. These options can be safely set on every iteration of the closure.
+    /// `Database::run_with_policy` bounds the retries without transaction options.
     ///
     /// # Warning: Maybe committed transactions
     ///
@@ -253,77 +272,913 @@ impl Database {
         F: Fn(RetryableTransaction, bool) -> Fut,
         Fut: Future<Output = Result<T, FdbBindingError>>,
     {
-        let mut maybe_committed_transaction = false;
         // we just need to create the transaction once,
         // in case there is a error, it will be reset automatically
-        let mut transaction = self.create_retryable_trx()?;
+        run_retryable(self.create_retryable_trx()?, closure).await
+    }
+
+    /// Runs a transactional function like `Database::run`, retrying the failed attempts as
+    /// configured by `policy`.
+    ///
+    /// The policy can bound the attempts and the time spent retrying, add a jittered backoff to
+    /// the one of FoundationDB, retry custom errors, choose how to handle the commits which may
+    /// have succeeded and observe each attempt. When the policy gives up, the error of the last
+    /// attempt is returned.
+    pub async fn run_with_policy<F, Fut, T>(
+        &self,
+        policy: &RetryPolicy<'_>,
+        closure: F,
+    ) -> Result<T, FdbBindingError>
+    where
+        F: Fn(RetryableTransaction, bool) -> Fut,
+        Fut: Future<Output = Result<T, FdbBindingError>>,
+    {
+        let options = RunOptions {
+            policy,
+            on_conflict: None,
+            versionstamp: false,
+        };
+        let outcome = run_retryable_with(self.create_retryable_trx()?, closure, options).await?;
+        Ok(outcome.value)
+    }
+
+    /// Runs a transactional function like `Database::run`, retrying the attempts whose commit
+    /// result is unknown only if they did not commit.
+    ///
+    /// Each attempt writes a unique transaction id in the subspace of `idempotency`, which is
+    /// read after a `commit_unknown_result` error: if the previous attempt committed, its value is
+    /// returned without running the closure again. See the [`crate::idempotency`] module.
+    pub async fn run_idempotent<F, Fut, T>(
+        &self,
+        idempotency: &Idempotency,
+        closure: F,
+    ) -> Result<T, FdbBindingError>
+    where
+        F: Fn(RetryableTransaction, bool) -> Fut,
+        Fut: Future<Output = Result<T, FdbBindingError>>,
+    {
+        let policy = RetryPolicy::new()
+            .with_maybe_committed(MaybeCommittedPolicy::Idempotent(idempotency.clone()));
+        self.run_with_policy(&policy, closure).await
+    }
+
+    /// Runs a transactional function like `Database::run`, returning the metadata of the commit
+    /// along with the value of the closure.
+    ///
+    /// The outcome holds the committed version and the versionstamp of the transaction, which are
+    /// lost by `Database::run`, as well as the number of attempts and the time spent.
+    pub async fn run_with_outcome<F, Fut, T>(
+        &self,
+        closure: F,
+    ) -> Result<RunOutcome<T>, FdbBindingError>
+    where
+        F: Fn(RetryableTransaction, bool) -> Fut,
+        Fut: Future<Output = Result<T, FdbBindingError>>,
+    {
+        let policy = RetryPolicy::default();
+        let options = RunOptions {
+            policy: &policy,
+            on_conflict: None,
+            versionstamp: true,
+        };
+        run_retryable_with(self.create_retryable_trx()?, closure, options).await
+    }
+
+    /// Runs a transactional function like `Database::run`, reporting the key ranges which
+    /// conflicted with other transactions.
+    ///
+    /// `TransactionOption::ReportConflictingKeys` is set on every attempt, and when the commit
+    /// fails with `not_committed`, `on_conflict` is called with the conflicting key ranges before
+    /// retrying. This finds the hot keys of contentious transactions, at the cost of reading the
+    /// ranges after each conflict.
+    #[cfg_api_versions(min = 630)]
+    pub async fn run_reporting_conflicts<F, Fut, T, H>(
+        &self,
+        closure: F,
+        on_conflict: H,
+    ) -> Result<T, FdbBindingError>
+    where
+        F: Fn(RetryableTransaction, bool) -> Fut,
+        Fut: Future<Output = Result<T, FdbBindingError>>,
+        H: Fn(&[Range<Vec<u8>>]) + Send + Sync,
+    {
+        let policy = RetryPolicy::default();
+        let options = RunOptions {
+            policy: &policy,
+            on_conflict: Some(&on_conflict),
+            versionstamp: false,
+        };
+        let outcome = run_retryable_with(self.create_retryable_trx()?, closure, options).await?;
+        Ok(outcome.value)
+    }
+
+    /// Perform a no-op against FDB to check network thread liveness. This operation will not change the underlying data
+    /// in any way, nor will it perform any I/O against the FDB cluster. However, it will schedule some amount of work
+    /// onto the FDB client and wait for it to complete. The FoundationDB client operates by scheduling onto an event
+    /// queue that is then processed by a single thread (the "network thread"). This method can be used to determine if
+    /// the network thread has entered a state where it is no longer processing requests or if its time to process
+    /// requests has increased. If the network thread is busy, this operation may take some amount of time to complete,
+    /// which is why this operation returns a future.
+    pub async fn perform_no_op(&self) -> FdbResult<()> {
+        let trx = self.create_trx()?;
+
+        // Set the read version of the transaction, then read it back. This requires no I/O, but it does
+        // require the network thread be running. The exact value used for the read version is unimportant.
+        trx.set_read_version(42);
+        trx.get_read_version().await?;
+        Ok(())
+    }
+
+    /// Reboots the worker process listening at `address`, such as `"127.0.0.1:4500"`.
+    ///
+    /// With `check`, the worker is only rebooted if its data is durable, and with a non-zero
+    /// `duration`, it is suspended for `duration` seconds before restarting.
+    ///
+    /// Returns `false` if the worker could not be found or rebooted.
+    #[cfg_api_versions(min = 700)]
+    pub fn reboot_worker(
+        &self,
+        address: &str,
+        check: bool,
+        duration: i32,
+    ) -> impl Future<Output = FdbResult<bool>> + Send + Sync + Unpin {
+        FdbFuture::<i64>::new(unsafe {
+            fdb_sys::fdb_database_reboot_worker(
+                self.inner.as_ptr(),
+                address.as_ptr(),
+                fdb_len(address.len(), "address"),
+                fdb_bool(check),
+                duration,
+            )
+        })
+        .map_ok(|rebooted| rebooted != 0)
+    }
+
+    /// Forces a recovery of the cluster, the data center `dcid` becoming the primary one.
+    ///
+    /// The mutations which were not replicated to `dcid` are lost, this is meant to recover a
+    /// cluster whose primary data center is lost.
+    #[cfg_api_versions(min = 700)]
+    pub fn force_recovery_with_data_loss(
+        &self,
+        dcid: &[u8],
+    ) -> impl Future<Output = FdbResult<()>> + Send + Sync + Unpin {
+        FdbFuture::<()>::new(unsafe {
+            fdb_sys::fdb_database_force_recovery_with_data_loss(
+                self.inner.as_ptr(),
+                dcid.as_ptr(),
+                fdb_len(dcid.len(), "dcid"),
+            )
+        })
+    }
+
+    /// Takes a snapshot of the cluster, running `snap_command` on every process.
+    ///
+    /// `uid` identifies the snapshot, as 32 hexadecimal digits. The cluster must be configured
+    /// with the whitelisted binary paths of `snap_command`, see the
+    /// [relevant documentation](https://apple.github.io/foundationdb/disk-snapshot-backup.html).
+    #[cfg_api_versions(min = 700)]
+    pub fn create_snapshot(
+        &self,
+        uid: &[u8],
+        snap_command: &[u8],
+    ) -> impl Future<Output = FdbResult<()>> + Send + Sync + Unpin {
+        FdbFuture::<()>::new(unsafe {
+            fdb_sys::fdb_database_create_snapshot(
+                self.inner.as_ptr(),
+                uid.as_ptr(),
+                fdb_len(uid.len(), "uid"),
+                snap_command.as_ptr(),
+                fdb_len(snap_command.len(), "snap_command"),
+            )
+        })
+    }
 
+    /// Returns the protocol version of the cluster.
+    ///
+    /// With `expected_version`, the future is only ready once the protocol version of the cluster
+    /// differs from it, which can be used to wait for an upgrade.
+    #[cfg_api_versions(min = 700)]
+    pub fn get_server_protocol(
+        &self,
+        expected_version: Option<ProtocolVersion>,
+    ) -> impl Future<Output = FdbResult<ProtocolVersion>> + Send + Sync + Unpin {
+        FdbFuture::<u64>::new(unsafe {
+            fdb_sys::fdb_database_get_server_protocol(
+                self.inner.as_ptr(),
+                expected_version.map_or(0, ProtocolVersion::as_u64),
+            )
+        })
+        .map_ok(ProtocolVersion)
+    }
+
+    /// Returns how busy the network thread of the client is, between 0 (idle) and 1 (busy all the
+    /// time), averaged over the last seconds.
+    #[cfg_api_versions(min = 700)]
+    pub fn get_main_thread_busyness(&self) -> f64 {
+        unsafe { fdb_sys::fdb_database_get_main_thread_busyness(self.inner.as_ptr()) }
+    }
+
+    /// Reads a range across as many transactions as needed, using snapshot reads.
+    ///
+    /// Unlike `Transaction::get_ranges`, which fails once its transaction is older than 5
+    /// seconds, the range is read in successive transactions: when a read fails with a retryable
+    /// error, like `transaction_too_old`, a new transaction resumes the read after the last key
+    /// returned.
+    ///
+    /// With `pin_read_version`, every transaction reads at the read version of the first one while
+    /// this version is still valid, so the result is a consistent snapshot unless
+    /// `transaction_too_old` is hit. Once it is, the read continues at a newer version, which is
+    /// pinned in turn.
+    ///
+    /// # Arguments
+    ///
+    /// * `opt`: the range, limit, target_bytes and mode
+    /// * `pin_read_version`: `true` to keep the read version when a transaction is retried
+    pub fn scan_ranges<'a>(
+        &'a self,
+        opt: RangeOption<'a>,
+        pin_read_version: bool,
+    ) -> impl Stream<Item = FdbResult<FdbValues>> + Send + 'a {
+        self.scan_ranges_with(opt, pin_read_version, |trx, opt, iteration| {
+            trx.get_range(opt, iteration, true)
+        })
+    }
+
+    /// Reads a range across as many transactions as needed, like `Database::scan_ranges`, using
+    /// `read` to read each batch of the range.
+    ///
+    /// `read` is given the transaction, the range option and the iteration of the batch, for
+    /// instance to set options on the transaction before reading. An error it returns is handled
+    /// like an error of `Transaction::get_range`.
+    pub fn scan_ranges_with<'a, R, Fut>(
+        &'a self,
+        opt: RangeOption<'a>,
+        pin_read_version: bool,
+        read: R,
+    ) -> impl Stream<Item = FdbResult<FdbValues>> + Send + 'a
+    where
+        R: Fn(&Transaction, &RangeOption, usize) -> Fut + Send + Sync + 'a,
+        Fut: Future<Output = FdbResult<FdbValues>> + Send,
+    {
+        let read = Arc::new(read);
+        stream::unfold(Some((None, opt, 1, None)), move |state| {
+            let read = read.clone();
+            async move {
+                let (trx, opt, iteration, mut read_version): (
+                    Option<Transaction>,
+                    RangeOption,
+                    usize,
+                    Option<i64>,
+                ) = state?;
+                let mut trx = match trx {
+                    Some(trx) => trx,
+                    None => match self.create_trx() {
+                        Ok(trx) => trx,
+                        Err(err) => return Some((Err(err), None)),
+                    },
+                };
+
+                loop {
+                    let result = async {
+                        if pin_read_version && read_version.is_none() {
+                            read_version = Some(trx.get_read_version().await?);
+                        }
+                        read(&trx, &opt, iteration).await
+                    }
+                    .await;
+
+                    match result {
+                        Ok(values) => {
+                            let state = opt
+                                .clone()
+                                .next_range(&values)
+                                .map(|next_opt| (Some(trx), next_opt, iteration + 1, read_version));
+                            return Some((Ok(values), state));
+                        }
+                        // the range option is after the last key read, the read resumes from
+                        // there with the reset transaction, at the pinned version unless it is
+                        // too old
+                        Err(err) => {
+                            let is_too_old = err.kind() == FdbErrorKind::TransactionTooOld;
+                            match trx.on_error(err).await {
+                                Ok(reset) => trx = reset,
+                                Err(err) => return Some((Err(err), None)),
+                            }
+                            match read_version {
+                                Some(_) if is_too_old => read_version = None,
+                                Some(version) => trx.set_read_version(version),
+                                None => {}
+                            }
+                        }
+                    }
+                }
+            }
+        })
+    }
+
+    /// Reads a range across as many transactions as needed, see `Database::scan_ranges`.
+    ///
+    /// Returns a stream of KeyValue.
+    pub fn scan_ranges_keyvalues<'a>(
+        &'a self,
+        opt: RangeOption<'a>,
+        pin_read_version: bool,
+    ) -> impl Stream<Item = FdbResult<FdbValue>> + Send + 'a {
+        self.scan_ranges(opt, pin_read_version)
+            .map_ok(|values| stream::iter(values.into_iter().map(Ok)))
+            .try_flatten()
+    }
+
+    /// Streams the value of a key each time it changes, starting with its current value.
+    ///
+    /// A watch is set on the key and set again each time it fires, or fails with a retryable
+    /// error. As a watch may fire without the value having changed, and several changes may be
+    /// reported by a single watch, a value is only returned when it differs from the previous one:
+    /// intermediate values may be missed, but the last value is always returned.
+    ///
+    /// When the `too_many_watches` limit is reached, the watch is set again after an increasing
+    /// delay. The stream ends after returning any other error.
+    ///
+    /// Dropping the stream cancels its pending watch.
+    pub fn watch_stream<'a>(
+        &'a self,
+        key: &'a [u8],
+    ) -> impl Stream<Item = FdbResult<Option<Vec<u8>>>> + Send + 'a {
+        stream::unfold(
+            Some((None, None, WATCH_MIN_BACKOFF)),
+            move |state| async move {
+                let (mut last, mut watch, mut backoff): (
+                    Option<Option<Vec<u8>>>,
+                    Option<Watch>,
+                    Duration,
+                ) = state?;
+
+                loop {
+                    if let Some(watch) = watch.take() {
+                        match watch.await {
+                            Ok(()) => backoff = WATCH_MIN_BACKOFF,
+                            Err(err) if err.kind() == FdbErrorKind::TooManyWatches => {
+                                delay(backoff).await;
+                                backoff = (backoff * 2).min(WATCH_MAX_BACKOFF);
+                            }
+                            Err(err) if err.is_retryable() => {}
+                            Err(err) => return Some((Err(err), None)),
+                        }
+                    }
+
+                    match self.read_and_watch(key).await {
+                        Ok((value, next_watch)) => {
+                            watch = Some(next_watch);
+                            if last.as_ref() != Some(&value) {
+                                last = Some(value.clone());
+                                return Some((Ok(value), Some((last, watch, backoff))));
+                            }
+                        }
+                        Err(err) if err.kind() == FdbErrorKind::TooManyWatches => {
+                            delay(backoff).await;
+                            backoff = (backoff * 2).min(WATCH_MAX_BACKOFF);
+                        }
+                        Err(err) => return Some((Err(err), None)),
+                    }
+                }
+            },
+        )
+    }
+
+    /// Reads the value of a key and sets a watch on it, in a committed transaction
+    async fn read_and_watch(&self, key: &[u8]) -> FdbResult<(Option<Vec<u8>>, Watch)> {
+        let mut trx = self.create_trx()?;
         loop {
-            // executing the closure
-            let result_closure = closure(transaction.clone(), maybe_committed_transaction).await;
+            let value = match trx.get(key, false).await {
+                Ok(value) => value.map(|value| value.to_vec()),
+                Err(err) => {
+                    trx = trx.on_error(err).await?;
+                    continue;
+                }
+            };
+            let watch = trx.watch(key).boxed();
+            match trx.commit().await {
+                Ok(_) => return Ok((value, watch)),
+                Err(err) => trx = err.on_error().await?,
+            }
+        }
+    }
+
+    /// Reads a range with several concurrent transactions.
+    ///
+    /// The range is split with `Transaction::get_range_split_points` in shards of about
+    /// `chunk_bytes` bytes, and up to `concurrency` shards are read at the same time, each one in
+    /// its own transaction using snapshot reads.
+    ///
+    /// The chunks of a shard are returned in order, but the chunks of different shards are
+    /// interleaved. A shard whose transaction fails with a retryable error, like
+    /// `transaction_too_old`, is resumed after the last key read with a new read version: the
+    /// result is not a consistent snapshot of the range.
+    #[cfg_api_versions(min = 700)]
+    pub fn parallel_scan(
+        &self,
+        range: Range<Vec<u8>>,
+        chunk_bytes: i64,
+        concurrency: usize,
+    ) -> impl Stream<Item = FdbResult<FdbValues>> + Send + '_ {
+        stream::once(self.split_range(range, chunk_bytes))
+            .map_ok(move |shards| self.scan_shards(shards, concurrency.max(1)))
+            .try_flatten()
+    }
+
+    /// Splits a range in shards of about `chunk_bytes` bytes
+    #[cfg_api_versions(min = 700)]
+    async fn split_range(
+        &self,
+        range: Range<Vec<u8>>,
+        chunk_bytes: i64,
+    ) -> FdbResult<Vec<Range<Vec<u8>>>> {
+        let mut trx = self.create_trx()?;
+        let split_points = loop {
+            match trx
+                .get_range_split_points(&range.start, &range.end, chunk_bytes)
+                .await
+            {
+                Ok(split_points) => break split_points,
+                Err(err) => trx = trx.on_error(err).await?,
+            }
+        };
+
+        // the split points contain the begin and end keys of the range
+        let shards: Vec<Range<Vec<u8>>> = split_points
+            .windows(2)
+            .map(|points| points[0].key().to_vec()..points[1].key().to_vec())
+            .collect();
+        if shards.is_empty() {
+            Ok(vec![range])
+        } else {
+            Ok(shards)
+        }
+    }
 
-            if let Err(e) = result_closure {
+    /// Reads the shards, with at most `concurrency` of them at the same time
+    #[cfg_api_versions(min = 700)]
+    fn scan_shards(
+        &self,
+        shards: Vec<Range<Vec<u8>>>,
+        concurrency: usize,
+    ) -> impl Stream<Item = FdbResult<FdbValues>> + Send + '_ {
+        let mut shards = shards.into_iter();
+        let mut active = stream::SelectAll::new();
+        for shard in shards.by_ref().take(concurrency) {
+            active.push(self.scan_shard(shard));
+        }
+
+        stream::unfold(
+            (shards, active),
+            move |(mut shards, mut active)| async move {
+                loop {
+                    // `None` marks the end of a shard, another one can be started
+                    match active.next().await? {
+                        Some(values) => return Some((values, (shards, active))),
+                        None => {
+                            if let Some(shard) = shards.next() {
+                                active.push(self.scan_shard(shard));
+                            }
+                        }
+                    }
+                }
+            },
+        )
+    }
+
+    /// Reads a shard in its own transaction, followed by a `None` marking its end
+    #[cfg_api_versions(min = 700)]
+    fn scan_shard(
+        &self,
+        shard: Range<Vec<u8>>,
+    ) -> stream::BoxStream<'_, Option<FdbResult<FdbValues>>> {
+        self.scan_ranges(RangeOption::from(shard), false)
+            .map(Some)
+            .chain(stream::once(future::ready(None)))
+            .boxed()
+    }
+}
+
+/// The version of the protocol spoken by a FoundationDB cluster
+///
+/// Clients and servers can only talk to each other if their protocol versions are compatible, as
+/// the ones of the patch releases of a minor version.
+#[cfg_api_versions(min = 700)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ProtocolVersion(u64);
+
+#[cfg_api_versions(min = 700)]
+impl ProtocolVersion {
+    /// The flag of the object serializer, set on the protocol versions since 6.3
+    const OBJECT_SERIALIZER_FLAG: u64 = 0x1000_0000_0000_0000;
+    /// The bits which must be equal for two protocol versions to be compatible
+    const COMPATIBLE_MASK: u64 = 0xffff_ffff_ffff_0000;
+
+    /// The raw value of the protocol version
+    pub fn as_u64(self) -> u64 {
+        self.0
+    }
+
+    /// The protocol version without the object serializer flag
+    pub fn version(self) -> u64 {
+        self.0 & !Self::OBJECT_SERIALIZER_FLAG
+    }
+
+    /// The release of FoundationDB speaking the protocol version, as `(major, minor)`
+    pub fn release(self) -> (u8, u8) {
+        let release = (self.0 >> 24) as u8;
+        (release >> 4, release & 0xf)
+    }
+
+    /// `true` if a client and a server speaking these protocol versions can talk to each other
+    pub fn is_compatible(self, other: ProtocolVersion) -> bool {
+        self.version() & Self::COMPATIBLE_MASK == other.version() & Self::COMPATIBLE_MASK
+    }
+}
+
+#[cfg_api_versions(min = 700)]
+impl fmt::Display for ProtocolVersion {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:x}", self.version())
+    }
+}
+
+#[cfg_api_versions(min = 700)]
+impl From<u64> for ProtocolVersion {
+    fn from(version: u64) -> Self {
+        ProtocolVersion(version)
+    }
+}
+
+/// The result of `Database::run_with_outcome`: the value returned by the transactional function,
+/// with the metadata of its commit.
+#[derive(Debug, Clone)]
+pub struct RunOutcome<T> {
+    /// The value returned by the transactional function
+    pub value: T,
+    /// The version at which the transaction committed, -1 if it was read-only.
+    ///
+    /// It is `None` if the result of the commit was unknown, and the attempt was found to be
+    /// committed by `MaybeCommittedPolicy::Reconcile`. With `MaybeCommittedPolicy::Idempotent`,
+    /// it is read from the transaction id.
+    pub committed_version: Option<i64>,
+    /// The versionstamp of the transaction, with a user version of 0.
+    ///
+    /// It is `None` if the transaction was read-only, or if the version is unknown like
+    /// `committed_version`.
+    pub versionstamp: Option<Versionstamp>,
+    /// The number of attempts, starting at 1
+    pub attempts: u32,
+    /// The time spent running the attempts
+    pub elapsed: Duration,
+}
+
+/// Called with the key ranges of a conflict by `Database::run_reporting_conflicts`
+type ConflictHook<'a> = dyn Fn(&[Range<Vec<u8>>]) + Send + Sync + 'a;
+
+/// The options of the retry loop of `Database::run` and its variants
+struct RunOptions<'r, 'a> {
+    /// How the failed attempts are retried
+    policy: &'r RetryPolicy<'a>,
+    /// Called with the conflicting keys of the failed commits, if any
+    on_conflict: Option<&'r ConflictHook<'r>>,
+    /// Whether the versionstamp of the committed transaction is read
+    versionstamp: bool,
+}
+
+/// The retry loop of `Database::run`, starting with the given transaction
+pub(crate) async fn run_retryable<F, Fut, T>(
+    transaction: RetryableTransaction,
+    closure: F,
+) -> Result<T, FdbBindingError>
+where
+    F: Fn(RetryableTransaction, bool) -> Fut,
+    Fut: Future<Output = Result<T, FdbBindingError>>,
+{
+    let policy = RetryPolicy::default();
+    let options = RunOptions {
+        policy: &policy,
+        on_conflict: None,
+        versionstamp: false,
+    };
+    let outcome = run_retryable_with(transaction, closure, options).await?;
+    Ok(outcome.value)
+}
+
+/// The retry loop of `Database::run`, following `options.policy` and reporting the conflicts to
+/// `options.on_conflict` if any
+async fn run_retryable_with<F, Fut, T>(
+    transaction: RetryableTransaction,
+    closure: F,
+    options: RunOptions<'_, '_>,
+) -> Result<RunOutcome<T>, FdbBindingError>
+where
+    F: Fn(RetryableTransaction, bool) -> Fut,
+    Fut: Future<Output = Result<T, FdbBindingError>>,
+{
+    let span = Span::run();
+    let outcome = span
+        .instrument(run_attempts(transaction, closure, options, &span))
+        .await;
+    span.record_outcome(&outcome);
+    outcome
+}
+
+/// The attempts of `run_retryable_with`, each one in a child span of `span`
+async fn run_attempts<F, Fut, T>(
+    mut transaction: RetryableTransaction,
+    closure: F,
+    options: RunOptions<'_, '_>,
+    span: &Span,
+) -> Result<RunOutcome<T>, FdbBindingError>
+where
+    F: Fn(RetryableTransaction, bool) -> Fut,
+    Fut: Future<Output = Result<T, FdbBindingError>>,
+{
+    let mut retrier = Retrier::new(options.policy);
+    let mut maybe_committed_transaction = false;
+    let transaction_id = retrier.idempotency().map(Idempotency::new_transaction_id);
+
+    loop {
+        retrier.begin_attempt();
+        let attempt_span = span.attempt(retrier.attempts(), maybe_committed_transaction);
+        if options.on_conflict.is_some() {
+            report_conflicting_keys(&transaction)?;
+        }
+        #[cfg(feature = "tracing")]
+        propagate_trace_id(&transaction, &retrier, &attempt_span)?;
+
+        // executing the closure
+        let attempt = closure(transaction.clone(), maybe_committed_transaction);
+        let value = match attempt_span.instrument(attempt).await {
+            Ok(value) => value,
+            Err(e) => {
                 // checks if it is an FdbError
-                if let Some(e) = e.get_fdb_error() {
-                    maybe_committed_transaction = e.is_maybe_committed();
+                if let Some(fdb_error) = e.get_fdb_error() {
+                    if fdb_error.is_maybe_committed() && retrier.fails_maybe_committed() {
+                        retrier.notify_failure(&e, false);
+                        return Err(e);
+                    }
+                    maybe_committed_transaction = fdb_error.is_maybe_committed();
                     // The closure returned an Error,
-                    match transaction.on_error(e).await {
+                    // The only FdbBindingError that can be thrown here is `ReferenceToTransactionKept`
+                    match attempt_span
+                        .instrument(transaction.on_error(fdb_error))
+                        .await?
+                    {
                         // we can retry the error
-                        Ok(Ok(t)) => {
+                        Ok(t) if retrier.within_limits() => {
+                            retrier.notify_failure(&e, true);
                             transaction = t;
+                            backoff(&retrier).await;
                             continue;
                         }
-                        Ok(Err(non_retryable_error)) => {
-                            return Err(FdbBindingError::from(non_retryable_error))
+                        Ok(_) => {
+                            retrier.notify_failure(&e, false);
+                            return Err(e);
+                        }
+                        Err(non_retryable_error) => {
+                            retrier.notify_failure(&e, false);
+                            return Err(FdbBindingError::from(non_retryable_error));
                         }
-                        // The only FdbBindingError that can be thrown here is `ReferenceToTransactionKept`
-                        Err(non_retryable_error) => return Err(non_retryable_error),
                     }
                 }
+                if retrier.retries_custom_error(&e) && retrier.within_limits() {
+                    retrier.notify_failure(&e, true);
+                    transaction = transaction.reset()?;
+                    backoff(&retrier).await;
+                    continue;
+                }
                 // Otherwise, it cannot be retried
+                retrier.notify_failure(&e, false);
                 return Err(e);
             }
+        };
 
-            let commit_result = transaction.commit().await;
-
-            match commit_result {
-                // The only FdbBindingError that can be thrown here is `ReferenceToTransactionKept`
-                Err(err) => return Err(err),
-                Ok(Ok(_)) => return result_closure,
-                Ok(Err(transaction_commit_error)) => {
-                    maybe_committed_transaction = transaction_commit_error.is_maybe_committed();
-                    // we have an error during commit, checking if it is a retryable error
-                    match transaction_commit_error.on_error().await {
-                        Ok(t) => {
-                            transaction = RetryableTransaction::new(t);
-                            continue;
-                        }
-                        Err(non_retryable_error) => {
-                            return Err(FdbBindingError::from(non_retryable_error))
-                        }
+        if let Some(transaction_id) = &transaction_id {
+            transaction_id.mark(&transaction);
+        }
+
+        // the versionstamp is only available once the commit succeeded
+        let versionstamp = if options.versionstamp {
+            Some(transaction.get_versionstamp())
+        } else {
+            None
+        };
+        let commit_result = attempt_span.instrument(transaction.commit()).await;
+
+        match commit_result {
+            // The only FdbBindingError that can be thrown here is `ReferenceToTransactionKept`
+            Err(err) => return Err(err),
+            Ok(Ok(committed)) => {
+                let versionstamp = match versionstamp {
+                    // read-only transactions have no versionstamp
+                    Some(versionstamp) => versionstamp.await.ok().and_then(|versionstamp| {
+                        Some(Versionstamp::complete(
+                            versionstamp.as_ref().try_into().ok()?,
+                            0,
+                        ))
+                    }),
+                    None => None,
+                };
+                return Ok(RunOutcome {
+                    value,
+                    committed_version: committed.committed_version().ok(),
+                    versionstamp,
+                    attempts: retrier.attempts(),
+                    elapsed: retrier.elapsed(),
+                });
+            }
+            Ok(Err(transaction_commit_error)) => {
+                let e = FdbBindingError::from(*transaction_commit_error);
+                let maybe_committed = transaction_commit_error.is_maybe_committed();
+                if maybe_committed && retrier.fails_maybe_committed() {
+                    retrier.notify_failure(&e, false);
+                    return Err(e);
+                }
+                maybe_committed_transaction = maybe_committed;
+                if let Some(on_conflict) = options.on_conflict {
+                    notify_conflicting_keys(&transaction_commit_error, on_conflict).await;
+                }
+                // we have an error during commit, checking if it is a retryable error
+                match attempt_span
+                    .instrument(transaction_commit_error.on_error())
+                    .await
+                {
+                    Ok(t) if retrier.within_limits() => {
+                        retrier.notify_failure(&e, true);
+                        transaction = RetryableTransaction::new(t);
+                    }
+                    Ok(_) => {
+                        retrier.notify_failure(&e, false);
+                        return Err(e);
+                    }
+                    Err(non_retryable_error) => {
+                        retrier.notify_failure(&e, false);
+                        return Err(FdbBindingError::from(non_retryable_error));
                     }
                 }
+                backoff(&retrier).await;
+
+                let reconciler = match (retrier.reconcile(), &transaction_id) {
+                    (Some(reconcile), _) => Some(Reconciler::Closure(reconcile)),
+                    (None, Some(transaction_id)) => Some(Reconciler::TransactionId(transaction_id)),
+                    (None, None) => None,
+                };
+                if let (true, Some(reconciler)) = (maybe_committed, reconciler) {
+                    let (t, reconciled) = attempt_span
+                        .instrument(reconcile_commit(transaction, reconciler, &retrier))
+                        .await?;
+                    if let Reconciled::Committed(versionstamp) = reconciled {
+                        return Ok(RunOutcome {
+                            value,
+                            committed_version: versionstamp.as_ref().map(commit_version),
+                            versionstamp,
+                            attempts: retrier.attempts(),
+                            elapsed: retrier.elapsed(),
+                        });
+                    }
+                    transaction = t;
+                    maybe_committed_transaction = false;
+                }
             }
         }
     }
+}
 
-    /// Perform a no-op against FDB to check network thread liveness. This operation will not change the underlying data
-    /// in any way, nor will it perform any I/O against the FDB cluster. However, it will schedule some amount of work
-    /// onto the FDB client and wait for it to complete. The FoundationDB client operates by scheduling onto an event
-    /// queue that is then processed by a single thread (the "network thread"). This method can be used to determine if
-    /// the network thread has entered a state where it is no longer processing requests or if its time to process
-    /// requests has increased. If the network thread is busy, this operation may take some amount of time to complete,
-    /// which is why this operation returns a future.
-    pub async fn perform_no_op(&self) -> FdbResult<()> {
-        let trx = self.create_trx()?;
+/// How an attempt whose commit result is unknown is found to be committed
+#[derive(Clone, Copy)]
+enum Reconciler<'r, 'a> {
+    /// By the closure of `MaybeCommittedPolicy::Reconcile`
+    Closure(&'r Reconcile<'a>),
+    /// By reading the id written by the attempt, with `MaybeCommittedPolicy::Idempotent`
+    TransactionId(&'r TransactionId),
+}
 
-        // Set the read version of the transaction, then read it back. This requires no I/O, but it does
-        // require the network thread be running. The exact value used for the read version is unimportant.
-        trx.set_read_version(42);
-        trx.get_read_version().await?;
-        Ok(())
+/// Whether an attempt whose commit result was unknown committed
+enum Reconciled {
+    NotCommitted,
+    /// Committed, with its versionstamp if it is known
+    Committed(Option<Versionstamp>),
+}
+
+/// Finds out whether the attempt whose commit result is unknown committed, retrying the check
+/// on the retryable errors
+async fn reconcile_commit(
+    mut transaction: RetryableTransaction,
+    reconciler: Reconciler<'_, '_>,
+    retrier: &Retrier<'_, '_>,
+) -> Result<(RetryableTransaction, Reconciled), FdbBindingError> {
+    loop {
+        let reconciled = match reconciler {
+            Reconciler::Closure(reconcile) => {
+                reconcile(transaction.clone())
+                    .await
+                    .map(|committed| match committed {
+                        true => Reconciled::Committed(None),
+                        false => Reconciled::NotCommitted,
+                    })
+            }
+            Reconciler::TransactionId(transaction_id) => transaction_id
+                .read(&transaction)
+                .await
+                .map(|versionstamp| match versionstamp {
+                    Some(versionstamp) => Reconciled::Committed(Some(versionstamp)),
+                    None => Reconciled::NotCommitted,
+                }),
+        };
+        let e = match reconciled {
+            Ok(reconciled) => return Ok((transaction, reconciled)),
+            Err(e) => e,
+        };
+        let fdb_error = match e.get_fdb_error() {
+            Some(fdb_error) if retrier.within_limits() => fdb_error,
+            _ => return Err(e),
+        };
+        transaction = transaction
+            .on_error(fdb_error)
+            .await?
+            .map_err(FdbBindingError::from)?;
+    }
+}
+
+/// The version of a commit, from its versionstamp
+fn commit_version(versionstamp: &Versionstamp) -> i64 {
+    let mut version = [0; 8];
+    version.copy_from_slice(&versionstamp.transaction_version()[..8]);
+    i64::from_be_bytes(version)
+}
+
+/// Propagates the trace id of the policy to the transaction of the attempt, see
+/// `RetryPolicy::propagate_trace_id`
+#[cfg(feature = "tracing")]
+fn propagate_trace_id(
+    transaction: &Transaction,
+    retrier: &Retrier<'_, '_>,
+    span: &Span,
+) -> FdbResult<()> {
+    match span.in_scope(|| retrier.trace_id()) {
+        Some(trace_id) => {
+            span.record_trace_id(&trace_id);
+            set_debug_transaction_identifier(transaction, trace_id)
+        }
+        None => Ok(()),
+    }
+}
+
+#[cfg(feature = "tracing")]
+#[cfg_api_versions(min = 610)]
+fn set_debug_transaction_identifier(transaction: &Transaction, trace_id: String) -> FdbResult<()> {
+    transaction.set_option(options::TransactionOption::DebugTransactionIdentifier(
+        trace_id,
+    ))?;
+    transaction.set_option(options::TransactionOption::LogTransaction)
+}
+
+#[cfg(feature = "tracing")]
+#[cfg_api_versions(min = 510, max = 600)]
+fn set_debug_transaction_identifier(
+    _transaction: &Transaction,
+    _trace_id: String,
+) -> FdbResult<()> {
+    Ok(())
+}
+
+/// Waits the jittered backoff of the retry policy, if any
+async fn backoff(retrier: &Retrier<'_, '_>) {
+    let duration = retrier.backoff();
+    if duration > Duration::from_secs(0) {
+        delay(duration).await;
     }
 }
+
+/// Asks the commit of the transaction to report its conflicting keys
+#[cfg_api_versions(min = 630)]
+fn report_conflicting_keys(transaction: &Transaction) -> FdbResult<()> {
+    transaction.set_option(options::TransactionOption::ReportConflictingKeys)
+}
+
+#[cfg_api_versions(min = 510, max = 620)]
+fn report_conflicting_keys(_transaction: &Transaction) -> FdbResult<()> {
+    Ok(())
+}
+
+/// Calls `on_conflict` with the conflicting keys of a failed commit, if it conflicted
+#[cfg_api_versions(min = 630)]
+async fn notify_conflicting_keys(err: &TransactionCommitError, on_conflict: &ConflictHook<'_>) {
+    // the ranges are only informative, failing to read them does not fail the retry loop
+    if let Ok(ranges) = err.conflicting_keys().await {
+        if !ranges.is_empty() {
+            on_conflict(&ranges);
+        }
+    }
+}
+
+#[cfg_api_versions(min = 510, max = 620)]
+async fn notify_conflicting_keys(_err: &TransactionCommitError, _on_conflict: &ConflictHook<'_>) {}
+
 pub trait DatabaseTransact: Sized {
     type Item;
     type Error: TransactError;
@@ -468,3 +1323,19 @@ impl TransactOption {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    #[cfg(any(feature = "fdb-7_0", feature = "fdb-7_1"))]
+    #[test]
+    fn test_protocol_version() {
+        use super::ProtocolVersion;
+
+        let v710 = ProtocolVersion::from(0x1fdb_00b0_7101_0001);
+        assert_eq!(v710.version(), 0x0fdb_00b0_7101_0001);
+        assert_eq!(v710.to_string(), "fdb00b071010001");
+        assert_eq!(v710.release(), (7, 1));
+        assert!(v710.is_compatible(ProtocolVersion::from(0x0fdb_00b0_7101_0000)));
+        assert!(!v710.is_compatible(ProtocolVersion::from(0x0fdb_00b0_7000_0000)));
+    }
+}
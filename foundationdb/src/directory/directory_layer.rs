@@ -13,11 +13,10 @@ use crate::directory::directory_subspace::DirectorySubspace;
 use crate::directory::error::DirectoryError;
 use crate::directory::node::Node;
 use crate::directory::{compare_slice, strinc, Directory, DirectoryOutput};
-use crate::future::FdbSlice;
 use crate::tuple::hca::HighContentionAllocator;
 use crate::tuple::{Element, Subspace, TuplePack};
 use crate::RangeOption;
-use crate::{FdbResult, Transaction};
+use crate::{FdbResult, KeyValue, KeyValues, ReadTransaction, WriteTransaction};
 use async_recursion::async_recursion;
 use async_trait::async_trait;
 use std::cmp::Ordering;
@@ -128,17 +127,17 @@ impl DirectoryLayer {
         self.path.as_slice()
     }
 
-    fn node_with_optional_prefix(&self, prefix: Option<FdbSlice>) -> Option<Subspace> {
-        prefix.map(|fdb_slice| self.node_with_prefix(&fdb_slice.deref()))
+    fn node_with_optional_prefix(&self, prefix: Option<&[u8]>) -> Option<Subspace> {
+        prefix.map(|prefix| self.node_with_prefix(&prefix))
     }
 
     fn node_with_prefix<T: TuplePack>(&self, prefix: &T) -> Subspace {
         self.inner.node_subspace.subspace(prefix)
     }
 
-    async fn find(
+    async fn find<T: ReadTransaction>(
         &self,
-        trx: &Transaction,
+        trx: &T,
         path: &[String],
     ) -> Result<Option<Node>, DirectoryError> {
         let mut current_path = vec![];
@@ -155,7 +154,7 @@ impl DirectoryLayer {
             let fdb_slice_value = trx.get(key.bytes(), false).await?;
 
             loaded = true;
-            node_subspace = match self.node_with_optional_prefix(fdb_slice_value) {
+            node_subspace = match self.node_with_optional_prefix(fdb_slice_value.as_deref()) {
                 None => return Ok(None),
                 Some(subspace) => subspace,
             };
@@ -212,9 +211,9 @@ impl DirectoryLayer {
 
     /// `create_or_open_internal` is the function used to open and/or create a directory.
     #[async_recursion]
-    async fn create_or_open_internal(
+    async fn create_or_open_internal<T: WriteTransaction>(
         &self,
-        trx: &Transaction,
+        trx: &T,
         path: &[String],
         prefix: Option<&'async_recursion [u8]>,
         layer: Option<&'async_recursion [u8]>,
@@ -292,9 +291,9 @@ impl DirectoryLayer {
         node.get_contents()
     }
 
-    async fn create_internal(
+    async fn create_internal<T: WriteTransaction>(
         &self,
-        trx: &Transaction,
+        trx: &T,
         path: &[String],
         layer: Option<&[u8]>,
         prefix: Option<&[u8]>,
@@ -331,9 +330,9 @@ impl DirectoryLayer {
         self.contents_of_node(&node, path, layer)
     }
 
-    async fn get_parent_node(
+    async fn get_parent_node<T: WriteTransaction>(
         &self,
-        trx: &Transaction,
+        trx: &T,
         path: &[String],
     ) -> Result<Subspace, DirectoryError> {
         return match path.split_last() {
@@ -351,9 +350,9 @@ impl DirectoryLayer {
         };
     }
 
-    async fn is_prefix_free(
+    async fn is_prefix_free<T: ReadTransaction>(
         &self,
-        trx: &Transaction,
+        trx: &T,
         prefix: &[u8],
         snapshot: bool,
     ) -> Result<bool, DirectoryError> {
@@ -377,9 +376,9 @@ impl DirectoryLayer {
         Ok(result.is_empty())
     }
 
-    async fn node_containing_key(
+    async fn node_containing_key<T: ReadTransaction>(
         &self,
-        trx: &Transaction,
+        trx: &T,
         key: &[u8],
         snapshot: bool,
     ) -> Result<Option<Subspace>, DirectoryError> {
@@ -416,9 +415,9 @@ impl DirectoryLayer {
         Ok(None)
     }
 
-    async fn get_prefix(
+    async fn get_prefix<T: WriteTransaction>(
         &self,
-        trx: &Transaction,
+        trx: &T,
         prefix: Option<&[u8]>,
     ) -> Result<Vec<u8>, DirectoryError> {
         match prefix {
@@ -443,9 +442,9 @@ impl DirectoryLayer {
     }
 
     /// `check_version` is checking the Directory's version in FDB.
-    async fn check_version(
+    async fn check_version<T: WriteTransaction>(
         &self,
-        trx: &Transaction,
+        trx: &T,
         allow_creation: bool,
     ) -> Result<(), DirectoryError> {
         let version = self.get_version_value(trx).await?;
@@ -489,7 +488,10 @@ impl DirectoryLayer {
     }
 
     /// `initialize_directory` is initializing the directory
-    async fn initialize_directory(&self, trx: &Transaction) -> Result<(), DirectoryError> {
+    async fn initialize_directory<T: WriteTransaction>(
+        &self,
+        trx: &T,
+    ) -> Result<(), DirectoryError> {
         let mut value = vec![];
         value.extend(&MAJOR_VERSION.to_le_bytes());
         value.extend(&MINOR_VERSION.to_le_bytes());
@@ -501,16 +503,16 @@ impl DirectoryLayer {
         Ok(())
     }
 
-    async fn get_version_value(&self, trx: &Transaction) -> FdbResult<Option<FdbSlice>> {
+    async fn get_version_value<T: ReadTransaction>(&self, trx: &T) -> FdbResult<Option<T::Value>> {
         let version_subspace: &[u8] = b"version";
         let version_key = self.root_node.subspace(&version_subspace);
 
         trx.get(version_key.bytes(), false).await
     }
 
-    async fn exists_internal(
+    async fn exists_internal<T: WriteTransaction>(
         &self,
-        trx: &Transaction,
+        trx: &T,
         path: &[String],
     ) -> Result<bool, DirectoryError> {
         self.check_version(trx, false).await?;
@@ -526,9 +528,9 @@ impl DirectoryLayer {
         }
     }
 
-    async fn list_internal(
+    async fn list_internal<T: WriteTransaction>(
         &self,
-        trx: &Transaction,
+        trx: &T,
         path: &[String],
     ) -> Result<Vec<String>, DirectoryError> {
         self.check_version(trx, false).await?;
@@ -553,9 +555,9 @@ impl DirectoryLayer {
         node.list_sub_folders(trx).await
     }
 
-    async fn move_to_internal(
+    async fn move_to_internal<T: WriteTransaction>(
         &self,
-        trx: &Transaction,
+        trx: &T,
         old_path: &[String],
         new_path: &[String],
     ) -> Result<DirectoryOutput, DirectoryError> {
@@ -625,9 +627,9 @@ impl DirectoryLayer {
         self.contents_of_node(&old_node.subspace, new_path, &old_node.layer)
     }
 
-    async fn remove_from_parent(
+    async fn remove_from_parent<T: WriteTransaction>(
         &self,
-        trx: &Transaction,
+        trx: &T,
         path: &[String],
     ) -> Result<(), DirectoryError> {
         let (last_element, parent_path) = path
@@ -646,9 +648,9 @@ impl DirectoryLayer {
     }
 
     #[async_recursion]
-    async fn remove_internal(
+    async fn remove_internal<T: WriteTransaction>(
         &self,
-        trx: &Transaction,
+        trx: &T,
         path: &[String],
         fail_on_nonexistent: bool,
     ) -> Result<bool, DirectoryError> {
@@ -686,9 +688,9 @@ impl DirectoryLayer {
     }
 
     #[async_recursion]
-    async fn remove_recursive(
+    async fn remove_recursive<T: WriteTransaction>(
         &self,
-        trx: &Transaction,
+        trx: &T,
         node_sub: &Subspace,
     ) -> Result<(), DirectoryError> {
         let sub_dir = node_sub.subspace(&DEFAULT_SUB_DIRS);
@@ -700,10 +702,17 @@ impl DirectoryLayer {
             let range = trx.get_range(&range_option, 1024, false).await?;
             let has_more = range.more();
 
-            for row_key in range {
-                let sub_node = self.node_with_prefix(&row_key.value());
+            // collected first, as the pairs of the range can't be kept across an await
+            let rows: Vec<(Vec<u8>, Vec<u8>)> = range
+                .iter()
+                .map(|row| (row.key().to_vec(), row.value().to_vec()))
+                .collect();
+            drop(range);
+
+            for (row_key, row_value) in rows {
+                let sub_node = self.node_with_prefix(&row_value.as_slice());
                 self.remove_recursive(trx, &sub_node).await?;
-                begin = row_key.key().pack_to_vec();
+                begin = row_key.as_slice().pack_to_vec();
             }
 
             if !has_more {
@@ -721,10 +730,10 @@ impl DirectoryLayer {
 }
 
 #[async_trait]
-impl Directory for DirectoryLayer {
+impl<T: WriteTransaction> Directory<T> for DirectoryLayer {
     async fn create_or_open(
         &self,
-        txn: &Transaction,
+        txn: &T,
         path: &[String],
         prefix: Option<&[u8]>,
         layer: Option<&[u8]>,
@@ -735,7 +744,7 @@ impl Directory for DirectoryLayer {
 
     async fn create(
         &self,
-        txn: &Transaction,
+        txn: &T,
         path: &[String],
         prefix: Option<&[u8]>,
         layer: Option<&[u8]>,
@@ -746,7 +755,7 @@ impl Directory for DirectoryLayer {
 
     async fn open(
         &self,
-        txn: &Transaction,
+        txn: &T,
         path: &[String],
         layer: Option<&[u8]>,
     ) -> Result<DirectoryOutput, DirectoryError> {
@@ -754,13 +763,13 @@ impl Directory for DirectoryLayer {
             .await
     }
 
-    async fn exists(&self, trx: &Transaction, path: &[String]) -> Result<bool, DirectoryError> {
+    async fn exists(&self, trx: &T, path: &[String]) -> Result<bool, DirectoryError> {
         self.exists_internal(trx, path).await
     }
 
     async fn move_directory(
         &self,
-        _trx: &Transaction,
+        _trx: &T,
         _new_path: &[String],
     ) -> Result<DirectoryOutput, DirectoryError> {
         Err(DirectoryError::CannotMoveRootDirectory)
@@ -773,30 +782,22 @@ impl Directory for DirectoryLayer {
     /// parent directory of newPath does not exist.
     async fn move_to(
         &self,
-        trx: &Transaction,
+        trx: &T,
         old_path: &[String],
         new_path: &[String],
     ) -> Result<DirectoryOutput, DirectoryError> {
         self.move_to_internal(trx, old_path, new_path).await
     }
 
-    async fn remove(&self, trx: &Transaction, path: &[String]) -> Result<bool, DirectoryError> {
+    async fn remove(&self, trx: &T, path: &[String]) -> Result<bool, DirectoryError> {
         self.remove_internal(trx, path, true).await
     }
 
-    async fn remove_if_exists(
-        &self,
-        trx: &Transaction,
-        path: &[String],
-    ) -> Result<bool, DirectoryError> {
+    async fn remove_if_exists(&self, trx: &T, path: &[String]) -> Result<bool, DirectoryError> {
         self.remove_internal(trx, path, false).await
     }
 
-    async fn list(
-        &self,
-        trx: &Transaction,
-        path: &[String],
-    ) -> Result<Vec<String>, DirectoryError> {
+    async fn list(&self, trx: &T, path: &[String]) -> Result<Vec<String>, DirectoryError> {
         self.list_internal(trx, path).await
     }
 }
@@ -13,7 +13,7 @@ use crate::directory::directory_subspace::DirectorySubspace;
 use crate::directory::error::DirectoryError;
 use crate::directory::{Directory, DirectoryOutput};
 use crate::tuple::Subspace;
-use crate::Transaction;
+use crate::WriteTransaction;
 use async_trait::async_trait;
 use std::ops::Deref;
 use std::sync::Arc;
@@ -119,10 +119,10 @@ impl DirectoryPartition {
 }
 
 #[async_trait]
-impl Directory for DirectoryPartition {
+impl<T: WriteTransaction> Directory<T> for DirectoryPartition {
     async fn create_or_open(
         &self,
-        txn: &Transaction,
+        txn: &T,
         path: &[String],
         prefix: Option<&[u8]>,
         layer: Option<&[u8]>,
@@ -135,7 +135,7 @@ impl Directory for DirectoryPartition {
 
     async fn create(
         &self,
-        txn: &Transaction,
+        txn: &T,
         path: &[String],
         prefix: Option<&[u8]>,
         layer: Option<&[u8]>,
@@ -148,14 +148,14 @@ impl Directory for DirectoryPartition {
 
     async fn open(
         &self,
-        txn: &Transaction,
+        txn: &T,
         path: &[String],
         layer: Option<&[u8]>,
     ) -> Result<DirectoryOutput, DirectoryError> {
         self.inner.directory_subspace.open(txn, path, layer).await
     }
 
-    async fn exists(&self, trx: &Transaction, path: &[String]) -> Result<bool, DirectoryError> {
+    async fn exists(&self, trx: &T, path: &[String]) -> Result<bool, DirectoryError> {
         let directory_layer = self.get_directory_layer_for_path(path);
 
         directory_layer
@@ -168,7 +168,7 @@ impl Directory for DirectoryPartition {
 
     async fn move_directory(
         &self,
-        trx: &Transaction,
+        trx: &T,
         new_path: &[String],
     ) -> Result<DirectoryOutput, DirectoryError> {
         let directory_layer = self.get_directory_layer_for_path(&[]);
@@ -203,7 +203,7 @@ impl Directory for DirectoryPartition {
 
     async fn move_to(
         &self,
-        trx: &Transaction,
+        trx: &T,
         old_path: &[String],
         new_path: &[String],
     ) -> Result<DirectoryOutput, DirectoryError> {
@@ -213,7 +213,7 @@ impl Directory for DirectoryPartition {
             .await
     }
 
-    async fn remove(&self, trx: &Transaction, path: &[String]) -> Result<bool, DirectoryError> {
+    async fn remove(&self, trx: &T, path: &[String]) -> Result<bool, DirectoryError> {
         let directory_layer = self.get_directory_layer_for_path(path);
         directory_layer
             .remove(
@@ -223,11 +223,7 @@ impl Directory for DirectoryPartition {
             .await
     }
 
-    async fn remove_if_exists(
-        &self,
-        trx: &Transaction,
-        path: &[String],
-    ) -> Result<bool, DirectoryError> {
+    async fn remove_if_exists(&self, trx: &T, path: &[String]) -> Result<bool, DirectoryError> {
         let directory_layer = self.get_directory_layer_for_path(path);
         directory_layer
             .remove_if_exists(
@@ -237,11 +233,7 @@ impl Directory for DirectoryPartition {
             .await
     }
 
-    async fn list(
-        &self,
-        trx: &Transaction,
-        path: &[String],
-    ) -> Result<Vec<String>, DirectoryError> {
+    async fn list(&self, trx: &T, path: &[String]) -> Result<Vec<String>, DirectoryError> {
         self.inner.directory_subspace.list(trx, path).await
     }
 }
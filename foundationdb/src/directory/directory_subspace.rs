@@ -12,7 +12,7 @@ use crate::directory::directory_layer::DirectoryLayer;
 use crate::directory::error::DirectoryError;
 use crate::directory::{Directory, DirectoryOutput};
 use crate::tuple::{PackResult, Subspace, TuplePack, TupleUnpack};
-use crate::Transaction;
+use crate::WriteTransaction;
 use async_trait::async_trait;
 
 /// A `DirectorySubspace` represents the contents of a directory, but it also remembers
@@ -109,10 +109,10 @@ impl DirectorySubspace {
 }
 
 #[async_trait]
-impl Directory for DirectorySubspace {
+impl<T: WriteTransaction> Directory<T> for DirectorySubspace {
     async fn create_or_open(
         &self,
-        txn: &Transaction,
+        txn: &T,
         path: &[String],
         prefix: Option<&[u8]>,
         layer: Option<&[u8]>,
@@ -124,7 +124,7 @@ impl Directory for DirectorySubspace {
 
     async fn create(
         &self,
-        txn: &Transaction,
+        txn: &T,
         path: &[String],
         prefix: Option<&[u8]>,
         layer: Option<&[u8]>,
@@ -136,7 +136,7 @@ impl Directory for DirectorySubspace {
 
     async fn open(
         &self,
-        txn: &Transaction,
+        txn: &T,
         path: &[String],
         layer: Option<&[u8]>,
     ) -> Result<DirectoryOutput, DirectoryError> {
@@ -145,7 +145,7 @@ impl Directory for DirectorySubspace {
             .await
     }
 
-    async fn exists(&self, trx: &Transaction, path: &[String]) -> Result<bool, DirectoryError> {
+    async fn exists(&self, trx: &T, path: &[String]) -> Result<bool, DirectoryError> {
         let directory_layer = self.get_directory_layer_for_path(path);
 
         directory_layer
@@ -158,7 +158,7 @@ impl Directory for DirectorySubspace {
 
     async fn move_directory(
         &self,
-        trx: &Transaction,
+        trx: &T,
         new_path: &[String],
     ) -> Result<DirectoryOutput, DirectoryError> {
         let directory_layer = self.get_directory_layer_for_path(&[]);
@@ -193,7 +193,7 @@ impl Directory for DirectorySubspace {
 
     async fn move_to(
         &self,
-        trx: &Transaction,
+        trx: &T,
         old_path: &[String],
         new_path: &[String],
     ) -> Result<DirectoryOutput, DirectoryError> {
@@ -206,7 +206,7 @@ impl Directory for DirectorySubspace {
             .await
     }
 
-    async fn remove(&self, trx: &Transaction, path: &[String]) -> Result<bool, DirectoryError> {
+    async fn remove(&self, trx: &T, path: &[String]) -> Result<bool, DirectoryError> {
         let directory_layer = self.get_directory_layer_for_path(path);
         directory_layer
             .remove(
@@ -216,11 +216,7 @@ impl Directory for DirectorySubspace {
             .await
     }
 
-    async fn remove_if_exists(
-        &self,
-        trx: &Transaction,
-        path: &[String],
-    ) -> Result<bool, DirectoryError> {
+    async fn remove_if_exists(&self, trx: &T, path: &[String]) -> Result<bool, DirectoryError> {
         let directory_layer = self.get_directory_layer_for_path(path);
         directory_layer
             .remove_if_exists(
@@ -230,11 +226,7 @@ impl Directory for DirectorySubspace {
             .await
     }
 
-    async fn list(
-        &self,
-        trx: &Transaction,
-        path: &[String],
-    ) -> Result<Vec<String>, DirectoryError> {
+    async fn list(&self, trx: &T, path: &[String]) -> Result<Vec<String>, DirectoryError> {
         self.directory_layer
             .list(trx, &self.get_partition_subpath(path, None)?)
             .await
@@ -66,7 +66,7 @@ mod error;
 mod node;
 
 use crate::tuple::{PackResult, Subspace, TuplePack, TupleUnpack};
-use crate::Transaction;
+use crate::{Transaction, WriteTransaction};
 use async_trait::async_trait;
 use core::cmp;
 pub use directory_layer::DirectoryLayer;
@@ -76,8 +76,11 @@ pub use error::DirectoryError;
 use std::cmp::Ordering;
 
 /// `Directory` represents a subspace of keys in a FoundationDB database, identified by a hierarchical path.
+///
+/// Directories are implemented for any [`WriteTransaction`], `T` defaults to `Transaction` so
+/// `dyn Directory` stays usable.
 #[async_trait]
-pub trait Directory {
+pub trait Directory<T: WriteTransaction = Transaction> {
     /// Creates or opens the subdirectory of this Directory located at path (creating parent directories, if necessary).
     ///
     /// # Warning
@@ -85,7 +88,7 @@ pub trait Directory {
     /// If you need to create several paths with the same prefix, you **must** use several transactions(See [this link](https://github.com/apple/foundationdb/issues/895#issuecomment-436704180) for context)
     async fn create_or_open(
         &self,
-        txn: &Transaction,
+        txn: &T,
         path: &[String],
         prefix: Option<&[u8]>,
         layer: Option<&[u8]>,
@@ -98,7 +101,7 @@ pub trait Directory {
     /// If you need to create several paths with the same prefix, you **must** use several transactions(See [this link](https://github.com/apple/foundationdb/issues/895#issuecomment-436704180) for context)
     async fn create(
         &self,
-        txn: &Transaction,
+        txn: &T,
         path: &[String],
         prefix: Option<&[u8]>,
         layer: Option<&[u8]>,
@@ -107,42 +110,37 @@ pub trait Directory {
     /// Opens the subdirectory of this Directory located at path.
     async fn open(
         &self,
-        txn: &Transaction,
+        txn: &T,
         path: &[String],
         layer: Option<&[u8]>,
     ) -> Result<DirectoryOutput, DirectoryError>;
 
     /// Checks if the subdirectory of this Directory located at path exists.
-    async fn exists(&self, trx: &Transaction, path: &[String]) -> Result<bool, DirectoryError>;
+    async fn exists(&self, trx: &T, path: &[String]) -> Result<bool, DirectoryError>;
 
     /// Moves this Directory to the specified newAbsolutePath.
     async fn move_directory(
         &self,
-        trx: &Transaction,
+        trx: &T,
         new_path: &[String],
     ) -> Result<DirectoryOutput, DirectoryError>;
 
     /// Moves the subdirectory of this Directory located at oldpath to newpath.
     async fn move_to(
         &self,
-        trx: &Transaction,
+        trx: &T,
         old_path: &[String],
         new_path: &[String],
     ) -> Result<DirectoryOutput, DirectoryError>;
 
     /// Removes the subdirectory of this Directory located at path and all of its subdirectories, as well as all of their contents.
-    async fn remove(&self, trx: &Transaction, path: &[String]) -> Result<bool, DirectoryError>;
+    async fn remove(&self, trx: &T, path: &[String]) -> Result<bool, DirectoryError>;
 
     /// Removes the subdirectory of this Directory located at path (if the path exists) and all of its subdirectories, as well as all of their contents.
-    async fn remove_if_exists(
-        &self,
-        trx: &Transaction,
-        path: &[String],
-    ) -> Result<bool, DirectoryError>;
+    async fn remove_if_exists(&self, trx: &T, path: &[String]) -> Result<bool, DirectoryError>;
 
     /// List the subdirectories of this directory at a given subpath.
-    async fn list(&self, trx: &Transaction, path: &[String])
-        -> Result<Vec<String>, DirectoryError>;
+    async fn list(&self, trx: &T, path: &[String]) -> Result<Vec<String>, DirectoryError>;
 }
 
 pub(crate) fn compare_slice<T: Ord>(a: &[T], b: &[T]) -> cmp::Ordering {
@@ -231,10 +229,10 @@ impl DirectoryOutput {
 }
 
 #[async_trait]
-impl Directory for DirectoryOutput {
+impl<T: WriteTransaction> Directory<T> for DirectoryOutput {
     async fn create_or_open(
         &self,
-        txn: &Transaction,
+        txn: &T,
         path: &[String],
         prefix: Option<&[u8]>,
         layer: Option<&[u8]>,
@@ -251,7 +249,7 @@ impl Directory for DirectoryOutput {
 
     async fn create(
         &self,
-        txn: &Transaction,
+        txn: &T,
         path: &[String],
         prefix: Option<&[u8]>,
         layer: Option<&[u8]>,
@@ -264,7 +262,7 @@ impl Directory for DirectoryOutput {
 
     async fn open(
         &self,
-        txn: &Transaction,
+        txn: &T,
         path: &[String],
         layer: Option<&[u8]>,
     ) -> Result<DirectoryOutput, DirectoryError> {
@@ -274,7 +272,7 @@ impl Directory for DirectoryOutput {
         }
     }
 
-    async fn exists(&self, trx: &Transaction, path: &[String]) -> Result<bool, DirectoryError> {
+    async fn exists(&self, trx: &T, path: &[String]) -> Result<bool, DirectoryError> {
         match self {
             DirectoryOutput::DirectorySubspace(d) => d.exists(trx, path).await,
             DirectoryOutput::DirectoryPartition(d) => d.exists(trx, path).await,
@@ -283,7 +281,7 @@ impl Directory for DirectoryOutput {
 
     async fn move_directory(
         &self,
-        trx: &Transaction,
+        trx: &T,
         new_path: &[String],
     ) -> Result<DirectoryOutput, DirectoryError> {
         match self {
@@ -294,7 +292,7 @@ impl Directory for DirectoryOutput {
 
     async fn move_to(
         &self,
-        trx: &Transaction,
+        trx: &T,
         old_path: &[String],
         new_path: &[String],
     ) -> Result<DirectoryOutput, DirectoryError> {
@@ -304,29 +302,21 @@ impl Directory for DirectoryOutput {
         }
     }
 
-    async fn remove(&self, trx: &Transaction, path: &[String]) -> Result<bool, DirectoryError> {
+    async fn remove(&self, trx: &T, path: &[String]) -> Result<bool, DirectoryError> {
         match self {
             DirectoryOutput::DirectorySubspace(d) => d.remove(trx, path).await,
             DirectoryOutput::DirectoryPartition(d) => d.remove(trx, path).await,
         }
     }
 
-    async fn remove_if_exists(
-        &self,
-        trx: &Transaction,
-        path: &[String],
-    ) -> Result<bool, DirectoryError> {
+    async fn remove_if_exists(&self, trx: &T, path: &[String]) -> Result<bool, DirectoryError> {
         match self {
             DirectoryOutput::DirectorySubspace(d) => d.remove_if_exists(trx, path).await,
             DirectoryOutput::DirectoryPartition(d) => d.remove_if_exists(trx, path).await,
         }
     }
 
-    async fn list(
-        &self,
-        trx: &Transaction,
-        path: &[String],
-    ) -> Result<Vec<String>, DirectoryError> {
+    async fn list(&self, trx: &T, path: &[String]) -> Result<Vec<String>, DirectoryError> {
         match self {
             DirectoryOutput::DirectorySubspace(d) => d.list(trx, path).await,
             DirectoryOutput::DirectoryPartition(d) => d.list(trx, path).await,
@@ -376,6 +366,6 @@ mod tests {
         assert_eq!(strinc(vec![61u8, 62u8, 255u8]), vec![61u8, 63u8]);
         assert_eq!(strinc(vec![253u8, 255u8]), vec![254u8]);
         assert_eq!(strinc(vec![253u8, 255u8, 255u8]), vec![254u8]);
-        assert_eq!(strinc(vec![255u8, 255u8, 255u8]), vec![]);
+        assert_eq!(strinc(vec![255u8, 255u8, 255u8]), Vec::<u8>::new());
     }
 }
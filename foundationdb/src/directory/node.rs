@@ -5,7 +5,7 @@ use crate::directory::error::DirectoryError;
 use crate::directory::DirectoryOutput;
 use crate::tuple::Subspace;
 use crate::RangeOption;
-use crate::Transaction;
+use crate::{KeyValue, ReadTransaction};
 
 #[derive(Debug, Clone)]
 pub(super) struct Node {
@@ -18,8 +18,8 @@ pub(super) struct Node {
 
 impl Node {
     // `load_metadata` is loading extra information for the node, like the layer
-    pub(crate) async fn load_metadata(
-        trx: &Transaction,
+    pub(crate) async fn load_metadata<T: ReadTransaction>(
+        trx: &T,
         subspace: &Subspace,
     ) -> Result<Vec<u8>, DirectoryError> {
         let key = subspace.pack(&LAYER_SUFFIX);
@@ -35,9 +35,9 @@ impl Node {
     }
 
     /// list sub-folders for a node
-    pub(crate) async fn list_sub_folders(
+    pub(crate) async fn list_sub_folders<T: ReadTransaction>(
         &self,
-        trx: &Transaction,
+        trx: &T,
     ) -> Result<Vec<String>, DirectoryError> {
         let mut results = vec![];
 
@@ -45,7 +45,7 @@ impl Node {
 
         let fdb_values = trx.get_range(&range_option, 1_024, false).await?;
 
-        for fdb_value in fdb_values {
+        for fdb_value in fdb_values.iter() {
             let subspace = Subspace::from_bytes(fdb_value.key());
             // stripping from subspace
             let sub_directory: (i64, String) = self.subspace.unpack(subspace.bytes())?;
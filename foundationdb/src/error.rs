@@ -13,6 +13,7 @@ use std::fmt;
 use std::fmt::{Debug, Display, Formatter};
 
 use crate::directory::DirectoryError;
+use crate::error_kind::{FdbErrorCategory, FdbErrorKind};
 use crate::options;
 use crate::tuple::hca::HcaError;
 use crate::tuple::PackError;
@@ -74,6 +75,42 @@ impl FdbError {
     pub fn code(self) -> i32 {
         self.error_code
     }
+
+    /// The typed error code, to match on instead of the raw one.
+    pub fn kind(self) -> FdbErrorKind {
+        FdbErrorKind::from_code(self.error_code)
+    }
+
+    /// Indicates an invalid use of the client API, like an invalid option or an inverted range.
+    pub fn is_client_error(self) -> bool {
+        self.kind().category() == FdbErrorCategory::Client
+    }
+
+    /// Indicates a failure of the transaction itself, like a conflict or a too old read version.
+    pub fn is_transaction_error(self) -> bool {
+        self.kind().category() == FdbErrorCategory::Transaction
+    }
+
+    /// Indicates a failure of a tenant operation, like a missing tenant.
+    pub fn is_tenant_error(self) -> bool {
+        self.kind().category() == FdbErrorCategory::Tenant
+    }
+
+    /// Indicates a failure of the special key space.
+    pub fn is_special_keys_error(self) -> bool {
+        self.kind().category() == FdbErrorCategory::SpecialKeys
+    }
+
+    /// Indicates a failure of the cluster or of one of its servers.
+    pub fn is_cluster_error(self) -> bool {
+        self.kind().category() == FdbErrorCategory::Cluster
+    }
+}
+
+impl From<FdbErrorKind> for FdbError {
+    fn from(kind: FdbErrorKind) -> Self {
+        Self::from_code(kind.code())
+    }
 }
 
 impl fmt::Display for FdbError {
@@ -142,6 +179,12 @@ impl From<DirectoryError> for FdbBindingError {
     }
 }
 
+impl From<PackError> for FdbBindingError {
+    fn from(e: PackError) -> Self {
+        Self::PackError(e)
+    }
+}
+
 impl FdbBindingError {
     /// create a new custom error
     pub fn new_custom_error(e: Box<dyn std::error::Error + Send + Sync>) -> Self {
@@ -0,0 +1,416 @@
+// Copyright 2018 foundationdb-rs developers, https://github.com/Clikengo/foundationdb-rs/graphs/contributors
+// Copyright 2013-2018 Apple, Inc and the FoundationDB project authors.
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+//! Typed FoundationDB error codes.
+//!
+//! The table below follows `flow/error_definitions.h` of FoundationDB, and is expanded by the
+//! `fdb_errors!` macro into [`FdbErrorKind`] and its accessors. Codes which are not in the table
+//! are kept as [`FdbErrorKind::Other`].
+
+use std::fmt;
+
+/// The group an error code belongs to, following the sections of the FDB error table.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum FdbErrorCategory {
+    /// Transient failures and conflicts of a transaction, most of them retryable
+    Transaction,
+    /// Failures of the cluster and its servers
+    Cluster,
+    /// Failures of the local platform: disk, files and network
+    Platform,
+    /// Invalid use of the client API
+    Client,
+    /// Failures of the special key space, `\xff\xff`
+    SpecialKeys,
+    /// Failures of the tenant operations
+    Tenant,
+    /// Failures of the bindings and layers: API version, tuples and directories
+    Layer,
+    /// Failures of backups, restores and disk snapshots
+    Backup,
+    /// Internal errors, which are bugs
+    Internal,
+    /// An error code unknown to this version of the crate
+    Other,
+}
+
+macro_rules! fdb_errors {
+    ($($category:ident $code:literal $variant:ident $name:literal $description:literal,)*) => {
+        /// A FoundationDB error code.
+        ///
+        /// Matching on the kind rather than on the raw code makes the intent explicit:
+        ///
+        /// ```
+        /// use foundationdb::{FdbError, FdbErrorKind};
+        ///
+        /// let err = FdbError::from_code(1020);
+        /// assert_eq!(err.kind(), FdbErrorKind::NotCommitted);
+        /// assert!(err.is_transaction_error());
+        /// ```
+        #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+        #[non_exhaustive]
+        pub enum FdbErrorKind {
+            $(
+                #[doc = $description]
+                $variant,
+            )*
+            /// An error code which is not in the table of this version of the crate
+            Other(i32),
+        }
+
+        impl FdbErrorKind {
+            /// The kind of a raw FoundationDB error code.
+            pub fn from_code(code: i32) -> Self {
+                match code {
+                    $($code => FdbErrorKind::$variant,)*
+                    code => FdbErrorKind::Other(code),
+                }
+            }
+
+            /// Raw FoundationDB error code
+            pub fn code(self) -> i32 {
+                match self {
+                    $(FdbErrorKind::$variant => $code,)*
+                    FdbErrorKind::Other(code) => code,
+                }
+            }
+
+            /// The name of the error in the FDB error table, like `not_committed`.
+            pub fn name(self) -> Option<&'static str> {
+                match self {
+                    $(FdbErrorKind::$variant => Some($name),)*
+                    FdbErrorKind::Other(_) => None,
+                }
+            }
+
+            /// The description of the error in the FDB error table.
+            ///
+            /// It does not need the client library, unlike `FdbError::message`.
+            pub fn description(self) -> Option<&'static str> {
+                match self {
+                    $(FdbErrorKind::$variant => Some($description),)*
+                    FdbErrorKind::Other(_) => None,
+                }
+            }
+
+            /// The group the error belongs to.
+            pub fn category(self) -> FdbErrorCategory {
+                match self {
+                    $(FdbErrorKind::$variant => FdbErrorCategory::$category,)*
+                    FdbErrorKind::Other(_) => FdbErrorCategory::Other,
+                }
+            }
+        }
+
+        #[cfg(test)]
+        const ALL_KINDS: &[FdbErrorKind] = &[$(FdbErrorKind::$variant,)*];
+    };
+}
+
+fdb_errors! {
+    Cluster 1000 OperationFailed "operation_failed" "Operation failed",
+    Cluster 1001 WrongShardServer "wrong_shard_server" "Shard is not available from this server",
+    Cluster 1002 OperationObsolete "operation_obsolete" "Operation result no longer necessary",
+    Cluster 1003 ColdCacheServer "cold_cache_server" "Cache server is not warm for this range",
+    Cluster 1004 TimedOut "timed_out" "Operation timed out",
+    Cluster 1005 CoordinatedStateConflict "coordinated_state_conflict" "Conflict occurred while changing coordination information",
+    Cluster 1006 AllAlternativesFailed "all_alternatives_failed" "All alternatives failed",
+    Transaction 1007 TransactionTooOld "transaction_too_old" "Transaction is too old to perform reads or be committed",
+    Cluster 1008 NoMoreServers "no_more_servers" "Not enough physical servers available",
+    Transaction 1009 FutureVersion "future_version" "Request for future version",
+    Cluster 1010 MovekeysConflict "movekeys_conflict" "Conflicting attempts to change data distribution",
+    Cluster 1011 TlogStopped "tlog_stopped" "TLog stopped",
+    Cluster 1012 ServerRequestQueueFull "server_request_queue_full" "Server request queue is full",
+    Transaction 1020 NotCommitted "not_committed" "Transaction not committed due to conflict with another transaction",
+    Transaction 1021 CommitUnknownResult "commit_unknown_result" "Transaction may or may not have committed",
+    Transaction 1025 TransactionCancelled "transaction_cancelled" "Operation aborted because the transaction was cancelled",
+    Cluster 1026 ConnectionFailed "connection_failed" "Network connection failed",
+    Cluster 1027 CoordinatorsChanged "coordinators_changed" "Coordination servers have changed",
+    Cluster 1028 NewCoordinatorsTimedOut "new_coordinators_timed_out" "New coordination servers did not respond in a timely way",
+    Cluster 1029 WatchCancelled "watch_cancelled" "Watch cancelled because storage server watch limit exceeded",
+    Cluster 1030 RequestMaybeDelivered "request_maybe_delivered" "Request may or may not have been delivered",
+    Transaction 1031 TransactionTimedOut "transaction_timed_out" "Operation aborted because the transaction timed out",
+    Client 1032 TooManyWatches "too_many_watches" "Too many watches currently set",
+    Cluster 1033 LocalityInformationUnavailable "locality_information_unavailable" "Locality information not available",
+    Client 1034 WatchesDisabled "watches_disabled" "Watches cannot be set if read your writes is disabled",
+    Internal 1035 DefaultErrorOr "default_error_or" "Default error for an ErrorOr object",
+    Transaction 1036 AccessedUnreadable "accessed_unreadable" "Read or wrote an unreadable key",
+    Transaction 1037 ProcessBehind "process_behind" "Storage process does not have recent mutations",
+    Transaction 1038 DatabaseLocked "database_locked" "Database is locked",
+    Cluster 1039 ClusterVersionChanged "cluster_version_changed" "The protocol version of the cluster has changed",
+    Client 1040 ExternalClientAlreadyLoaded "external_client_already_loaded" "External client has already been loaded",
+    Platform 1041 LookupFailed "lookup_failed" "DNS lookup failed",
+    Cluster 1042 ProxyMemoryLimitExceeded "proxy_memory_limit_exceeded" "CommitProxy commit memory limit exceeded",
+    Cluster 1043 ShutdownInProgress "shutdown_in_progress" "Operation no longer supported due to shutdown",
+    Internal 1044 SerializationFailed "serialization_failed" "Failed to deserialize an object",
+    Cluster 1048 ConnectionUnreferenced "connection_unreferenced" "No peer references for connection",
+    Cluster 1049 ConnectionIdle "connection_idle" "Connection closed after idle timeout",
+    Platform 1050 DiskAdapterReset "disk_adapter_reset" "The disk queue adpater reset",
+    Transaction 1051 BatchTransactionThrottled "batch_transaction_throttled" "Batch GRV request rate limit exceeded",
+    Cluster 1052 DdCancelled "dd_cancelled" "Data distribution components cancelled",
+    Cluster 1053 DdNotFound "dd_not_found" "Data distributor not found",
+    Client 1054 WrongConnectionFile "wrong_connection_file" "Connection file mismatch",
+    Cluster 1055 VersionAlreadyCompacted "version_already_compacted" "The requested changes have been compacted away",
+    Cluster 1056 LocalConfigChanged "local_config_changed" "Local configuration file has changed. Restart and apply these changes",
+    Cluster 1057 FailedToReachQuorum "failed_to_reach_quorum" "Failed to reach quorum from configuration database nodes. Retry sending these requests",
+    Cluster 1060 UnknownChangeFeed "unknown_change_feed" "Change feed not found",
+    Cluster 1061 ChangeFeedNotRegistered "change_feed_not_registered" "Change feed not registered",
+    Cluster 1062 GranuleAssignmentConflict "granule_assignment_conflict" "Conflicting attempts to assign blob granules",
+    Cluster 1063 ChangeFeedCancelled "change_feed_cancelled" "Change feed was cancelled",
+    Cluster 1064 BlobGranuleFileLoadError "blob_granule_file_load_error" "Error loading a blob file during granule materialization",
+    Cluster 1065 BlobGranuleTransactionTooOld "blob_granule_transaction_too_old" "Read version is older than blob granule history supports",
+    Cluster 1066 BlobManagerReplaced "blob_manager_replaced" "This blob manager has been replaced.",
+    Cluster 1067 ChangeFeedPopped "change_feed_popped" "Tried to read a version older than what has been popped from the change feed",
+
+    Internal 1100 BrokenPromise "broken_promise" "Broken promise",
+    Internal 1101 OperationCancelled "operation_cancelled" "Asynchronous operation cancelled",
+    Internal 1102 FutureReleased "future_released" "Future has been released",
+    Internal 1103 ConnectionLeaked "connection_leaked" "Connection object leaked",
+
+    Cluster 1200 RecruitmentFailed "recruitment_failed" "Recruitment of a server failed",
+    Cluster 1201 MoveToRemovedServer "move_to_removed_server" "Attempt to move keys to a storage server that was removed",
+    Cluster 1202 WorkerRemoved "worker_removed" "Normal worker shut down",
+    Cluster 1203 ClusterRecoveryFailed "cluster_recovery_failed" "Cluster recovery failed",
+    Cluster 1204 MasterMaxVersionsInFlight "master_max_versions_in_flight" "Master hit maximum number of versions in flight",
+    Cluster 1205 TlogFailed "tlog_failed" "Cluster recovery terminating because a TLog failed",
+    Cluster 1206 WorkerRecoveryFailed "worker_recovery_failed" "Recovery of a worker process failed",
+    Cluster 1207 PleaseReboot "please_reboot" "Reboot of server process requested",
+    Cluster 1208 PleaseRebootDelete "please_reboot_delete" "Reboot of server process requested, with deletion of state",
+    Cluster 1209 CommitProxyFailed "commit_proxy_failed" "Master terminating because a CommitProxy failed",
+    Cluster 1210 ResolverFailed "resolver_failed" "Cluster recovery terminating because a Resolver failed",
+    Cluster 1211 ServerOverloaded "server_overloaded" "Server is under too much load and cannot respond",
+    Cluster 1212 BackupWorkerFailed "backup_worker_failed" "Cluster recovery terminating because a backup worker failed",
+    Transaction 1213 TagThrottled "tag_throttled" "Transaction tag is being throttled",
+    Cluster 1214 GrvProxyFailed "grv_proxy_failed" "Cluster recovery terminating because a GRVProxy failed",
+    Cluster 1215 DdTrackerCancelled "dd_tracker_cancelled" "The data distribution tracker has been cancelled",
+    Cluster 1216 FailedToProgress "failed_to_progress" "Process has failed to make sufficient progress",
+
+    Platform 1500 PlatformError "platform_error" "Platform error",
+    Platform 1501 LargeAllocFailed "large_alloc_failed" "Large block allocation failed",
+    Platform 1502 PerformanceCounterError "performance_counter_error" "QueryPerformanceCounter error",
+    Platform 1510 IoError "io_error" "Disk i/o operation failed",
+    Platform 1511 FileNotFound "file_not_found" "File not found",
+    Platform 1512 BindFailed "bind_failed" "Unable to bind to network",
+    Platform 1513 FileNotReadable "file_not_readable" "File could not be read",
+    Platform 1514 FileNotWritable "file_not_writable" "File could not be written",
+    Platform 1515 NoClusterFileFound "no_cluster_file_found" "No cluster file found in current directory or default location",
+    Platform 1516 FileTooLarge "file_too_large" "File too large to be read",
+    Platform 1517 NonSequentialOp "non_sequential_op" "Non sequential file operation not allowed",
+    Platform 1518 HttpBadResponse "http_bad_response" "HTTP response was badly formed",
+    Platform 1519 HttpNotAccepted "http_not_accepted" "HTTP request not accepted",
+    Platform 1520 ChecksumFailed "checksum_failed" "A data checksum failed",
+    Platform 1521 IoTimeout "io_timeout" "A disk IO operation failed to complete in a timely manner",
+    Platform 1522 FileCorrupt "file_corrupt" "A structurally corrupt data file was detected",
+    Platform 1523 HttpRequestFailed "http_request_failed" "HTTP response code not received or indicated failure",
+    Platform 1524 HttpAuthFailed "http_auth_failed" "HTTP request failed due to bad credentials",
+    Platform 1525 HttpBadRequestId "http_bad_request_id" "HTTP response contained an unexpected X-Request-ID header",
+
+    Client 2000 ClientInvalidOperation "client_invalid_operation" "Invalid API call",
+    Client 2002 CommitReadIncomplete "commit_read_incomplete" "Commit with incomplete read",
+    Client 2003 TestSpecificationInvalid "test_specification_invalid" "Invalid test specification",
+    Client 2004 KeyOutsideLegalRange "key_outside_legal_range" "Key outside legal range",
+    Client 2005 InvertedRange "inverted_range" "Range begin key larger than end key",
+    Client 2006 InvalidOptionValue "invalid_option_value" "Option set with an invalid value",
+    Client 2007 InvalidOption "invalid_option" "Option not valid in this context",
+    Client 2008 NetworkNotSetup "network_not_setup" "Action not possible before the network is configured",
+    Client 2009 NetworkAlreadySetup "network_already_setup" "Network can be configured only once",
+    Client 2010 ReadVersionAlreadySet "read_version_already_set" "Transaction already has a read version set",
+    Client 2011 VersionInvalid "version_invalid" "Version not valid",
+    Client 2012 RangeLimitsInvalid "range_limits_invalid" "Range limits not valid",
+    Client 2013 InvalidDatabaseName "invalid_database_name" "Database name must be 'DB'",
+    Client 2014 AttributeNotFound "attribute_not_found" "Attribute not found",
+    Client 2015 FutureNotSet "future_not_set" "Future not ready",
+    Client 2016 FutureNotError "future_not_error" "Future not an error",
+    Client 2017 UsedDuringCommit "used_during_commit" "Operation issued while a commit was outstanding",
+    Client 2018 InvalidMutationType "invalid_mutation_type" "Unrecognized atomic mutation type",
+    Client 2019 AttributeTooLarge "attribute_too_large" "Attribute too large for type int",
+    Client 2020 TransactionInvalidVersion "transaction_invalid_version" "Transaction does not have a valid commit version",
+    Client 2021 NoCommitVersion "no_commit_version" "Transaction is read-only and therefore does not have a commit version",
+    Client 2022 EnvironmentVariableNetworkOptionFailed "environment_variable_network_option_failed" "Environment variable network option could not be set",
+    Client 2023 TransactionReadOnly "transaction_read_only" "Attempted to commit a transaction specified as read-only",
+    Client 2024 InvalidCacheEvictionPolicy "invalid_cache_eviction_policy" "Invalid cache eviction policy, only random and lru are supported",
+    Client 2025 NetworkCannotBeRestarted "network_cannot_be_restarted" "Network can only be started once",
+    Client 2026 BlockedFromNetworkThread "blocked_from_network_thread" "Detected a deadlock in a callback called from the network thread",
+    Client 2027 InvalidConfigDbRangeRead "invalid_config_db_range_read" "Invalid configuration database range read",
+    Client 2028 InvalidConfigDbKey "invalid_config_db_key" "Invalid configuration database key provided",
+    Client 2029 InvalidConfigPath "invalid_config_path" "Invalid configuration path",
+    Client 2030 MapperBadIndex "mapper_bad_index" "The index in K[] or V[] is not a valid number or out of range",
+    Client 2031 MapperNoSuchKey "mapper_no_such_key" "A mapped key is not set in database",
+    Client 2032 MapperBadRangeDescriptor "mapper_bad_range_decriptor" "\"{...}\" must be the last element of the mapper tuple",
+    Client 2033 QuickGetKeyValuesHasMore "quick_get_key_values_has_more" "One of the mapped range queries is too large",
+    Client 2034 QuickGetValueMiss "quick_get_value_miss" "Found a mapped key that is not served in the same SS",
+    Client 2035 QuickGetKeyValuesMiss "quick_get_key_values_miss" "Found a mapped range that is not served in the same SS",
+    Client 2036 BlobGranuleNoRyw "blob_granule_no_ryw" "Blob Granule Read Transactions must be specified as ryw-disabled",
+    Client 2037 BlobGranuleNotMaterialized "blob_granule_not_materialized" "Blob Granule Read was not materialized",
+    Client 2038 GetMappedKeyValuesHasMore "get_mapped_key_values_has_more" "getMappedRange does not support continuation for now",
+    Client 2039 GetMappedRangeReadsYourWrites "get_mapped_range_reads_your_writes" "getMappedRange tries to read data that were previously written in the transaction",
+
+    Client 2100 IncompatibleProtocolVersion "incompatible_protocol_version" "Incompatible protocol version",
+    Client 2101 TransactionTooLarge "transaction_too_large" "Transaction exceeds byte limit",
+    Client 2102 KeyTooLarge "key_too_large" "Key length exceeds limit",
+    Client 2103 ValueTooLarge "value_too_large" "Value length exceeds limit",
+    Client 2104 ConnectionStringInvalid "connection_string_invalid" "Connection string invalid",
+    Platform 2105 AddressInUse "address_in_use" "Local address in use",
+    Platform 2106 InvalidLocalAddress "invalid_local_address" "Invalid local address",
+    Platform 2107 TlsError "tls_error" "TLS error",
+    Client 2108 UnsupportedOperation "unsupported_operation" "Operation is not supported",
+    Client 2109 TooManyTags "too_many_tags" "Too many tags set on transaction",
+    Client 2110 TagTooLong "tag_too_long" "Tag set on transaction is too long",
+    Client 2111 TooManyTagThrottles "too_many_tag_throttles" "Too many tag throttles have been created",
+    SpecialKeys 2112 SpecialKeysCrossModuleRead "special_keys_cross_module_read" "Special key space range read crosses modules. Refer to the `special_key_space_relaxed' transaction option for more details.",
+    SpecialKeys 2113 SpecialKeysNoModuleFound "special_keys_no_module_found" "Special key space range read does not intersect a module. Refer to the `special_key_space_relaxed' transaction option for more details.",
+    SpecialKeys 2114 SpecialKeysWriteDisabled "special_keys_write_disabled" "Special Key space is not allowed to write by default. Refer to the `special_key_space_enable_writes` transaction option for more details.",
+    SpecialKeys 2115 SpecialKeysNoWriteModuleFound "special_keys_no_write_module_found" "Special key space key or keyrange in set or clear does not intersect a module",
+    SpecialKeys 2116 SpecialKeysCrossModuleClear "special_keys_cross_module_clear" "Special key space clear crosses modules",
+    SpecialKeys 2117 SpecialKeysApiFailure "special_keys_api_failure" "Api call through special keys failed. For more information, call get on special key 0xff0xff/error_message to get a json string of the error message.",
+    Tenant 2130 TenantNameRequired "tenant_name_required" "Tenant name must be specified to access data in the cluster",
+    Tenant 2131 TenantNotFound "tenant_not_found" "Tenant does not exist",
+    Tenant 2132 TenantAlreadyExists "tenant_already_exists" "A tenant with the given name already exists",
+    Tenant 2133 TenantNotEmpty "tenant_not_empty" "Cannot delete a non-empty tenant",
+    Tenant 2134 InvalidTenantName "invalid_tenant_name" "Tenant name cannot begin with \\xff",
+    Tenant 2135 TenantPrefixAllocatorConflict "tenant_prefix_allocator_conflict" "The database already has keys stored at the prefix allocated for the tenant",
+    Tenant 2136 TenantsDisabled "tenants_disabled" "Tenants have been disabled in the cluster",
+    Tenant 2137 UnknownTenant "unknown_tenant" "Tenant is not available from this server",
+
+    Layer 2200 ApiVersionUnset "api_version_unset" "API version is not set",
+    Layer 2201 ApiVersionAlreadySet "api_version_already_set" "API version may be set only once",
+    Layer 2202 ApiVersionInvalid "api_version_invalid" "API version not valid",
+    Layer 2203 ApiVersionNotSupported "api_version_not_supported" "API version not supported",
+    Layer 2210 ExactModeWithoutLimits "exact_mode_without_limits" "EXACT streaming mode requires limits, but none were given",
+    Layer 2250 InvalidTupleDataType "invalid_tuple_data_type" "Unrecognized data type in packed tuple",
+    Layer 2251 InvalidTupleIndex "invalid_tuple_index" "Tuple does not have element at specified index",
+    Layer 2252 KeyNotInSubspace "key_not_in_subspace" "Cannot unpack key that is not in subspace",
+    Layer 2253 ManualPrefixesNotEnabled "manual_prefixes_not_enabled" "Cannot specify a prefix unless manual prefixes are enabled",
+    Layer 2254 PrefixInPartition "prefix_in_partition" "Cannot specify a prefix in a partition",
+    Layer 2255 CannotOpenRootDirectory "cannot_open_root_directory" "Root directory cannot be opened",
+    Layer 2256 DirectoryAlreadyExists "directory_already_exists" "Directory already exists",
+    Layer 2257 DirectoryDoesNotExist "directory_does_not_exist" "Directory does not exist",
+    Layer 2258 ParentDirectoryDoesNotExist "parent_directory_does_not_exist" "Directory's parent does not exist",
+    Layer 2259 MismatchedLayer "mismatched_layer" "Directory has already been created with a different layer string",
+    Layer 2260 InvalidDirectoryLayerMetadata "invalid_directory_layer_metadata" "Invalid directory layer metadata",
+    Layer 2261 CannotMoveDirectoryBetweenPartitions "cannot_move_directory_between_partitions" "Directory cannot be moved between partitions",
+    Layer 2262 CannotUsePartitionAsSubspace "cannot_use_partition_as_subspace" "Directory partition cannot be used as subspace",
+    Layer 2263 IncompatibleDirectoryVersion "incompatible_directory_version" "Directory layer was created with an incompatible version",
+    Layer 2264 DirectoryPrefixNotEmpty "directory_prefix_not_empty" "Database has keys stored at the prefix chosen by the automatic prefix allocator",
+    Layer 2265 DirectoryPrefixInUse "directory_prefix_in_use" "Directory layer already has a conflicting prefix",
+    Layer 2266 InvalidDestinationDirectory "invalid_destination_directory" "Target directory is invalid",
+    Layer 2267 CannotModifyRootDirectory "cannot_modify_root_directory" "Root directory cannot be modified",
+    Layer 2268 InvalidUuidSize "invalid_uuid_size" "UUID is not sixteen bytes",
+
+    Backup 2300 BackupError "backup_error" "Backup error",
+    Backup 2301 RestoreError "restore_error" "Restore error",
+    Backup 2311 BackupDuplicate "backup_duplicate" "Backup duplicate request",
+    Backup 2312 BackupUnneeded "backup_unneeded" "Backup unneeded request",
+    Backup 2313 BackupBadBlockSize "backup_bad_block_size" "Backup file block size too small",
+    Backup 2314 BackupInvalidUrl "backup_invalid_url" "Backup Container URL invalid",
+    Backup 2315 BackupInvalidInfo "backup_invalid_info" "Backup Container info invalid",
+    Backup 2316 BackupCannotExpire "backup_cannot_expire" "Cannot expire requested data from backup without violating minimum restorability",
+    Backup 2317 BackupAuthMissing "backup_auth_missing" "Cannot find authentication details (such as a password or secret key) for the specified Backup Container URL",
+    Backup 2318 BackupAuthUnreadable "backup_auth_unreadable" "Cannot read or parse one or more sources of authentication information for Backup Container URLs",
+    Backup 2319 BackupDoesNotExist "backup_does_not_exist" "Backup does not exist",
+    Backup 2361 RestoreInvalidVersion "restore_invalid_version" "Invalid restore version",
+    Backup 2362 RestoreCorruptedData "restore_corrupted_data" "Corrupted backup data",
+    Backup 2363 RestoreMissingData "restore_missing_data" "Missing backup data",
+    Backup 2364 RestoreDuplicateTag "restore_duplicate_tag" "Restore duplicate request",
+    Backup 2365 RestoreUnknownTag "restore_unknown_tag" "Restore tag does not exist",
+    Backup 2366 RestoreUnknownFileType "restore_unknown_file_type" "Unknown backup/restore file type",
+    Backup 2367 RestoreUnsupportedFileVersion "restore_unsupported_file_version" "Unsupported backup file version",
+    Backup 2368 RestoreBadRead "restore_bad_read" "Unexpected number of bytes read",
+    Backup 2369 RestoreCorruptedDataPadding "restore_corrupted_data_padding" "Backup file has unexpected padding bytes",
+    Backup 2370 RestoreDestinationNotEmpty "restore_destination_not_empty" "Attempted to restore into a non-empty destination database",
+    Backup 2371 RestoreDuplicateUid "restore_duplicate_uid" "Attempted to restore using a UID that had been used for an aborted restore",
+    Backup 2381 TaskInvalidVersion "task_invalid_version" "Invalid task version",
+    Backup 2382 TaskInterrupted "task_interrupted" "Task execution stopped due to timeout, abort, or completion by another worker",
+    Layer 2400 KeyNotFound "key_not_found" "Expected key is missing",
+    Layer 2401 JsonMalformed "json_malformed" "JSON string was malformed",
+    Layer 2402 JsonEofExpected "json_eof_expected" "JSON string did not terminate where expected",
+    Backup 2500 SnapDisableTlogPopFailed "snap_disable_tlog_pop_failed" "Failed to disable tlog pops",
+    Backup 2501 SnapStorageFailed "snap_storage_failed" "Failed to snapshot storage nodes",
+    Backup 2502 SnapTlogFailed "snap_tlog_failed" "Failed to snapshot TLog nodes",
+    Backup 2503 SnapCoordFailed "snap_coord_failed" "Failed to snapshot coordinator nodes",
+    Backup 2504 SnapEnableTlogPopFailed "snap_enable_tlog_pop_failed" "Failed to enable tlog pops",
+    Backup 2505 SnapPathNotWhitelisted "snap_path_not_whitelisted" "Snapshot create binary path not whitelisted",
+    Backup 2506 SnapNotFullyRecoveredUnsupported "snap_not_fully_recovered_unsupported" "Unsupported when the cluster is not fully recovered",
+    Backup 2507 SnapLogAntiQuorumUnsupported "snap_log_anti_quorum_unsupported" "Unsupported when log anti quorum is configured",
+    Backup 2508 SnapWithRecoveryUnsupported "snap_with_recovery_unsupported" "Cluster recovery during snapshot operation not supported",
+    Backup 2509 SnapInvalidUidString "snap_invalid_uid_string" "The given uid string is not a 32-length hex string",
+
+    Internal 4000 UnknownError "unknown_error" "An unknown error occurred",
+    Internal 4100 InternalError "internal_error" "An internal error occurred",
+}
+
+impl fmt::Display for FdbErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.description() {
+            Some(description) => f.write_str(description),
+            None => write!(f, "Unknown error code {}", self.code()),
+        }
+    }
+}
+
+impl From<i32> for FdbErrorKind {
+    fn from(code: i32) -> Self {
+        Self::from_code(code)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    #[test]
+    fn test_codes_roundtrip() {
+        let mut codes = HashSet::new();
+        for kind in ALL_KINDS {
+            assert!(codes.insert(kind.code()), "duplicated code {}", kind.code());
+            assert_eq!(FdbErrorKind::from_code(kind.code()), *kind);
+            assert_ne!(kind.category(), FdbErrorCategory::Other);
+        }
+    }
+
+    #[test]
+    fn test_names() {
+        let mut names = HashSet::new();
+        for kind in ALL_KINDS {
+            let name = kind.name().unwrap();
+            assert!(names.insert(name), "duplicated name {}", name);
+            // the variants are the names in CamelCase
+            let camel_case: String = name
+                .split('_')
+                .map(|word| word[..1].to_uppercase() + &word[1..])
+                .collect();
+            if *kind != FdbErrorKind::MapperBadRangeDescriptor {
+                assert_eq!(format!("{:?}", kind), camel_case);
+            }
+        }
+    }
+
+    #[test]
+    fn test_other() {
+        let kind = FdbErrorKind::from_code(1999);
+        assert_eq!(kind, FdbErrorKind::Other(1999));
+        assert_eq!(kind.code(), 1999);
+        assert_eq!(kind.name(), None);
+        assert_eq!(kind.category(), FdbErrorCategory::Other);
+        assert_eq!(kind.to_string(), "Unknown error code 1999");
+    }
+
+    #[test]
+    fn test_display() {
+        assert_eq!(
+            FdbErrorKind::NotCommitted.to_string(),
+            "Transaction not committed due to conflict with another transaction"
+        );
+        assert_eq!(
+            FdbErrorKind::TenantNotFound.category(),
+            FdbErrorCategory::Tenant
+        );
+    }
+}
@@ -31,6 +31,8 @@ use std::pin::Pin;
 use std::ptr::NonNull;
 use std::sync::Arc;
 
+#[cfg_api_versions(min = 710)]
+pub use crate::blob_granules::FdbKeyRanges;
 #[cfg_api_versions(min = 700)]
 pub use crate::fdb_keys::FdbKeys;
 #[cfg_api_versions(min = 710)]
@@ -83,6 +85,17 @@ where
             phantom: std::marker::PhantomData,
         }
     }
+
+    /// Blocks the current thread until the future is ready, then extracts its result.
+    ///
+    /// This must not be called from the network thread, where fdb fails with
+    /// `blocked_from_network_thread`.
+    pub(crate) fn wait(mut self) -> FdbResult<T> {
+        let f = self.f.take().expect("cannot wait after resolve");
+        error::eval(unsafe { fdb_sys::fdb_future_block_until_ready(f.as_ptr()) })?;
+        error::eval(unsafe { fdb_sys::fdb_future_get_error(f.as_ptr()) })?;
+        T::try_from(f)
+    }
 }
 
 impl<T> Future for FdbFuture<T>
@@ -512,6 +525,17 @@ impl TryFrom<FdbFutureHandle> for i64 {
     }
 }
 
+#[cfg_api_versions(min = 700)]
+impl TryFrom<FdbFutureHandle> for u64 {
+    type Error = FdbError;
+
+    fn try_from(f: FdbFutureHandle) -> FdbResult<Self> {
+        let mut value: u64 = 0;
+        error::eval(unsafe { fdb_sys::fdb_future_get_uint64(f.as_ptr(), &mut value) })?;
+        Ok(value)
+    }
+}
+
 impl TryFrom<FdbFutureHandle> for () {
     type Error = FdbError;
     fn try_from(_f: FdbFutureHandle) -> FdbResult<Self> {
@@ -0,0 +1,203 @@
+// Copyright 2018 foundationdb-rs developers, https://github.com/Clikengo/foundationdb-rs/graphs/contributors
+// Copyright 2013-2018 Apple, Inc and the FoundationDB project authors.
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+//! Automatic idempotency of the transactions whose commit result is unknown.
+//!
+//! When a commit fails with `commit_unknown_result`, the transaction may or may not have been
+//! committed. With [`Idempotency`], each attempt of `Database::run_idempotent` writes a unique
+//! transaction id key under a subspace, in the same transaction, its value being the versionstamp
+//! of the commit. After an unknown result, the retry reads this key first: if it is set, the
+//! previous attempt committed and its result is returned without running it again.
+//!
+//! The check is done by the transaction of the retry, so a late commit of the previous attempt
+//! makes the retry conflict rather than run twice.
+//!
+//! The ids are kept until they are expired by [`Idempotency::expire`], or periodically by
+//! [`Idempotency::expire_periodically`]. They must be kept longer than a run can take.
+//!
+//! ```no_run
+//! use foundationdb::idempotency::Idempotency;
+//! use foundationdb::tuple::Subspace;
+//!
+//! # async fn example(db: foundationdb::Database) -> Result<(), foundationdb::FdbBindingError> {
+//! let idempotency = Idempotency::new(Subspace::from_bytes(&b"idempotency"[..]));
+//! db.run_idempotent(&idempotency, |trx, _maybe_committed| async move {
+//!     trx.atomic_op(b"counter", &1i64.to_le_bytes(), foundationdb::options::MutationType::Add);
+//!     Ok(())
+//! })
+//! .await?;
+//! # Ok(())
+//! # }
+//! ```
+
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use rand::Rng;
+
+use crate::options::MutationType;
+use crate::timer::delay;
+use crate::tuple::{pack_with_versionstamp, unpack, Subspace, Versionstamp};
+use crate::{Database, FdbBindingError, ReadTransaction, WriteTransaction};
+
+/// Where the transaction ids of the idempotent runs are stored.
+///
+/// The ids are stored in `subspace` as `(created_at, id)` keys, `created_at` being the start of
+/// the run in milliseconds since the Unix epoch, and `id` 16 random bytes.
+#[derive(Debug, Clone)]
+pub struct Idempotency {
+    subspace: Subspace,
+}
+
+impl Idempotency {
+    /// Stores the transaction ids in `subspace`, which should not be used for anything else.
+    pub fn new(subspace: Subspace) -> Self {
+        Self { subspace }
+    }
+
+    /// The subspace of the transaction ids
+    pub fn subspace(&self) -> &Subspace {
+        &self.subspace
+    }
+
+    /// Generates the id of a new run
+    pub(crate) fn new_transaction_id(&self) -> TransactionId {
+        let id: [u8; 16] = rand::thread_rng().gen();
+        TransactionId {
+            key: self
+                .subspace
+                .pack(&(unix_millis(SystemTime::now()), &id[..])),
+        }
+    }
+
+    /// Clears the transaction ids of the runs started more than `max_age` ago.
+    pub async fn expire(&self, db: &Database, max_age: Duration) -> Result<(), FdbBindingError> {
+        let cutoff = SystemTime::now()
+            .checked_sub(max_age)
+            .map_or(0, unix_millis);
+        let begin = self.subspace.range().0;
+        let end = self.subspace.pack(&(cutoff,));
+        db.run(|trx, _maybe_committed| {
+            let (begin, end) = (&begin, &end);
+            async move {
+                trx.clear_range(begin, end);
+                Ok(())
+            }
+        })
+        .await
+    }
+
+    /// Expires the transaction ids older than `max_age` every `interval`.
+    ///
+    /// The returned future only resolves if an expiration fails, and is meant to be spawned on
+    /// the executor of the application.
+    pub async fn expire_periodically(
+        &self,
+        db: &Database,
+        interval: Duration,
+        max_age: Duration,
+    ) -> Result<(), FdbBindingError> {
+        loop {
+            self.expire(db, max_age).await?;
+            delay(interval).await;
+        }
+    }
+}
+
+/// The id of an idempotent run, shared by its attempts
+pub(crate) struct TransactionId {
+    key: Vec<u8>,
+}
+
+impl TransactionId {
+    /// Writes the id in the transaction, with the versionstamp of its commit as value
+    pub(crate) fn mark<T: WriteTransaction>(&self, trx: &T) {
+        let value = pack_with_versionstamp(&Versionstamp::incomplete(0));
+        trx.atomic_op(&self.key, &value, MutationType::SetVersionstampedValue);
+    }
+
+    /// Reads the versionstamp of the attempt which committed the id, if any
+    pub(crate) async fn read<T: ReadTransaction>(
+        &self,
+        trx: &T,
+    ) -> Result<Option<Versionstamp>, FdbBindingError> {
+        match trx.get(&self.key, false).await? {
+            Some(value) => Ok(Some(unpack(&value)?)),
+            None => Ok(None),
+        }
+    }
+}
+
+fn unix_millis(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .map_or(0, |since_epoch| since_epoch.as_millis() as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::memory::MemoryDatabase;
+    use crate::{FdbError, FdbErrorKind};
+
+    #[test]
+    fn test_transaction_ids() {
+        let idempotency = Idempotency::new(Subspace::from_bytes(&b"ids"[..]));
+        let first = idempotency.new_transaction_id();
+        let second = idempotency.new_transaction_id();
+        assert_ne!(first.key, second.key);
+
+        let (created_at, id): (u64, Vec<u8>) = idempotency.subspace().unpack(&first.key).unwrap();
+        assert!(created_at > 0);
+        assert_eq!(id.len(), 16);
+
+        // the ids created before the cutoff are before its key
+        let cutoff = idempotency.subspace().pack(&(created_at + 1,));
+        assert!(first.key < cutoff);
+        let cutoff = idempotency.subspace().pack(&(created_at,));
+        assert!(first.key > cutoff);
+    }
+
+    #[test]
+    fn test_lost_commit_reply() {
+        futures::executor::block_on(async {
+            let db = MemoryDatabase::new();
+            let idempotency = Idempotency::new(Subspace::from_bytes(&b"ids"[..]));
+
+            // the attempt commits but its reply is lost: the retry finds its id, with the
+            // versionstamp of the commit
+            let committed = idempotency.new_transaction_id();
+            let trx = db.create_trx().unwrap();
+            trx.set(b"key", b"committed");
+            committed.mark(&trx);
+            trx.commit().await.unwrap();
+            let versionstamp = trx.get_versionstamp().await.unwrap();
+            trx.on_error(FdbError::from(FdbErrorKind::CommitUnknownResult))
+                .await
+                .unwrap();
+            assert_eq!(
+                committed.read(&trx).await.unwrap(),
+                Some(Versionstamp::complete(versionstamp.try_into().unwrap(), 0))
+            );
+
+            // the attempt conflicts: the retry does not find its id
+            let conflicting = idempotency.new_transaction_id();
+            let trx = db.create_trx().unwrap();
+            trx.get(b"key", false).await.unwrap();
+            trx.set(b"key", b"conflicting");
+            conflicting.mark(&trx);
+            db.run(|trx, _maybe_committed| async move {
+                trx.set(b"key", b"concurrent");
+                Ok(())
+            })
+            .await
+            .unwrap();
+            let err = trx.commit().await.unwrap_err();
+            trx.on_error(err).await.unwrap();
+            assert_eq!(conflicting.read(&trx).await.unwrap(), None);
+        });
+    }
+}
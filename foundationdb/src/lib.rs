@@ -10,23 +10,51 @@
 extern crate static_assertions;
 
 pub mod api;
+#[cfg(feature = "fdb-7_1")]
+#[deny(missing_docs)]
+pub mod blob_granules;
+#[deny(missing_docs)]
+pub mod blocking;
+#[cfg(any(
+    feature = "fdb-6_2",
+    feature = "fdb-6_3",
+    feature = "fdb-7_0",
+    feature = "fdb-7_1"
+))]
+pub mod bulk_load;
 #[cfg(any(feature = "fdb-5_1", feature = "fdb-5_2", feature = "fdb-6_0"))]
 pub mod cluster;
 mod database;
 pub mod directory;
 mod error;
+mod error_kind;
 #[cfg(any(feature = "fdb-7_0", feature = "fdb-7_1"))]
 #[deny(missing_docs)]
 pub mod fdb_keys;
 pub mod future;
+pub mod idempotency;
 mod keyselector;
+#[cfg(all(feature = "management", any(feature = "fdb-7_0", feature = "fdb-7_1")))]
+#[deny(missing_docs)]
+pub mod management;
 #[cfg(any(feature = "fdb-7_1"))]
 #[deny(missing_docs)]
 pub mod mapped_key_values;
+pub mod memory;
 /// Generated configuration types for use with the various `set_option` functions
 #[allow(clippy::all)]
 pub mod options;
+mod retry;
+#[cfg(feature = "status")]
+#[deny(missing_docs)]
+pub mod status;
+#[cfg(feature = "fdb-7_1")]
+#[deny(missing_docs)]
+pub mod tenant;
+mod timer;
+mod trace;
 mod transaction;
+mod transaction_traits;
 pub mod tuple;
 
 #[cfg(any(feature = "fdb-5_1", feature = "fdb-5_2", feature = "fdb-6_0"))]
@@ -36,8 +64,13 @@ pub use crate::database::*;
 pub use crate::error::FdbBindingError;
 pub use crate::error::FdbError;
 pub use crate::error::FdbResult;
+pub use crate::error_kind::{FdbErrorCategory, FdbErrorKind};
 pub use crate::keyselector::*;
+pub use crate::retry::{AttemptFailure, MaybeCommittedPolicy, RetryPolicy};
+#[cfg(feature = "fdb-7_1")]
+pub use crate::tenant::Tenant;
 pub use crate::transaction::*;
+pub use crate::transaction_traits::{KeyValue, KeyValues, ReadTransaction, WriteTransaction};
 
 /// Initialize the FoundationDB Client API, this can only be called once per process.
 ///
@@ -76,6 +109,59 @@ pub unsafe fn boot() -> api::NetworkAutoStop {
     network_builder.boot().expect("fdb network running")
 }
 
+/// Initialize the FoundationDB Client API, like `boot()`, without requiring the caller to stop
+/// the network before the program exits.
+///
+/// The network is stopped when the returned `NetworkGuard` is dropped, or when the program exits,
+/// see `NetworkBuilder::start`. This can only be called once per process.
+///
+/// # Examples
+///
+/// ```rust
+/// let network = foundationdb::start_network();
+/// // do some interesting things with the API...
+/// drop(network);
+/// ```
+pub fn start_network() -> api::NetworkGuard {
+    let network_builder = api::FdbApiBuilder::default()
+        .build()
+        .expect("foundationdb API to be initialized");
+    network_builder.start().expect("fdb network running")
+}
+
+/// Runs `f` with the FoundationDB Client API initialized, and stops the network once it returns
+/// or panics. This can only be called once per process.
+///
+/// # Examples
+///
+/// ```rust
+/// foundationdb::run_with_network(|| {
+///     futures::executor::block_on(async {
+///         let db = foundationdb::Database::default()?;
+///         let trx = db.create_trx()?;
+///         trx.get(b"hello", false).await?;
+///         Ok::<(), foundationdb::FdbError>(())
+///     })
+/// })
+/// .expect("failed to read");
+/// ```
+///
+/// ```rust
+/// foundationdb::run_with_network(|| {
+///     let runtime = tokio::runtime::Runtime::new().unwrap();
+///     runtime.block_on(async {
+///         // do some interesting things with the API...
+///     })
+/// });
+/// ```
+pub fn run_with_network<F, R>(f: F) -> R
+where
+    F: FnOnce() -> R,
+{
+    let _network = start_network();
+    f()
+}
+
 /// Returns the default Fdb cluster configuration file path
 #[cfg(target_os = "linux")]
 pub fn default_config_path() -> &'static str {
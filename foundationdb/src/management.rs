@@ -0,0 +1,317 @@
+// Copyright 2022 foundationdb-rs developers, https://github.com/Clikengo/foundationdb-rs/graphs/contributors
+// Copyright 2013-2018 Apple, Inc and the FoundationDB project authors.
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+//! Management of the cluster through the special keys, used in api version 700 and more.
+//!
+//! The operations of fdbcli are exposed as special keys: `\xff\xff/management/` for the
+//! exclusions, the consistency check, the data distribution and the maintenance zones,
+//! `\xff\xff/configuration/` for the coordinators and the process classes, and
+//! `\xff\xff/worker_interfaces/` for the workers. The tenants are managed with
+//! `Database::create_tenant` and its siblings.
+//!
+//! The functions of this module operate on a `Transaction`, enabling the writes to the special
+//! keys. The writes are validated when the transaction is committed, which fails with
+//! `special_keys_api_failure` if they are rejected: use [`commit`] to get the reason of the
+//! failure as a [`SpecialKeysError`].
+//!
+//! More info can be found in the [relevant documentation](https://apple.github.io/foundationdb/special-keys.html).
+
+use std::fmt;
+
+use futures::TryStreamExt;
+use serde::Deserialize;
+
+use crate::directory::strinc;
+use crate::options::TransactionOption;
+use crate::{
+    FdbBindingError, FdbError, FdbErrorKind, FdbResult, RangeOption, Transaction,
+    TransactionCommitError, TransactionCommitted,
+};
+
+const EXCLUDED_PREFIX: &[u8] = b"\xff\xff/management/excluded/";
+const FAILED_PREFIX: &[u8] = b"\xff\xff/management/failed/";
+const EXCLUDED_FORCE_KEY: &[u8] = b"\xff\xff/management/options/excluded/force";
+const FAILED_FORCE_KEY: &[u8] = b"\xff\xff/management/options/failed/force";
+const CONSISTENCY_CHECK_SUSPENDED_KEY: &[u8] = b"\xff\xff/management/consistency_check_suspended";
+const DATA_DISTRIBUTION_MODE_KEY: &[u8] = b"\xff\xff/management/data_distribution/mode";
+const MAINTENANCE_PREFIX: &[u8] = b"\xff\xff/management/maintenance/";
+const CONFIGURATION_PREFIX: &[u8] = b"\xff\xff/configuration/";
+const WORKER_INTERFACES_PREFIX: &[u8] = b"\xff\xff/worker_interfaces/";
+const ERROR_MESSAGE_KEY: &[u8] = b"\xff\xff/error_message";
+
+/// The reason why the writes to the special keys were rejected, read from
+/// `\xff\xff/error_message`
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize)]
+pub struct SpecialKeysError {
+    /// `true` if the operation may succeed if retried
+    #[serde(default)]
+    pub retriable: bool,
+    /// The fdbcli command of the operation, such as `"exclude"`
+    #[serde(default)]
+    pub command: String,
+    /// A human readable description of the error
+    #[serde(default)]
+    pub message: String,
+}
+
+impl fmt::Display for SpecialKeysError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {}", self.command, self.message)
+    }
+}
+
+impl std::error::Error for SpecialKeysError {}
+
+/// The error of a management commit
+#[derive(Debug)]
+pub enum ManagementError {
+    /// The writes to the special keys were rejected
+    SpecialKeys(SpecialKeysError),
+    /// The commit failed, it may be retried with `TransactionCommitError::on_error`
+    Commit(TransactionCommitError),
+}
+
+impl fmt::Display for ManagementError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ManagementError::SpecialKeys(err) => err.fmt(f),
+            ManagementError::Commit(err) => err.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for ManagementError {}
+
+impl From<ManagementError> for FdbBindingError {
+    fn from(err: ManagementError) -> Self {
+        match err {
+            ManagementError::SpecialKeys(err) => FdbBindingError::CustomError(Box::new(err)),
+            ManagementError::Commit(err) => FdbError::from(err).into(),
+        }
+    }
+}
+
+/// Commits a transaction writing special keys.
+///
+/// If the writes are rejected, the reason is read from `\xff\xff/error_message` and returned as
+/// a `ManagementError::SpecialKeys`.
+pub async fn commit(trx: Transaction) -> Result<TransactionCommitted, ManagementError> {
+    let err = match trx.commit().await {
+        Ok(committed) => return Ok(committed),
+        Err(err) => err,
+    };
+    if err.kind() == FdbErrorKind::SpecialKeysApiFailure {
+        if let Ok(Some(special_keys_error)) = read_error_message(err.transaction()).await {
+            return Err(ManagementError::SpecialKeys(special_keys_error));
+        }
+    }
+    Err(ManagementError::Commit(err))
+}
+
+/// Reads the reason why the writes to the special keys were rejected, `None` if they were not.
+pub async fn read_error_message(trx: &Transaction) -> FdbResult<Option<SpecialKeysError>> {
+    let message = trx.get(ERROR_MESSAGE_KEY, false).await?;
+    Ok(message.and_then(|message| serde_json::from_slice(&message).ok()))
+}
+
+/// Excludes servers, so their data is moved to other servers, and they are not recruited anymore.
+///
+/// The addresses are either `ip:port` or `ip` to exclude all the servers of a machine. The
+/// commit waits for the data to be moved, unless `force`.
+pub fn exclude_servers(trx: &Transaction, addresses: &[&str], force: bool) -> FdbResult<()> {
+    write_exclusions(trx, EXCLUDED_PREFIX, EXCLUDED_FORCE_KEY, addresses, force)
+}
+
+/// Excludes failed servers, whose data is not available anymore.
+///
+/// Unlike `exclude_servers`, the servers are removed from the cluster: they cannot rejoin it,
+/// and the data they are the only ones to have is lost. The commit fails if it would lose all
+/// the replicas of a range, unless `force`.
+pub fn exclude_failed_servers(trx: &Transaction, addresses: &[&str], force: bool) -> FdbResult<()> {
+    write_exclusions(trx, FAILED_PREFIX, FAILED_FORCE_KEY, addresses, force)
+}
+
+fn write_exclusions(
+    trx: &Transaction,
+    prefix: &[u8],
+    force_key: &[u8],
+    addresses: &[&str],
+    force: bool,
+) -> FdbResult<()> {
+    enable_writes(trx)?;
+    for address in addresses {
+        trx.set(&key(prefix, address), &[]);
+    }
+    if force {
+        trx.set(force_key, &[]);
+    }
+    Ok(())
+}
+
+/// Includes previously excluded servers, whether they were excluded as failed or not.
+pub fn include_servers(trx: &Transaction, addresses: &[&str]) -> FdbResult<()> {
+    enable_writes(trx)?;
+    for address in addresses {
+        trx.clear(&key(EXCLUDED_PREFIX, address));
+        trx.clear(&key(FAILED_PREFIX, address));
+    }
+    Ok(())
+}
+
+/// Includes all the excluded servers.
+pub fn include_all_servers(trx: &Transaction) -> FdbResult<()> {
+    enable_writes(trx)?;
+    for prefix in [EXCLUDED_PREFIX, FAILED_PREFIX] {
+        trx.clear_range(prefix, &strinc(prefix.to_vec()));
+    }
+    Ok(())
+}
+
+/// Returns the addresses of the excluded servers.
+pub async fn get_excluded(trx: &Transaction) -> FdbResult<Vec<String>> {
+    read_names(trx, EXCLUDED_PREFIX).await
+}
+
+/// Returns the addresses of the servers excluded as failed.
+pub async fn get_failed(trx: &Transaction) -> FdbResult<Vec<String>> {
+    read_names(trx, FAILED_PREFIX).await
+}
+
+/// Suspends or resumes the consistency check.
+pub fn set_consistency_check_suspended(trx: &Transaction, suspended: bool) -> FdbResult<()> {
+    enable_writes(trx)?;
+    if suspended {
+        trx.set(CONSISTENCY_CHECK_SUSPENDED_KEY, &[]);
+    } else {
+        trx.clear(CONSISTENCY_CHECK_SUSPENDED_KEY);
+    }
+    Ok(())
+}
+
+/// Returns `true` if the consistency check is suspended.
+pub async fn is_consistency_check_suspended(trx: &Transaction) -> FdbResult<bool> {
+    Ok(trx
+        .get(CONSISTENCY_CHECK_SUSPENDED_KEY, false)
+        .await?
+        .is_some())
+}
+
+/// Enables or disables the data distribution.
+pub fn set_data_distribution_enabled(trx: &Transaction, enabled: bool) -> FdbResult<()> {
+    enable_writes(trx)?;
+    trx.set(
+        DATA_DISTRIBUTION_MODE_KEY,
+        if enabled { b"1" } else { b"0" },
+    );
+    Ok(())
+}
+
+/// Puts a zone in maintenance for `seconds`, its failures not triggering data movements.
+pub fn set_maintenance(trx: &Transaction, zone_id: &str, seconds: f64) -> FdbResult<()> {
+    enable_writes(trx)?;
+    trx.set(
+        &key(MAINTENANCE_PREFIX, zone_id),
+        seconds.to_string().as_bytes(),
+    );
+    Ok(())
+}
+
+/// Ends the maintenance of a zone.
+pub fn clear_maintenance(trx: &Transaction, zone_id: &str) -> FdbResult<()> {
+    enable_writes(trx)?;
+    trx.clear(&key(MAINTENANCE_PREFIX, zone_id));
+    Ok(())
+}
+
+/// Returns the zones in maintenance, with the remaining seconds of their maintenance.
+pub async fn get_maintenance(trx: &Transaction) -> FdbResult<Vec<(String, f64)>> {
+    Ok(read_range(trx, MAINTENANCE_PREFIX)
+        .await?
+        .into_iter()
+        .map(|(zone_id, seconds)| {
+            let seconds = String::from_utf8_lossy(&seconds).parse().unwrap_or(0.0);
+            (zone_id, seconds)
+        })
+        .collect())
+}
+
+/// Writes a key of the `\xff\xff/configuration/` module, such as `coordinators/processes`.
+pub fn set_configuration(trx: &Transaction, key: &str, value: &[u8]) -> FdbResult<()> {
+    enable_writes(trx)?;
+    trx.set(&self::key(CONFIGURATION_PREFIX, key), value);
+    Ok(())
+}
+
+/// Changes the coordinators of the cluster.
+pub fn set_coordinators(trx: &Transaction, addresses: &[&str]) -> FdbResult<()> {
+    set_configuration(
+        trx,
+        "coordinators/processes",
+        addresses.join(",").as_bytes(),
+    )
+}
+
+/// Changes the class of a process, such as `"storage"`, `"transaction"` or `"default"`.
+pub fn set_process_class(trx: &Transaction, address: &str, class: &str) -> FdbResult<()> {
+    set_configuration(
+        trx,
+        &format!("process/class_type/{}", address),
+        class.as_bytes(),
+    )
+}
+
+/// Returns the workers of the cluster, as their address and their serialized interface.
+pub async fn get_worker_interfaces(trx: &Transaction) -> FdbResult<Vec<(String, Vec<u8>)>> {
+    read_range(trx, WORKER_INTERFACES_PREFIX).await
+}
+
+fn enable_writes(trx: &Transaction) -> FdbResult<()> {
+    trx.set_option(TransactionOption::SpecialKeySpaceEnableWrites)
+}
+
+fn key(prefix: &[u8], name: &str) -> Vec<u8> {
+    let mut key = prefix.to_vec();
+    key.extend_from_slice(name.as_bytes());
+    key
+}
+
+async fn read_names(trx: &Transaction, prefix: &[u8]) -> FdbResult<Vec<String>> {
+    Ok(read_range(trx, prefix)
+        .await?
+        .into_iter()
+        .map(|(name, _)| name)
+        .collect())
+}
+
+/// Reads the keys under a prefix, as the rest of the key and the value
+async fn read_range(trx: &Transaction, prefix: &[u8]) -> FdbResult<Vec<(String, Vec<u8>)>> {
+    let opt = RangeOption::from((prefix.to_vec(), strinc(prefix.to_vec())));
+    trx.get_ranges_keyvalues(opt, false)
+        .map_ok(|kv| {
+            let name = String::from_utf8_lossy(&kv.key()[prefix.len()..]).into_owned();
+            (name, kv.value().to_vec())
+        })
+        .try_collect()
+        .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_special_keys_error() {
+        let err: SpecialKeysError = serde_json::from_slice(
+            br#"{"retriable": false, "command": "exclude", "message": "Exclusion of 127.0.0.1:4500 would leave the cluster without enough storage servers"}"#,
+        )
+        .unwrap();
+        assert!(!err.retriable);
+        assert_eq!(err.command, "exclude");
+        assert!(err.to_string().starts_with("exclude: Exclusion"));
+    }
+}
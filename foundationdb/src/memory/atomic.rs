@@ -0,0 +1,172 @@
+// Copyright 2018 foundationdb-rs developers, https://github.com/Clikengo/foundationdb-rs/graphs/contributors
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+//! Evaluation of the atomic operations
+//!
+//! <https://apple.github.io/foundationdb/api-c.html#c.FDBMutationType>
+
+use crate::options::MutationType;
+use crate::{FdbError, FdbErrorKind, FdbResult};
+
+/// Maximum size of a value, used by `AppendIfFits`
+pub(super) const VALUE_SIZE_LIMIT: usize = 100_000;
+
+/// Applies `op` to the `existing` value of a key, returning the new value
+///
+/// Versionstamped operations are not handled here, as they depend on the commit version.
+pub(super) fn apply(
+    op: MutationType,
+    existing: Option<&[u8]>,
+    param: &[u8],
+) -> FdbResult<Option<Vec<u8>>> {
+    let value = match (op.code(), existing) {
+        // add
+        (2, existing) => Some(add(existing.unwrap_or(&[]), param)),
+        // and, or, xor
+        (6, None) => Some(param.to_vec()),
+        (6, Some(existing)) => Some(bitwise(existing, param, |a, b| a & b)),
+        (7, existing) => Some(bitwise(existing.unwrap_or(&[]), param, |a, b| a | b)),
+        (8, existing) => Some(bitwise(existing.unwrap_or(&[]), param, |a, b| a ^ b)),
+        // append_if_fits
+        (9, None) => Some(param.to_vec()),
+        (9, Some(existing)) if existing.len() + param.len() > VALUE_SIZE_LIMIT => {
+            Some(existing.to_vec())
+        }
+        (9, Some(existing)) => Some([existing, param].concat()),
+        // max, min
+        (12, None) | (13, None) => Some(param.to_vec()),
+        (12, Some(existing)) => Some(compare_le(existing, param, true)),
+        (13, Some(existing)) => Some(compare_le(existing, param, false)),
+        // byte_min, byte_max
+        (16, None) | (17, None) => Some(param.to_vec()),
+        (16, Some(existing)) => Some(existing.min(param).to_vec()),
+        (17, Some(existing)) => Some(existing.max(param).to_vec()),
+        // compare_and_clear
+        (20, Some(existing)) if existing == param => None,
+        (20, existing) => existing.map(<[u8]>::to_vec),
+        _ => return Err(FdbError::from(FdbErrorKind::InvalidMutationType)),
+    };
+    Ok(value)
+}
+
+/// Pads with zeros, or truncates, `existing` to the length of the parameter
+fn resize(existing: &[u8], len: usize) -> Vec<u8> {
+    let mut value = existing.to_vec();
+    value.resize(len, 0);
+    value
+}
+
+/// Little-endian addition, truncated to the length of the parameter
+fn add(existing: &[u8], param: &[u8]) -> Vec<u8> {
+    let mut value = resize(existing, param.len());
+    let mut carry = 0u16;
+    for (byte, p) in value.iter_mut().zip(param) {
+        let sum = u16::from(*byte) + u16::from(*p) + carry;
+        *byte = sum as u8;
+        carry = sum >> 8;
+    }
+    value
+}
+
+fn bitwise(existing: &[u8], param: &[u8], f: impl Fn(u8, u8) -> u8) -> Vec<u8> {
+    let mut value = resize(existing, param.len());
+    for (byte, p) in value.iter_mut().zip(param) {
+        *byte = f(*byte, *p);
+    }
+    value
+}
+
+/// Compares both values as little-endian unsigned integers, keeping the greatest if `max`
+fn compare_le(existing: &[u8], param: &[u8], max: bool) -> Vec<u8> {
+    let existing = resize(existing, param.len());
+    let existing_is_greater = existing.iter().rev().cmp(param.iter().rev()).is_gt();
+    if existing_is_greater == max {
+        existing
+    } else {
+        param.to_vec()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use foundationdb_macros::cfg_api_versions;
+
+    #[test]
+    fn test_add() {
+        let value = apply(MutationType::Add, None, &5i64.to_le_bytes()).unwrap();
+        assert_eq!(value, Some(5i64.to_le_bytes().to_vec()));
+
+        let value = apply(MutationType::Add, Some(&[0xff, 0x01]), &[0x01, 0x00, 0x00]).unwrap();
+        assert_eq!(value, Some(vec![0x00, 0x02, 0x00]));
+
+        let value = apply(MutationType::Add, Some(&(-1i64).to_le_bytes()), &[0x02]).unwrap();
+        assert_eq!(value, Some(vec![0x01]));
+    }
+
+    #[test]
+    fn test_bitwise() {
+        assert_eq!(
+            apply(MutationType::BitAnd, None, &[0x0f]).unwrap(),
+            Some(vec![0x0f])
+        );
+        assert_eq!(
+            apply(MutationType::BitAnd, Some(&[0x3c, 0xff]), &[0x0f]).unwrap(),
+            Some(vec![0x0c])
+        );
+        assert_eq!(
+            apply(MutationType::BitOr, Some(&[0x30]), &[0x0f, 0x01]).unwrap(),
+            Some(vec![0x3f, 0x01])
+        );
+        assert_eq!(
+            apply(MutationType::BitXor, Some(&[0xff]), &[0x0f]).unwrap(),
+            Some(vec![0xf0])
+        );
+    }
+
+    #[test]
+    fn test_compare() {
+        assert_eq!(
+            apply(MutationType::Max, Some(&[0x01, 0x02]), &[0x02, 0x01]).unwrap(),
+            Some(vec![0x01, 0x02])
+        );
+        assert_eq!(
+            apply(MutationType::Min, Some(&[0x01, 0x02]), &[0x02, 0x01]).unwrap(),
+            Some(vec![0x02, 0x01])
+        );
+        assert_eq!(
+            apply(MutationType::ByteMax, Some(b"abc"), b"abd").unwrap(),
+            Some(b"abd".to_vec())
+        );
+        assert_eq!(
+            apply(MutationType::ByteMin, Some(b"abc"), b"abd").unwrap(),
+            Some(b"abc".to_vec())
+        );
+    }
+
+    #[cfg_api_versions(min = 610)]
+    #[test]
+    fn test_append_and_compare_and_clear() {
+        assert_eq!(
+            apply(MutationType::AppendIfFits, Some(b"foo"), b"bar").unwrap(),
+            Some(b"foobar".to_vec())
+        );
+        let large = vec![0u8; VALUE_SIZE_LIMIT];
+        assert_eq!(
+            apply(MutationType::AppendIfFits, Some(&large), b"bar").unwrap(),
+            Some(large)
+        );
+        assert_eq!(
+            apply(MutationType::CompareAndClear, Some(b"foo"), b"foo").unwrap(),
+            None
+        );
+        assert_eq!(
+            apply(MutationType::CompareAndClear, Some(b"foo"), b"bar").unwrap(),
+            Some(b"foo".to_vec())
+        );
+    }
+}
@@ -0,0 +1,1247 @@
+// Copyright 2018 foundationdb-rs developers, https://github.com/Clikengo/foundationdb-rs/graphs/contributors
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+//! An in-memory backend, to test code using FoundationDB without a cluster
+//!
+//! [`MemoryDatabase`] and [`MemoryTransaction`] follow the API of [`Database`](crate::Database)
+//! and [`Transaction`](crate::Transaction), but neither the client library nor the network
+//! thread are needed. The data is versioned like in FoundationDB: a transaction reads a
+//! snapshot of the database at its read version, sees its own writes, and fails to commit with
+//! a retryable `not_committed` error if a key it read was written by a concurrent commit.
+//!
+//! This backend is meant for tests: every version of every key is kept forever, and reads are
+//! not optimized.
+//!
+//! ```
+//! use foundationdb::memory::MemoryDatabase;
+//! use foundationdb::options::MutationType;
+//!
+//! # futures::executor::block_on(async {
+//! let db = MemoryDatabase::new();
+//!
+//! db.run(|trx, _maybe_committed| async move {
+//!     trx.set(b"hello", b"world");
+//!     trx.atomic_op(b"counter", &1i64.to_le_bytes(), MutationType::Add);
+//!     Ok(())
+//! })
+//! .await
+//! .unwrap();
+//!
+//! let trx = db.create_trx().unwrap();
+//! assert_eq!(trx.get(b"hello", false).await.unwrap(), Some(b"world".to_vec()));
+//! # });
+//! ```
+
+mod atomic;
+mod store;
+
+use std::collections::BTreeMap;
+use std::ops::{Bound, Deref, Range};
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use futures::channel::oneshot;
+use futures::future::{BoxFuture, Either};
+use futures::{future, stream, Future, FutureExt, Stream, TryStreamExt};
+
+use crate::options::{ConflictRangeType, MutationType, TransactionOption};
+use crate::{
+    FdbBindingError, FdbError, FdbErrorKind, FdbResult, KeySelector, KeyValue, KeyValues,
+    RangeOption, ReadTransaction, WriteTransaction,
+};
+use atomic::VALUE_SIZE_LIMIT;
+use store::Store;
+
+/// Maximum size of a key, and of a key of the system key-space
+const KEY_SIZE_LIMIT: usize = 10_000;
+const SYSTEM_KEY_SIZE_LIMIT: usize = 30_000;
+
+/// Errors retried by `MemoryTransaction::on_error`
+const RETRYABLE_ERRORS: &[FdbErrorKind] = &[
+    FdbErrorKind::TransactionTooOld,
+    FdbErrorKind::FutureVersion,
+    FdbErrorKind::NotCommitted,
+    FdbErrorKind::CommitUnknownResult,
+    FdbErrorKind::ProcessBehind,
+    FdbErrorKind::TagThrottled,
+];
+
+/// End of the keys readable without the `ReadSystemKeys` option
+const KEYSPACE_END: &[u8] = b"\xff";
+
+/// Represents an in-memory database
+///
+/// Clones are handles to the same data.
+#[derive(Clone, Default)]
+pub struct MemoryDatabase {
+    store: Arc<Mutex<Store>>,
+}
+
+impl MemoryDatabase {
+    /// Create an empty database
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates a new transaction on the given database.
+    pub fn create_trx(&self) -> FdbResult<MemoryTransaction> {
+        Ok(MemoryTransaction {
+            store: self.store.clone(),
+            state: Arc::default(),
+        })
+    }
+
+    /// Runs the closure in a retry loop, committing the transaction once the closure succeeds.
+    ///
+    /// This is the in-memory counterpart of `Database::run`, the closure is given the
+    /// transaction and whether the previous attempt may have been committed.
+    pub async fn run<F, Fut, T>(&self, mut closure: F) -> Result<T, FdbBindingError>
+    where
+        F: FnMut(MemoryTransaction, bool) -> Fut,
+        Fut: Future<Output = Result<T, FdbBindingError>>,
+    {
+        let trx = self.create_trx()?;
+        let mut maybe_committed = false;
+
+        loop {
+            let err = match closure(trx.clone(), maybe_committed).await {
+                Ok(value) => match trx.commit().await {
+                    Ok(()) => return Ok(value),
+                    Err(err) => err,
+                },
+                Err(err) => match err.get_fdb_error() {
+                    Some(err) => err,
+                    None => return Err(err),
+                },
+            };
+
+            maybe_committed = err.kind() == FdbErrorKind::CommitUnknownResult;
+            trx.on_error(err).await?;
+        }
+    }
+}
+
+/// A transaction on a [`MemoryDatabase`]
+///
+/// Clones are handles to the same transaction.
+#[derive(Clone)]
+pub struct MemoryTransaction {
+    store: Arc<Mutex<Store>>,
+    state: Arc<Mutex<State>>,
+}
+
+#[derive(Default)]
+struct State {
+    read_version: Option<i64>,
+    /// Writes, in the order they were made
+    mutations: Vec<Mutation>,
+    read_conflicts: Vec<Range<Vec<u8>>>,
+    write_conflicts: Vec<Range<Vec<u8>>>,
+    /// Set by `TransactionOption::NextWriteNoWriteConflictRange`
+    next_write_no_conflict: bool,
+    /// Set by `TransactionOption::AccessSystemKeys`
+    access_system_keys: bool,
+    /// First error of an operation not returning a result, reported at commit
+    error: Option<FdbError>,
+    /// Set once committed, -1 for a read-only transaction
+    committed_version: Option<i64>,
+    versionstamp: Option<FdbResult<Vec<u8>>>,
+    versionstamp_waiters: Vec<oneshot::Sender<FdbResult<Vec<u8>>>>,
+}
+
+#[derive(Clone)]
+enum Mutation {
+    Set(Vec<u8>, Vec<u8>),
+    Clear(Vec<u8>),
+    ClearRange(Vec<u8>, Vec<u8>),
+    Atomic(Vec<u8>, Vec<u8>, MutationType),
+    VersionstampedKey(Vec<u8>, Vec<u8>),
+    VersionstampedValue(Vec<u8>, Vec<u8>),
+}
+
+impl MemoryTransaction {
+    fn lock(&self) -> (MutexGuard<'_, Store>, MutexGuard<'_, State>) {
+        // always lock the store first, to not deadlock with another transaction
+        let store = self.store.lock().expect("memory store lock poisoned");
+        let state = self.state.lock().expect("memory transaction lock poisoned");
+        (store, state)
+    }
+
+    fn write(&self, mutation: Mutation, write_conflict: Option<Range<Vec<u8>>>) {
+        let (_store, mut state) = self.lock();
+        if state.committed_version.is_none() {
+            state.mutations.push(mutation);
+            if !std::mem::take(&mut state.next_write_no_conflict) {
+                state.write_conflicts.extend(write_conflict);
+            }
+        }
+    }
+
+    fn fail(&self, err: FdbError) {
+        let (_store, mut state) = self.lock();
+        state.error.get_or_insert(err);
+    }
+
+    /// Checks a key and a value written by the transaction like the client library does
+    fn check_write(&self, key: &[u8], value: &[u8]) -> FdbResult<()> {
+        self.check_writable(key, false)?;
+        if is_key_too_large(key) {
+            return Err(FdbError::from(FdbErrorKind::KeyTooLarge));
+        }
+        if value.len() > VALUE_SIZE_LIMIT {
+            return Err(FdbError::from(FdbErrorKind::ValueTooLarge));
+        }
+        Ok(())
+    }
+
+    /// Fails with `key_outside_legal_range` if `key` is not in the key-space the transaction can
+    /// write, which ends at `\xff` unless `TransactionOption::AccessSystemKeys` is set, the end
+    /// of a range being allowed to be its end
+    fn check_writable(&self, key: &[u8], is_range_end: bool) -> FdbResult<()> {
+        let (_store, state) = self.lock();
+        let max_key: &[u8] = if state.access_system_keys {
+            b"\xff\xff"
+        } else {
+            b"\xff"
+        };
+        if key > max_key || (key == max_key && !is_range_end) {
+            return Err(FdbError::from(FdbErrorKind::KeyOutsideLegalRange));
+        }
+        Ok(())
+    }
+
+    /// Called to set an option on the transaction.
+    ///
+    /// Only `NextWriteNoWriteConflictRange` and `AccessSystemKeys` change the behavior of this
+    /// backend, the other options are accepted and ignored.
+    pub fn set_option(&self, opt: TransactionOption) -> FdbResult<()> {
+        let (_store, mut state) = self.lock();
+        match opt {
+            TransactionOption::NextWriteNoWriteConflictRange => state.next_write_no_conflict = true,
+            TransactionOption::AccessSystemKeys => state.access_system_keys = true,
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Modify the database snapshot represented by transaction to change the given
+    /// key to have the given value.
+    ///
+    /// Like with FoundationDB, the commit fails with `key_outside_legal_range`, `key_too_large`
+    /// or `value_too_large` if the key or the value cannot be written.
+    pub fn set(&self, key: &[u8], value: &[u8]) {
+        if let Err(err) = self.check_write(key, value) {
+            return self.fail(err);
+        }
+        self.write(
+            Mutation::Set(key.to_vec(), value.to_vec()),
+            Some(single_key_range(key)),
+        )
+    }
+
+    /// Modify the database snapshot represented by transaction to remove the given key from the
+    /// database.
+    pub fn clear(&self, key: &[u8]) {
+        if let Err(err) = self.check_writable(key, false) {
+            return self.fail(err);
+        }
+        // like with FoundationDB, no key can be this large, so there is nothing to clear
+        if is_key_too_large(key) {
+            return;
+        }
+        self.write(Mutation::Clear(key.to_vec()), Some(single_key_range(key)))
+    }
+
+    /// Modify the database snapshot represented by transaction to remove all keys (if any) which
+    /// are lexicographically greater than or equal to the given begin key and lexicographically
+    /// less than the given end_key.
+    pub fn clear_range(&self, begin: &[u8], end: &[u8]) {
+        if begin > end {
+            return self.fail(FdbError::from(FdbErrorKind::InvertedRange));
+        }
+        if let Err(err) = self
+            .check_writable(begin, false)
+            .and_then(|()| self.check_writable(end, true))
+        {
+            return self.fail(err);
+        }
+        self.write(
+            Mutation::ClearRange(begin.to_vec(), end.to_vec()),
+            Some(begin.to_vec()..end.to_vec()),
+        )
+    }
+
+    /// Modify the database snapshot represented by transaction to perform the operation indicated
+    /// by `op_type` with operand `param` to the value stored by the given key.
+    ///
+    /// Versionstamped keys and values are only readable once the transaction is committed.
+    pub fn atomic_op(&self, key: &[u8], param: &[u8], op_type: MutationType) {
+        // the key or the param of a versionstamped operation ends with the offset of the
+        // versionstamp
+        let checked = match op_type {
+            MutationType::SetVersionstampedKey => {
+                self.check_write(&key[..key.len().saturating_sub(4)], param)
+            }
+            MutationType::SetVersionstampedValue => {
+                self.check_write(key, &param[..param.len().saturating_sub(4)])
+            }
+            _ => self.check_write(key, param),
+        };
+        if let Err(err) = checked {
+            return self.fail(err);
+        }
+        match op_type {
+            // the key is only known at commit
+            MutationType::SetVersionstampedKey => self.write(
+                Mutation::VersionstampedKey(key.to_vec(), param.to_vec()),
+                None,
+            ),
+            MutationType::SetVersionstampedValue => self.write(
+                Mutation::VersionstampedValue(key.to_vec(), param.to_vec()),
+                Some(single_key_range(key)),
+            ),
+            _ => match atomic::apply(op_type, None, param) {
+                Ok(_) => self.write(
+                    Mutation::Atomic(key.to_vec(), param.to_vec(), op_type),
+                    Some(single_key_range(key)),
+                ),
+                Err(err) => self.fail(err),
+            },
+        }
+    }
+
+    /// Adds a conflict range to a transaction without performing the associated read or write.
+    pub fn add_conflict_range(
+        &self,
+        begin: &[u8],
+        end: &[u8],
+        ty: ConflictRangeType,
+    ) -> FdbResult<()> {
+        if begin > end {
+            return Err(FdbError::from(FdbErrorKind::InvertedRange));
+        }
+        let (_store, mut state) = self.lock();
+        let range = begin.to_vec()..end.to_vec();
+        match ty {
+            ConflictRangeType::Read => state.read_conflicts.push(range),
+            ConflictRangeType::Write => state.write_conflicts.push(range),
+        }
+        Ok(())
+    }
+
+    /// Reads a value from the database snapshot represented by transaction.
+    pub fn get(
+        &self,
+        key: &[u8],
+        snapshot: bool,
+    ) -> impl Future<Output = FdbResult<Option<Vec<u8>>>> + Send + Sync + Unpin {
+        future::ready(self.read(snapshot, |store, state, version| {
+            let value = state.apply_mutations(key, store.get(key, version))?;
+            Ok((value, Some(single_key_range(key))))
+        }))
+    }
+
+    /// Resolves a key selector against the keys in the database snapshot represented by
+    /// transaction.
+    pub fn get_key(
+        &self,
+        selector: &KeySelector,
+        snapshot: bool,
+    ) -> impl Future<Output = FdbResult<Vec<u8>>> + Send + Sync + Unpin {
+        future::ready(self.read(snapshot, |store, state, version| {
+            let view = state.view(store, version)?;
+            let key = resolve(&view, selector);
+            let conflict = if key.as_slice() < selector.key() {
+                key.clone()..key_after(selector.key())
+            } else {
+                selector.key().to_vec()..key_after(&key)
+            };
+            Ok((key, Some(conflict)))
+        }))
+    }
+
+    /// Reads all key-value pairs in the database snapshot represented by transaction (potentially
+    /// limited by `limit`) which have a key lexicographically greater than or equal to the key
+    /// resolved by the begin key selector and lexicographically less than the key resolved by the
+    /// end key selector.
+    ///
+    /// Every pair is returned at once, `target_bytes` and the streaming mode are ignored.
+    pub fn get_range(
+        &self,
+        opt: &RangeOption,
+        _iteration: usize,
+        snapshot: bool,
+    ) -> impl Future<Output = FdbResult<MemoryValues>> + Send + Sync + Unpin {
+        future::ready(self.range(opt, snapshot))
+    }
+
+    /// Gets a stream of values for the given range, see `Transaction::get_ranges`.
+    pub fn get_ranges<'a>(
+        &'a self,
+        opt: RangeOption<'a>,
+        snapshot: bool,
+    ) -> impl Stream<Item = FdbResult<MemoryValues>> + Send + Sync + Unpin + 'a {
+        stream::unfold(Some(opt), move |maybe_opt| {
+            future::ready(maybe_opt.map(|opt| {
+                let values = self.range(&opt, snapshot);
+                let next_opt = match &values {
+                    Ok(values) => next_range(opt, values),
+                    Err(..) => None,
+                };
+                (values, next_opt)
+            }))
+        })
+    }
+
+    /// Gets a stream of key-values for the given range, see `Transaction::get_ranges_keyvalues`.
+    pub fn get_ranges_keyvalues<'a>(
+        &'a self,
+        opt: RangeOption<'a>,
+        snapshot: bool,
+    ) -> impl Stream<Item = FdbResult<MemoryKeyValue>> + Unpin + 'a {
+        self.get_ranges(opt, snapshot)
+            .map_ok(|values| stream::iter(values.into_iter().map(Ok)))
+            .try_flatten()
+    }
+
+    /// Gets the read version of the transaction, the last committed version unless set with
+    /// `set_read_version`.
+    pub fn get_read_version(&self) -> impl Future<Output = FdbResult<i64>> + Send + Sync + Unpin {
+        future::ready(self.read(true, |_, _, version| Ok((version, None))))
+    }
+
+    /// Sets the snapshot read version used by a transaction.
+    pub fn set_read_version(&self, version: i64) {
+        let (_store, mut state) = self.lock();
+        state.read_version = Some(version);
+    }
+
+    /// Returns a future which will return the versionstamp which was used by any versionstamp
+    /// operations in this transaction, once it is committed.
+    pub fn get_versionstamp(
+        &self,
+    ) -> impl Future<Output = FdbResult<Vec<u8>>> + Send + Sync + Unpin {
+        let (_store, mut state) = self.lock();
+        match &state.versionstamp {
+            Some(versionstamp) => Either::Left(future::ready(versionstamp.clone())),
+            None => {
+                let (sender, receiver) = oneshot::channel();
+                state.versionstamp_waiters.push(sender);
+                Either::Right(receiver.map(|versionstamp| {
+                    versionstamp
+                        .unwrap_or_else(|_| Err(FdbError::from(FdbErrorKind::TransactionCancelled)))
+                }))
+            }
+        }
+    }
+
+    /// Attempts to commit the sets and clears previously applied to the database snapshot
+    /// represented by transaction to the actual database.
+    ///
+    /// The commit fails with a retryable `not_committed` error if a key read by the transaction
+    /// was written by a transaction committed since its read version.
+    pub fn commit(&self) -> impl Future<Output = FdbResult<()>> + Send + Sync + Unpin {
+        let (mut store, mut state) = self.lock();
+        let result = match state.committed_version {
+            Some(_) => return future::ready(Err(FdbError::from(FdbErrorKind::UsedDuringCommit))),
+            None => state.commit(&mut store),
+        };
+
+        let versionstamp = match result {
+            Ok(-1) => Err(FdbError::from(FdbErrorKind::NoCommitVersion)),
+            Ok(version) => Ok(versionstamp(version).to_vec()),
+            Err(err) => Err(err),
+        };
+        for waiter in state.versionstamp_waiters.drain(..) {
+            let _ = waiter.send(versionstamp.clone());
+        }
+        state.versionstamp = Some(versionstamp);
+
+        future::ready(result.map(|version| {
+            state.committed_version = Some(version);
+        }))
+    }
+
+    /// Retrieves the database version number at which a given transaction was committed.
+    ///
+    /// Read-only transactions have a committed version of -1.
+    pub fn committed_version(&self) -> FdbResult<i64> {
+        let (_store, state) = self.lock();
+        state
+            .committed_version
+            .ok_or_else(|| FdbError::from(FdbErrorKind::ClientInvalidOperation))
+    }
+
+    /// Resets the transaction if the error is retryable, returns the error otherwise.
+    pub fn on_error(
+        &self,
+        err: FdbError,
+    ) -> impl Future<Output = FdbResult<()>> + Send + Sync + Unpin {
+        future::ready(if RETRYABLE_ERRORS.contains(&err.kind()) {
+            self.reset();
+            Ok(())
+        } else {
+            Err(err)
+        })
+    }
+
+    /// Reset transaction to its initial state.
+    pub fn reset(&self) {
+        let (_store, mut state) = self.lock();
+        *state = State::default();
+    }
+
+    /// Runs a read at the read version of the transaction, recording its conflict range
+    fn read<T>(
+        &self,
+        snapshot: bool,
+        f: impl FnOnce(&Store, &State, i64) -> FdbResult<(T, Option<Range<Vec<u8>>>)>,
+    ) -> FdbResult<T> {
+        let (store, mut state) = self.lock();
+        if state.committed_version.is_some() {
+            return Err(FdbError::from(FdbErrorKind::UsedDuringCommit));
+        }
+        let version = *state.read_version.get_or_insert(store.version());
+        let (value, conflict) = f(&store, &state, version)?;
+        if !snapshot {
+            state.read_conflicts.extend(conflict);
+        }
+        Ok(value)
+    }
+
+    fn range(&self, opt: &RangeOption, snapshot: bool) -> FdbResult<MemoryValues> {
+        self.read(snapshot, |store, state, version| {
+            let view = state.view(store, version)?;
+            let begin = resolve(&view, &opt.begin);
+            let end = resolve(&view, &opt.end);
+            if begin >= end {
+                return Ok((MemoryValues::default(), None));
+            }
+
+            let mut key_values: Vec<MemoryKeyValue> = view
+                .range(begin.clone()..end.clone())
+                .map(|(key, value)| MemoryKeyValue {
+                    key: key.clone(),
+                    value: value.clone(),
+                })
+                .collect();
+            if opt.reverse {
+                key_values.reverse();
+            }
+
+            let limit = opt.limit.filter(|limit| *limit > 0);
+            let more = matches!(limit, Some(limit) if key_values.len() > limit);
+            if let (true, Some(limit)) = (more, limit) {
+                key_values.truncate(limit);
+            }
+
+            let conflict = match key_values.last() {
+                Some(last) if more && opt.reverse => last.key.clone()..end,
+                Some(last) if more => begin..key_after(&last.key),
+                _ => begin..end,
+            };
+            Ok((MemoryValues { key_values, more }, Some(conflict)))
+        })
+    }
+}
+
+impl State {
+    /// Value of `key` once the writes of the transaction are applied to the `stored` one
+    fn apply_mutations(&self, key: &[u8], stored: Option<&[u8]>) -> FdbResult<Option<Vec<u8>>> {
+        let mut value = stored.map(<[u8]>::to_vec);
+        for mutation in &self.mutations {
+            match mutation {
+                Mutation::Set(k, v) if k == key => value = Some(v.clone()),
+                Mutation::Clear(k) if k == key => value = None,
+                Mutation::ClearRange(begin, end) if begin.as_slice() <= key && key < end => {
+                    value = None
+                }
+                Mutation::Atomic(k, param, op) if k == key => {
+                    value = atomic::apply(*op, value.as_deref(), param)?
+                }
+                Mutation::VersionstampedValue(k, _) if k == key => {
+                    return Err(FdbError::from(FdbErrorKind::AccessedUnreadable))
+                }
+                _ => {}
+            }
+        }
+        Ok(value)
+    }
+
+    /// Every readable key-value pair, as seen by the transaction
+    fn view(&self, store: &Store, version: i64) -> FdbResult<BTreeMap<Vec<u8>, Vec<u8>>> {
+        let mut view: BTreeMap<Vec<u8>, Vec<u8>> = store
+            .range(b"".as_ref()..KEYSPACE_END, version)
+            .map(|(key, value)| (key.to_vec(), value.to_vec()))
+            .collect();
+
+        for mutation in &self.mutations {
+            match mutation {
+                Mutation::Set(k, v) if k.as_slice() < KEYSPACE_END => {
+                    view.insert(k.clone(), v.clone());
+                }
+                Mutation::Clear(k) => {
+                    view.remove(k);
+                }
+                Mutation::ClearRange(begin, end) => {
+                    view.retain(|k, _| k < begin || k >= end);
+                }
+                Mutation::Atomic(k, param, op) if k.as_slice() < KEYSPACE_END => {
+                    match atomic::apply(*op, view.get(k).map(Vec::as_slice), param)? {
+                        Some(value) => view.insert(k.clone(), value),
+                        None => view.remove(k),
+                    };
+                }
+                Mutation::VersionstampedValue(k, _) if k.as_slice() < KEYSPACE_END => {
+                    return Err(FdbError::from(FdbErrorKind::AccessedUnreadable))
+                }
+                _ => {}
+            }
+        }
+        Ok(view)
+    }
+
+    /// Checks for conflicts, and writes the mutations at a new version
+    fn commit(&mut self, store: &mut Store) -> FdbResult<i64> {
+        if let Some(err) = self.error {
+            return Err(err);
+        }
+        if self.mutations.is_empty() && self.write_conflicts.is_empty() {
+            return Ok(-1);
+        }
+
+        let read_version = *self.read_version.get_or_insert(store.version());
+        if store.has_conflict(read_version, &self.read_conflicts) {
+            return Err(FdbError::from(FdbErrorKind::NotCommitted));
+        }
+
+        let versionstamp = versionstamp(store.version() + 1);
+        let mut write_conflicts = self.write_conflicts.clone();
+        let mut mutations = Vec::with_capacity(self.mutations.len());
+        for mutation in &self.mutations {
+            let mutation = match mutation {
+                Mutation::VersionstampedKey(key, value) => {
+                    let key = substitute_versionstamp(key, &versionstamp)?;
+                    write_conflicts.push(single_key_range(&key));
+                    Mutation::Set(key, value.clone())
+                }
+                Mutation::VersionstampedValue(key, value) => {
+                    Mutation::Set(key.clone(), substitute_versionstamp(value, &versionstamp)?)
+                }
+                mutation => mutation.clone(),
+            };
+            mutations.push(mutation);
+        }
+
+        let version = store.begin_commit(write_conflicts);
+        for mutation in mutations {
+            match mutation {
+                Mutation::Set(key, value) => store.write(&key, Some(value)),
+                Mutation::Clear(key) => store.write(&key, None),
+                Mutation::ClearRange(begin, end) => store.clear_range(&begin..&end),
+                Mutation::Atomic(key, param, op) => {
+                    // the operation was validated when it was added
+                    let value = atomic::apply(op, store.get(&key, version), &param)?;
+                    store.write(&key, value)
+                }
+                Mutation::VersionstampedKey(..) | Mutation::VersionstampedValue(..) => {
+                    unreachable!("versionstamps are substituted before")
+                }
+            }
+        }
+        Ok(version)
+    }
+}
+
+/// The result of `MemoryTransaction::get_range`
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct MemoryValues {
+    key_values: Vec<MemoryKeyValue>,
+    more: bool,
+}
+
+impl MemoryValues {
+    /// `true` if there is another range after this one
+    pub fn more(&self) -> bool {
+        self.more
+    }
+}
+
+impl Deref for MemoryValues {
+    type Target = [MemoryKeyValue];
+
+    fn deref(&self) -> &Self::Target {
+        &self.key_values
+    }
+}
+
+impl AsRef<[MemoryKeyValue]> for MemoryValues {
+    fn as_ref(&self) -> &[MemoryKeyValue] {
+        self.deref()
+    }
+}
+
+impl<'a> IntoIterator for &'a MemoryValues {
+    type Item = &'a MemoryKeyValue;
+    type IntoIter = std::slice::Iter<'a, MemoryKeyValue>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.key_values.iter()
+    }
+}
+
+impl IntoIterator for MemoryValues {
+    type Item = MemoryKeyValue;
+    type IntoIter = std::vec::IntoIter<MemoryKeyValue>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.key_values.into_iter()
+    }
+}
+
+/// A key-value pair returned by `MemoryTransaction::get_range`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MemoryKeyValue {
+    key: Vec<u8>,
+    value: Vec<u8>,
+}
+
+impl MemoryKeyValue {
+    /// key
+    pub fn key(&self) -> &[u8] {
+        &self.key
+    }
+
+    /// value
+    pub fn value(&self) -> &[u8] {
+        &self.value
+    }
+}
+
+impl KeyValue for MemoryKeyValue {
+    fn key(&self) -> &[u8] {
+        &self.key
+    }
+
+    fn value(&self) -> &[u8] {
+        &self.value
+    }
+}
+
+impl KeyValues for MemoryValues {
+    fn more(&self) -> bool {
+        self.more
+    }
+}
+
+impl ReadTransaction for MemoryTransaction {
+    type Value = Vec<u8>;
+    type KeyValue = MemoryKeyValue;
+    type Values = MemoryValues;
+
+    fn set_option(&self, opt: TransactionOption) -> FdbResult<()> {
+        MemoryTransaction::set_option(self, opt)
+    }
+
+    fn get<'a>(&'a self, key: &[u8], snapshot: bool) -> BoxFuture<'a, FdbResult<Option<Vec<u8>>>> {
+        MemoryTransaction::get(self, key, snapshot).boxed()
+    }
+
+    fn get_key<'a>(
+        &'a self,
+        selector: &KeySelector,
+        snapshot: bool,
+    ) -> BoxFuture<'a, FdbResult<Vec<u8>>> {
+        MemoryTransaction::get_key(self, selector, snapshot).boxed()
+    }
+
+    fn get_range<'a>(
+        &'a self,
+        opt: &RangeOption,
+        iteration: usize,
+        snapshot: bool,
+    ) -> BoxFuture<'a, FdbResult<MemoryValues>> {
+        MemoryTransaction::get_range(self, opt, iteration, snapshot).boxed()
+    }
+
+    fn get_read_version(&self) -> BoxFuture<'_, FdbResult<i64>> {
+        MemoryTransaction::get_read_version(self).boxed()
+    }
+}
+
+impl WriteTransaction for MemoryTransaction {
+    fn set(&self, key: &[u8], value: &[u8]) {
+        MemoryTransaction::set(self, key, value)
+    }
+
+    fn clear(&self, key: &[u8]) {
+        MemoryTransaction::clear(self, key)
+    }
+
+    fn clear_range(&self, begin: &[u8], end: &[u8]) {
+        MemoryTransaction::clear_range(self, begin, end)
+    }
+
+    fn atomic_op(&self, key: &[u8], param: &[u8], op_type: MutationType) {
+        MemoryTransaction::atomic_op(self, key, param, op_type)
+    }
+
+    fn add_conflict_range(&self, begin: &[u8], end: &[u8], ty: ConflictRangeType) -> FdbResult<()> {
+        MemoryTransaction::add_conflict_range(self, begin, end, ty)
+    }
+
+    fn get_versionstamp(&self) -> BoxFuture<'_, FdbResult<Vec<u8>>> {
+        MemoryTransaction::get_versionstamp(self).boxed()
+    }
+}
+
+/// The range following `values`, like `RangeOption::next_range`
+fn next_range<'a>(mut opt: RangeOption<'a>, values: &MemoryValues) -> Option<RangeOption<'a>> {
+    if !values.more() {
+        return None;
+    }
+
+    let last_key = values.last()?.key();
+
+    if let Some(limit) = opt.limit.as_mut() {
+        *limit = limit.saturating_sub(values.len());
+        if *limit == 0 {
+            return None;
+        }
+    }
+
+    if opt.reverse {
+        opt.end.make_first_greater_or_equal(last_key);
+    } else {
+        opt.begin.make_first_greater_than(last_key);
+    }
+    Some(opt)
+}
+
+/// Resolves a key selector, clamping to the readable keyspace
+fn resolve(view: &BTreeMap<Vec<u8>, Vec<u8>>, selector: &KeySelector) -> Vec<u8> {
+    let key = selector.key();
+    // the number of keys before the anchor of the selector
+    let before = if selector.or_equal() {
+        view.range::<[u8], _>((Bound::Unbounded, Bound::Included(key)))
+            .count()
+    } else {
+        view.range::<[u8], _>((Bound::Unbounded, Bound::Excluded(key)))
+            .count()
+    };
+    let index = before as i64 - 1 + i64::from(selector.offset());
+
+    if index < 0 {
+        Vec::new()
+    } else {
+        view.keys()
+            .nth(index as usize)
+            .cloned()
+            .unwrap_or_else(|| KEYSPACE_END.to_vec())
+    }
+}
+
+/// `true` if no key this large can be stored
+fn is_key_too_large(key: &[u8]) -> bool {
+    let limit = if key.starts_with(b"\xff") {
+        SYSTEM_KEY_SIZE_LIMIT
+    } else {
+        KEY_SIZE_LIMIT
+    };
+    key.len() > limit
+}
+
+fn key_after(key: &[u8]) -> Vec<u8> {
+    let mut key = key.to_vec();
+    key.push(0);
+    key
+}
+
+fn single_key_range(key: &[u8]) -> Range<Vec<u8>> {
+    key.to_vec()..key_after(key)
+}
+
+/// The 10 bytes versionstamp of a commit: its version followed by the batch order
+fn versionstamp(version: i64) -> [u8; 10] {
+    let mut versionstamp = [0u8; 10];
+    versionstamp[..8].copy_from_slice(&version.to_be_bytes());
+    versionstamp
+}
+
+/// Replaces the placeholder of a versionstamped key or value, whose position is in the last
+/// bytes of the parameter.
+fn substitute_versionstamp(param: &[u8], versionstamp: &[u8; 10]) -> FdbResult<Vec<u8>> {
+    // before api 520, the position is stored on 2 bytes
+    let position_len = if cfg!(feature = "fdb-5_1") { 2 } else { 4 };
+    let invalid = || FdbError::from(FdbErrorKind::ClientInvalidOperation);
+
+    let split = param.len().checked_sub(position_len).ok_or_else(invalid)?;
+    let (data, position) = param.split_at(split);
+    let mut position_bytes = [0u8; 4];
+    position_bytes[..position_len].copy_from_slice(position);
+    let position = u32::from_le_bytes(position_bytes) as usize;
+    if position + versionstamp.len() > data.len() {
+        return Err(invalid());
+    }
+
+    let mut data = data.to_vec();
+    data[position..position + versionstamp.len()].copy_from_slice(versionstamp);
+    Ok(data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tuple::{pack_with_versionstamp, Subspace, Versionstamp};
+    use futures::executor::block_on;
+    use futures::StreamExt;
+
+    fn set_all(db: &MemoryDatabase, key_values: &[(&[u8], &[u8])]) {
+        let trx = db.create_trx().unwrap();
+        for (key, value) in key_values {
+            trx.set(key, value);
+        }
+        block_on(trx.commit()).unwrap();
+    }
+
+    fn keys(values: &MemoryValues) -> Vec<&[u8]> {
+        values.iter().map(MemoryKeyValue::key).collect()
+    }
+
+    #[test]
+    fn test_read_your_writes() {
+        let db = MemoryDatabase::new();
+        set_all(&db, &[(b"a", b"1"), (b"b", b"2"), (b"c", b"3")]);
+
+        let trx = db.create_trx().unwrap();
+        trx.set(b"d", b"4");
+        trx.clear(b"a");
+        trx.clear_range(b"b", b"c");
+        trx.set(b"b", b"5");
+        assert_eq!(block_on(trx.get(b"a", false)).unwrap(), None);
+        assert_eq!(block_on(trx.get(b"b", false)).unwrap(), Some(b"5".to_vec()));
+        assert_eq!(block_on(trx.get(b"d", false)).unwrap(), Some(b"4".to_vec()));
+
+        let range = block_on(trx.get_range(&RangeOption::from(b"".as_ref()..b"\xff"), 1, false));
+        assert_eq!(keys(&range.unwrap()), vec![b"b", b"c", b"d"]);
+
+        // other transactions don't see uncommitted writes
+        let other = db.create_trx().unwrap();
+        assert_eq!(
+            block_on(other.get(b"a", false)).unwrap(),
+            Some(b"1".to_vec())
+        );
+
+        block_on(trx.commit()).unwrap();
+        let trx = db.create_trx().unwrap();
+        assert_eq!(block_on(trx.get(b"a", false)).unwrap(), None);
+        assert_eq!(block_on(trx.get(b"b", false)).unwrap(), Some(b"5".to_vec()));
+    }
+
+    #[test]
+    fn test_snapshot_isolation() {
+        let db = MemoryDatabase::new();
+        set_all(&db, &[(b"a", b"1")]);
+
+        let trx = db.create_trx().unwrap();
+        assert_eq!(block_on(trx.get(b"a", true)).unwrap(), Some(b"1".to_vec()));
+        set_all(&db, &[(b"a", b"2")]);
+        assert_eq!(block_on(trx.get(b"a", true)).unwrap(), Some(b"1".to_vec()));
+
+        let trx = db.create_trx().unwrap();
+        assert_eq!(block_on(trx.get(b"a", true)).unwrap(), Some(b"2".to_vec()));
+    }
+
+    #[test]
+    fn test_key_selectors() {
+        let db = MemoryDatabase::new();
+        set_all(&db, &[(b"b", b""), (b"d", b""), (b"f", b"")]);
+        let trx = db.create_trx().unwrap();
+
+        let get_key = |selector| block_on(trx.get_key(&selector, false)).unwrap();
+        assert_eq!(
+            get_key(KeySelector::first_greater_or_equal(b"d".as_ref())),
+            b"d"
+        );
+        assert_eq!(
+            get_key(KeySelector::first_greater_than(b"d".as_ref())),
+            b"f"
+        );
+        assert_eq!(get_key(KeySelector::last_less_than(b"d".as_ref())), b"b");
+        assert_eq!(
+            get_key(KeySelector::last_less_or_equal(b"c".as_ref())),
+            b"b"
+        );
+        assert_eq!(
+            get_key(KeySelector::first_greater_or_equal(b"c".as_ref())),
+            b"d"
+        );
+        assert_eq!(
+            get_key(KeySelector::new(b"b".as_ref().into(), false, 3)),
+            b"f"
+        );
+        assert_eq!(get_key(KeySelector::last_less_than(b"b".as_ref())), b"");
+        assert_eq!(
+            get_key(KeySelector::first_greater_than(b"f".as_ref())),
+            b"\xff"
+        );
+
+        let opt = RangeOption::from((
+            KeySelector::first_greater_than(b"b".as_ref()),
+            KeySelector::first_greater_than(b"f".as_ref()),
+        ));
+        let range = block_on(trx.get_range(&opt, 1, false)).unwrap();
+        assert_eq!(keys(&range), vec![b"d", b"f"]);
+    }
+
+    #[test]
+    fn test_limits_and_reverse() {
+        let db = MemoryDatabase::new();
+        set_all(&db, &[(b"a", b""), (b"b", b""), (b"c", b""), (b"d", b"")]);
+        let trx = db.create_trx().unwrap();
+
+        let opt = RangeOption {
+            limit: Some(3),
+            ..RangeOption::from(b"a".as_ref()..b"d".as_ref())
+        };
+        let range = block_on(trx.get_range(&opt, 1, false)).unwrap();
+        assert_eq!(keys(&range), vec![b"a", b"b", b"c"]);
+        assert!(!range.more());
+
+        let opt = RangeOption {
+            limit: Some(2),
+            ..RangeOption::from(b"a".as_ref()..b"d".as_ref()).rev()
+        };
+        let range = block_on(trx.get_range(&opt, 1, false)).unwrap();
+        assert_eq!(keys(&range), vec![b"c", b"b"]);
+        assert!(range.more());
+
+        let opt = RangeOption {
+            limit: Some(3),
+            ..RangeOption::from(b"a".as_ref()..b"z".as_ref())
+        };
+        let all: Vec<MemoryKeyValue> = block_on(
+            trx.get_ranges_keyvalues(opt, false)
+                .map(Result::unwrap)
+                .collect(),
+        );
+        assert_eq!(all.len(), 3);
+    }
+
+    #[test]
+    fn test_atomic_ops() {
+        let db = MemoryDatabase::new();
+        let increment = |trx: &MemoryTransaction| {
+            trx.atomic_op(b"counter", &1i64.to_le_bytes(), MutationType::Add)
+        };
+
+        let trx = db.create_trx().unwrap();
+        increment(&trx);
+        increment(&trx);
+        assert_eq!(
+            block_on(trx.get(b"counter", true)).unwrap(),
+            Some(2i64.to_le_bytes().to_vec())
+        );
+
+        // atomic operations do not conflict with each other
+        let other = db.create_trx().unwrap();
+        increment(&other);
+        block_on(other.commit()).unwrap();
+        block_on(trx.commit()).unwrap();
+
+        let trx = db.create_trx().unwrap();
+        assert_eq!(
+            block_on(trx.get(b"counter", false)).unwrap(),
+            Some(3i64.to_le_bytes().to_vec())
+        );
+    }
+
+    #[test]
+    fn test_conflicts() {
+        let db = MemoryDatabase::new();
+        set_all(&db, &[(b"a", b"1")]);
+
+        let trx1 = db.create_trx().unwrap();
+        let trx2 = db.create_trx().unwrap();
+        block_on(trx1.get(b"a", false)).unwrap();
+        block_on(trx2.get(b"a", false)).unwrap();
+        trx1.set(b"a", b"2");
+        trx2.set(b"a", b"3");
+        block_on(trx1.commit()).unwrap();
+        let err = block_on(trx2.commit()).unwrap_err();
+        assert_eq!(err.kind(), FdbErrorKind::NotCommitted);
+
+        // snapshot reads do not conflict
+        let trx1 = db.create_trx().unwrap();
+        let trx2 = db.create_trx().unwrap();
+        block_on(trx1.get(b"a", true)).unwrap();
+        block_on(trx2.get(b"a", false)).unwrap();
+        trx1.set(b"b", b"1");
+        trx2.set(b"a", b"4");
+        block_on(trx2.commit()).unwrap();
+        block_on(trx1.commit()).unwrap();
+
+        // neither do read-only transactions
+        let trx1 = db.create_trx().unwrap();
+        block_on(trx1.get(b"a", false)).unwrap();
+        set_all(&db, &[(b"a", b"5")]);
+        block_on(trx1.commit()).unwrap();
+        assert_eq!(trx1.committed_version().unwrap(), -1);
+
+        // but explicit conflict ranges do
+        let trx1 = db.create_trx().unwrap();
+        block_on(trx1.get_read_version()).unwrap();
+        trx1.add_conflict_range(b"a", b"b", ConflictRangeType::Read)
+            .unwrap();
+        trx1.set(b"c", b"");
+        set_all(&db, &[(b"a", b"6")]);
+        assert!(block_on(trx1.commit()).is_err());
+    }
+
+    #[test]
+    fn test_write_limits() {
+        let db = MemoryDatabase::new();
+        let fails_with = |write: &dyn Fn(&MemoryTransaction), kind: FdbErrorKind| {
+            let trx = db.create_trx().unwrap();
+            write(&trx);
+            assert_eq!(block_on(trx.commit()).unwrap_err().kind(), kind);
+        };
+
+        fails_with(
+            &|trx| trx.set(b"\xff", b""),
+            FdbErrorKind::KeyOutsideLegalRange,
+        );
+        fails_with(
+            &|trx| trx.clear(b"\xffa"),
+            FdbErrorKind::KeyOutsideLegalRange,
+        );
+        fails_with(
+            &|trx| trx.clear_range(b"a", b"\xff\x00"),
+            FdbErrorKind::KeyOutsideLegalRange,
+        );
+        fails_with(
+            &|trx| trx.atomic_op(b"\xff", &1i64.to_le_bytes(), MutationType::Add),
+            FdbErrorKind::KeyOutsideLegalRange,
+        );
+        fails_with(
+            &|trx| trx.set(&vec![b'a'; KEY_SIZE_LIMIT + 1], b""),
+            FdbErrorKind::KeyTooLarge,
+        );
+        fails_with(
+            &|trx| trx.set(b"a", &vec![0; VALUE_SIZE_LIMIT + 1]),
+            FdbErrorKind::ValueTooLarge,
+        );
+
+        // the limits themselves are allowed, and the end of the user key-space can end a range
+        let trx = db.create_trx().unwrap();
+        trx.set(&vec![b'a'; KEY_SIZE_LIMIT], &vec![0; VALUE_SIZE_LIMIT]);
+        trx.clear_range(b"b", b"\xff");
+        // oversized keys do not exist, clearing them does nothing
+        trx.clear(&vec![b'b'; KEY_SIZE_LIMIT + 1]);
+        block_on(trx.commit()).unwrap();
+
+        // the system keys can be written with `AccessSystemKeys`
+        let trx = db.create_trx().unwrap();
+        trx.set_option(TransactionOption::AccessSystemKeys).unwrap();
+        trx.set(b"\xff/test", b"1");
+        trx.set(&[&b"\xff"[..], &vec![b'a'; KEY_SIZE_LIMIT]].concat(), b"1");
+        trx.clear_range(b"\xff/test", b"\xff\xff");
+        block_on(trx.commit()).unwrap();
+        fails_with(
+            &|trx| {
+                trx.set_option(TransactionOption::AccessSystemKeys).unwrap();
+                trx.set(b"\xff\xff/test", b"1")
+            },
+            FdbErrorKind::KeyOutsideLegalRange,
+        );
+    }
+
+    #[test]
+    fn test_run_retries() {
+        let db = MemoryDatabase::new();
+        let other = db.clone();
+        let mut attempts = 0;
+
+        let result = block_on(db.run(|trx, maybe_committed| {
+            attempts += 1;
+            let attempt = attempts;
+            let other = other.clone();
+            async move {
+                assert!(!maybe_committed);
+                let value = trx.get(b"key", false).await?;
+                if attempt == 1 {
+                    // a concurrent write makes this attempt conflict
+                    let concurrent = other.create_trx()?;
+                    concurrent.set(b"key", b"concurrent");
+                    concurrent.commit().await?;
+                }
+                trx.set(b"key", b"run");
+                Ok(value)
+            }
+        }));
+
+        assert!(matches!(result, Ok(Some(value)) if value == b"concurrent"));
+        assert_eq!(attempts, 2);
+    }
+
+    #[test]
+    fn test_versionstamps() {
+        let db = MemoryDatabase::new();
+        let subspace = Subspace::all().subspace(&"log");
+
+        let trx = db.create_trx().unwrap();
+        let key = pack_with_versionstamp(&("log", Versionstamp::incomplete(1)));
+        trx.atomic_op(&key, b"first", MutationType::SetVersionstampedKey);
+        let mut value = b"stamp:".to_vec();
+        value.extend_from_slice(&[0xff; 10]);
+        value.extend_from_slice(&6u32.to_le_bytes());
+        trx.atomic_op(b"value", &value, MutationType::SetVersionstampedValue);
+        assert_eq!(
+            block_on(trx.get(b"value", false)).unwrap_err().kind(),
+            FdbErrorKind::AccessedUnreadable
+        );
+
+        let versionstamp = trx.get_versionstamp();
+        block_on(trx.commit()).unwrap();
+        let versionstamp = block_on(versionstamp).unwrap();
+        assert_eq!(versionstamp.len(), 10);
+        let version = trx.committed_version().unwrap();
+        assert_eq!(versionstamp[..8], version.to_be_bytes());
+
+        let trx = db.create_trx().unwrap();
+        let range = block_on(trx.get_range(&subspace.range().into(), 1, false)).unwrap();
+        assert_eq!(range.len(), 1);
+        let unpacked: Versionstamp = subspace.unpack(range[0].key()).unwrap();
+        assert_eq!(unpacked.transaction_version(), versionstamp.as_slice());
+        assert_eq!(unpacked.user_version(), 1);
+
+        let value = block_on(trx.get(b"value", false)).unwrap().unwrap();
+        assert_eq!(&value[..6], b"stamp:");
+        assert_eq!(&value[6..], versionstamp.as_slice());
+    }
+
+    #[test]
+    fn test_directory_layer() {
+        use crate::directory::{Directory, DirectoryLayer};
+
+        let db = MemoryDatabase::new();
+        let directory = DirectoryLayer::default();
+        let path = vec![String::from("app"), String::from("users")];
+
+        let trx = db.create_trx().unwrap();
+        let created = block_on(directory.create_or_open(&trx, &path, None, None)).unwrap();
+        block_on(trx.commit()).unwrap();
+
+        let trx = db.create_trx().unwrap();
+        let opened = block_on(directory.open(&trx, &path, None)).unwrap();
+        assert_eq!(opened.bytes().unwrap(), created.bytes().unwrap());
+        let list = block_on(directory.list(&trx, &path[..1])).unwrap();
+        assert_eq!(list, vec![String::from("users")]);
+
+        assert!(block_on(directory.remove(&trx, &path)).unwrap());
+        assert!(!block_on(directory.exists(&trx, &path)).unwrap());
+    }
+}
@@ -0,0 +1,107 @@
+// Copyright 2018 foundationdb-rs developers, https://github.com/Clikengo/foundationdb-rs/graphs/contributors
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+//! Multi-version storage shared by the transactions of a `MemoryDatabase`
+
+use std::collections::BTreeMap;
+use std::ops::Range;
+
+/// Values of a key ordered by version, a `None` value being a clear
+type Versions = Vec<(i64, Option<Vec<u8>>)>;
+
+/// Every version of the keys, and the write conflict ranges of every commit
+#[derive(Default)]
+pub(super) struct Store {
+    data: BTreeMap<Vec<u8>, Versions>,
+    /// The last committed version
+    version: i64,
+    /// Write conflict ranges, by commit version
+    commits: Vec<(i64, Vec<Range<Vec<u8>>>)>,
+}
+
+impl Store {
+    /// The last committed version
+    pub(super) fn version(&self) -> i64 {
+        self.version
+    }
+
+    /// Value of `key` as seen at `version`
+    pub(super) fn get(&self, key: &[u8], version: i64) -> Option<&[u8]> {
+        self.data
+            .get(key)
+            .and_then(|versions| value_at(versions, version))
+    }
+
+    /// Key-value pairs in `range`, as seen at `version`
+    pub(super) fn range<'a>(
+        &'a self,
+        range: Range<&[u8]>,
+        version: i64,
+    ) -> impl Iterator<Item = (&'a [u8], &'a [u8])> + 'a {
+        let range = if range.start < range.end {
+            range.start.to_vec()..range.end.to_vec()
+        } else {
+            Vec::new()..Vec::new()
+        };
+        self.data.range(range).filter_map(move |(key, versions)| {
+            value_at(versions, version).map(|value| (key.as_slice(), value))
+        })
+    }
+
+    /// True if a commit after `read_version` wrote in one of the `read_ranges`
+    pub(super) fn has_conflict(&self, read_version: i64, read_ranges: &[Range<Vec<u8>>]) -> bool {
+        self.commits
+            .iter()
+            .rev()
+            .take_while(|(version, _)| *version > read_version)
+            .flat_map(|(_, write_ranges)| write_ranges)
+            .any(|write| {
+                read_ranges
+                    .iter()
+                    .any(|read| read.start < write.end && write.start < read.end)
+            })
+    }
+
+    /// Starts a new commit, returning its version
+    pub(super) fn begin_commit(&mut self, write_ranges: Vec<Range<Vec<u8>>>) -> i64 {
+        self.version += 1;
+        self.commits.push((self.version, write_ranges));
+        self.version
+    }
+
+    /// Writes a value for `key` at the version of the current commit
+    pub(super) fn write(&mut self, key: &[u8], value: Option<Vec<u8>>) {
+        let version = self.version;
+        if value.is_none() && !self.data.contains_key(key) {
+            return;
+        }
+        let versions = self.data.entry(key.to_vec()).or_default();
+        match versions.last_mut() {
+            Some((last, last_value)) if *last == version => *last_value = value,
+            _ => versions.push((version, value)),
+        }
+    }
+
+    /// Clears every key in `range` at the version of the current commit
+    pub(super) fn clear_range(&mut self, range: Range<&[u8]>) {
+        let keys: Vec<Vec<u8>> = self
+            .range(range, self.version)
+            .map(|(key, _)| key.to_vec())
+            .collect();
+        for key in keys {
+            self.write(&key, None);
+        }
+    }
+}
+
+fn value_at(versions: &[(i64, Option<Vec<u8>>)], version: i64) -> Option<&[u8]> {
+    versions
+        .iter()
+        .rev()
+        .find(|(v, _)| *v <= version)
+        .and_then(|(_, value)| value.as_deref())
+}
@@ -0,0 +1,427 @@
+// Copyright 2018 foundationdb-rs developers, https://github.com/Clikengo/foundationdb-rs/graphs/contributors
+// Copyright 2013-2018 Apple, Inc and the FoundationDB project authors.
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+//! Retry policies of `Database::run_with_policy`.
+
+use std::error::Error;
+use std::time::{Duration, Instant};
+
+use futures::future::BoxFuture;
+use futures::{Future, FutureExt};
+use rand::Rng;
+
+use crate::idempotency::Idempotency;
+use crate::{FdbBindingError, RetryableTransaction};
+
+type AttemptHook<'a> = dyn Fn(u32) + Send + Sync + 'a;
+type FailureHook<'a> = dyn Fn(&AttemptFailure) + Send + Sync + 'a;
+type CustomErrorPredicate<'a> = dyn Fn(&(dyn Error + Send + Sync)) -> bool + Send + Sync + 'a;
+#[cfg(feature = "tracing")]
+type TraceIdHook<'a> = dyn Fn() -> Option<String> + Send + Sync + 'a;
+pub(crate) type Reconcile<'a> =
+    dyn Fn(RetryableTransaction) -> BoxFuture<'a, Result<bool, FdbBindingError>> + Send + Sync + 'a;
+
+/// Controls how `Database::run_with_policy` retries the failed attempts of a transaction.
+///
+/// The default policy behaves like `Database::run`: the attempts are retried for as long as
+/// `on_error` allows it, with the backoff of FoundationDB only.
+///
+/// ```
+/// use std::time::Duration;
+/// use foundationdb::{MaybeCommittedPolicy, RetryPolicy};
+///
+/// let policy = RetryPolicy::new()
+///     .with_max_attempts(5)
+///     .with_timeout(Duration::from_secs(10))
+///     .with_backoff(Duration::from_millis(10), Duration::from_secs(1))
+///     .with_maybe_committed(MaybeCommittedPolicy::Fail)
+///     .on_failure(|failure| {
+///         eprintln!("attempt {} failed: {:?}", failure.attempt, failure.error);
+///     });
+/// ```
+#[derive(Default)]
+pub struct RetryPolicy<'a> {
+    max_attempts: Option<u32>,
+    timeout: Option<Duration>,
+    deadline: Option<Instant>,
+    backoff: Option<(Duration, Duration)>,
+    retry_custom_error: Option<Box<CustomErrorPredicate<'a>>>,
+    maybe_committed: MaybeCommittedPolicy<'a>,
+    on_attempt: Option<Box<AttemptHook<'a>>>,
+    on_failure: Option<Box<FailureHook<'a>>>,
+    #[cfg(feature = "tracing")]
+    trace_id: Option<Box<TraceIdHook<'a>>>,
+}
+
+impl<'a> RetryPolicy<'a> {
+    /// The default policy, retrying as long as `on_error` allows it.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Gives up after `max_attempts` attempts, the first one included.
+    pub fn with_max_attempts(mut self, max_attempts: u32) -> Self {
+        self.max_attempts = Some(max_attempts);
+        self
+    }
+
+    /// Gives up retrying once `timeout` elapsed since the start of the run.
+    ///
+    /// Unlike `TransactionOption::Timeout`, an attempt in progress is not cancelled.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Gives up retrying once the wall-clock `deadline` is reached.
+    pub fn with_deadline(mut self, deadline: Instant) -> Self {
+        self.deadline = Some(deadline);
+        self
+    }
+
+    /// Waits a random delay before each retry, on top of the backoff of FoundationDB.
+    ///
+    /// The delay is drawn between zero and `base * 2^(attempt - 1)`, capped to `max`, which
+    /// spreads the retries of conflicting clients.
+    pub fn with_backoff(mut self, base: Duration, max: Duration) -> Self {
+        self.backoff = Some((base, max));
+        self
+    }
+
+    /// Retries the attempts failing with a `FdbBindingError::CustomError` accepted by
+    /// `predicate`, after resetting the transaction.
+    ///
+    /// By default, custom errors are returned without retrying.
+    pub fn retry_custom_errors<P>(mut self, predicate: P) -> Self
+    where
+        P: Fn(&(dyn Error + Send + Sync)) -> bool + Send + Sync + 'a,
+    {
+        self.retry_custom_error = Some(Box::new(predicate));
+        self
+    }
+
+    /// Chooses what to do when the commit of an attempt may have succeeded.
+    pub fn with_maybe_committed(mut self, policy: MaybeCommittedPolicy<'a>) -> Self {
+        self.maybe_committed = policy;
+        self
+    }
+
+    /// Calls `hook` with the number of each attempt, starting at 1, before running it.
+    pub fn on_attempt<H>(mut self, hook: H) -> Self
+    where
+        H: Fn(u32) + Send + Sync + 'a,
+    {
+        self.on_attempt = Some(Box::new(hook));
+        self
+    }
+
+    /// Calls `hook` after each failed attempt, whether it is retried or not.
+    pub fn on_failure<H>(mut self, hook: H) -> Self
+    where
+        H: Fn(&AttemptFailure) + Send + Sync + 'a,
+    {
+        self.on_failure = Some(Box::new(hook));
+        self
+    }
+
+    /// Propagates the trace id returned by `trace_id` to the FoundationDB client trace logs.
+    ///
+    /// `trace_id` is called in the `attempt` span of each attempt, so that it can read the id of
+    /// the current trace, for example from the OpenTelemetry context of the span. The id is
+    /// recorded in the span, and set as the `TransactionOption::DebugTransactionIdentifier` of
+    /// the transaction along with `TransactionOption::LogTransaction`, which lines up the spans
+    /// with the trace logs of the client. The options are not set before API version 610.
+    ///
+    /// ```
+    /// use foundationdb::RetryPolicy;
+    ///
+    /// let policy = RetryPolicy::new().propagate_trace_id(|| {
+    ///     tracing::Span::current().id().map(|id| format!("{:016x}", id.into_u64()))
+    /// });
+    /// ```
+    #[cfg(feature = "tracing")]
+    pub fn propagate_trace_id<F>(mut self, trace_id: F) -> Self
+    where
+        F: Fn() -> Option<String> + Send + Sync + 'a,
+    {
+        self.trace_id = Some(Box::new(trace_id));
+        self
+    }
+}
+
+/// What `Database::run_with_policy` does when a commit fails with an error which may have
+/// committed the transaction, like `commit_unknown_result`.
+pub enum MaybeCommittedPolicy<'a> {
+    /// Returns the error, without retrying.
+    Fail,
+    /// Retries, the closure being told that the previous attempt may have committed.
+    ///
+    /// This is the behavior of `Database::run`.
+    Retry,
+    /// Calls the closure with the reset transaction to find out whether the previous attempt
+    /// committed.
+    ///
+    /// If it returns `true`, the result of the previous attempt is returned without running it
+    /// again, otherwise the attempt is retried as if it was not committed. It only applies to
+    /// the errors of the commit, the errors of the transactional closure being retried.
+    Reconcile(Box<Reconcile<'a>>),
+    /// Writes a unique transaction id with each attempt, and reads it after an unknown commit
+    /// result to find out whether the previous attempt committed.
+    ///
+    /// See the [`crate::idempotency`] module. Like `Reconcile`, it only applies to the errors of
+    /// the commit.
+    Idempotent(Idempotency),
+}
+
+impl<'a> MaybeCommittedPolicy<'a> {
+    /// Reconciles the maybe committed attempts with `reconcile`, see
+    /// [`MaybeCommittedPolicy::Reconcile`].
+    pub fn reconcile<F, Fut>(reconcile: F) -> Self
+    where
+        F: Fn(RetryableTransaction) -> Fut + Send + Sync + 'a,
+        Fut: Future<Output = Result<bool, FdbBindingError>> + Send + 'a,
+    {
+        MaybeCommittedPolicy::Reconcile(Box::new(move |trx| reconcile(trx).boxed()))
+    }
+}
+
+impl<'a> Default for MaybeCommittedPolicy<'a> {
+    fn default() -> Self {
+        MaybeCommittedPolicy::Retry
+    }
+}
+
+/// A failed attempt, given to the `RetryPolicy::on_failure` hook.
+#[derive(Debug)]
+pub struct AttemptFailure<'e> {
+    /// The number of the attempt, starting at 1
+    pub attempt: u32,
+    /// The error of the attempt
+    pub error: &'e FdbBindingError,
+    /// The time elapsed since the start of the run
+    pub elapsed: Duration,
+    /// Whether the attempt is retried
+    pub will_retry: bool,
+}
+
+/// The state of a run following a `RetryPolicy`
+pub(crate) struct Retrier<'p, 'a> {
+    policy: &'p RetryPolicy<'a>,
+    attempt: u32,
+    started: Instant,
+    deadline: Option<Instant>,
+}
+
+impl<'p, 'a> Retrier<'p, 'a> {
+    pub(crate) fn new(policy: &'p RetryPolicy<'a>) -> Self {
+        let started = Instant::now();
+        let timeout = policy.timeout.map(|timeout| started + timeout);
+        let deadline = match (policy.deadline, timeout) {
+            (Some(deadline), Some(timeout)) => Some(deadline.min(timeout)),
+            (deadline, timeout) => deadline.or(timeout),
+        };
+        Self {
+            policy,
+            attempt: 0,
+            started,
+            deadline,
+        }
+    }
+
+    /// Counts a new attempt
+    pub(crate) fn begin_attempt(&mut self) {
+        self.attempt += 1;
+        if let Some(on_attempt) = &self.policy.on_attempt {
+            on_attempt(self.attempt);
+        }
+    }
+
+    /// The number of attempts so far
+    pub(crate) fn attempts(&self) -> u32 {
+        self.attempt
+    }
+
+    /// The time elapsed since the start of the run
+    pub(crate) fn elapsed(&self) -> Duration {
+        self.started.elapsed()
+    }
+
+    /// Whether the attempts and the deadline of the policy allow another attempt
+    pub(crate) fn within_limits(&self) -> bool {
+        self.policy
+            .max_attempts
+            .map(|max_attempts| self.attempt < max_attempts)
+            .unwrap_or(true)
+            && self
+                .deadline
+                .map(|deadline| Instant::now() < deadline)
+                .unwrap_or(true)
+    }
+
+    /// Notifies the failure of the current attempt
+    pub(crate) fn notify_failure(&self, error: &FdbBindingError, will_retry: bool) {
+        if let Some(on_failure) = &self.policy.on_failure {
+            on_failure(&AttemptFailure {
+                attempt: self.attempt,
+                error,
+                elapsed: self.elapsed(),
+                will_retry,
+            });
+        }
+    }
+
+    /// Whether the error is a custom error that the policy retries
+    pub(crate) fn retries_custom_error(&self, error: &FdbBindingError) -> bool {
+        match (error, &self.policy.retry_custom_error) {
+            (FdbBindingError::CustomError(error), Some(predicate)) => predicate(error.as_ref()),
+            _ => false,
+        }
+    }
+
+    /// Whether the maybe committed errors are returned without retrying
+    pub(crate) fn fails_maybe_committed(&self) -> bool {
+        matches!(self.policy.maybe_committed, MaybeCommittedPolicy::Fail)
+    }
+
+    /// The closure reconciling the maybe committed attempts, if any
+    pub(crate) fn reconcile(&self) -> Option<&'p Reconcile<'a>> {
+        match &self.policy.maybe_committed {
+            MaybeCommittedPolicy::Reconcile(reconcile) => Some(reconcile.as_ref()),
+            _ => None,
+        }
+    }
+
+    /// Where the ids of the attempts are written, if the policy is idempotent
+    pub(crate) fn idempotency(&self) -> Option<&'p Idempotency> {
+        match &self.policy.maybe_committed {
+            MaybeCommittedPolicy::Idempotent(idempotency) => Some(idempotency),
+            _ => None,
+        }
+    }
+
+    /// The trace id to propagate to FoundationDB, if the policy propagates them
+    #[cfg(feature = "tracing")]
+    pub(crate) fn trace_id(&self) -> Option<String> {
+        self.policy
+            .trace_id
+            .as_ref()
+            .and_then(|trace_id| trace_id())
+    }
+
+    /// The jittered delay to wait before retrying the current attempt, never past the deadline
+    pub(crate) fn backoff(&self) -> Duration {
+        let (base, max) = match self.policy.backoff {
+            Some(backoff) => backoff,
+            None => return Duration::from_secs(0),
+        };
+        let cap = 1u32
+            .checked_shl(self.attempt.saturating_sub(1))
+            .and_then(|factor| base.checked_mul(factor))
+            .map_or(max, |cap| cap.min(max));
+        let cap_nanos = cap.as_nanos().min(u64::MAX as u128) as u64;
+        let backoff = Duration::from_nanos(rand::thread_rng().gen_range(0..=cap_nanos));
+        match self.deadline {
+            Some(deadline) => backoff.min(deadline.saturating_duration_since(Instant::now())),
+            None => backoff,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    #[test]
+    fn test_limits() {
+        let policy = RetryPolicy::new().with_max_attempts(2);
+        let mut retrier = Retrier::new(&policy);
+        retrier.begin_attempt();
+        assert!(retrier.within_limits());
+        retrier.begin_attempt();
+        assert!(!retrier.within_limits());
+
+        let policy = RetryPolicy::new().with_deadline(Instant::now());
+        let mut retrier = Retrier::new(&policy);
+        retrier.begin_attempt();
+        assert!(!retrier.within_limits());
+
+        let policy = RetryPolicy::new().with_timeout(Duration::from_secs(3600));
+        let mut retrier = Retrier::new(&policy);
+        retrier.begin_attempt();
+        assert!(retrier.within_limits());
+    }
+
+    #[test]
+    fn test_backoff() {
+        let policy = RetryPolicy::new();
+        let mut retrier = Retrier::new(&policy);
+        retrier.begin_attempt();
+        assert_eq!(retrier.backoff(), Duration::from_secs(0));
+
+        let base = Duration::from_millis(10);
+        let max = Duration::from_millis(50);
+        let policy = RetryPolicy::new().with_backoff(base, max);
+        let mut retrier = Retrier::new(&policy);
+        for attempt in 1..=40 {
+            retrier.begin_attempt();
+            let cap = if attempt <= 3 {
+                base * (1 << (attempt - 1))
+            } else {
+                max
+            };
+            for _ in 0..10 {
+                assert!(retrier.backoff() <= cap);
+            }
+        }
+
+        // the backoff never exceeds the deadline
+        let policy = RetryPolicy::new()
+            .with_backoff(max, max)
+            .with_deadline(Instant::now());
+        let mut retrier = Retrier::new(&policy);
+        retrier.begin_attempt();
+        assert_eq!(retrier.backoff(), Duration::from_secs(0));
+    }
+
+    #[test]
+    fn test_hooks() {
+        let attempts = AtomicU32::new(0);
+        let failures = AtomicU32::new(0);
+        let policy = RetryPolicy::new()
+            .on_attempt(|attempt| attempts.store(attempt, Ordering::SeqCst))
+            .on_failure(|failure| {
+                assert!(failure.will_retry);
+                failures.fetch_add(1, Ordering::SeqCst);
+            });
+        let mut retrier = Retrier::new(&policy);
+        retrier.begin_attempt();
+        retrier.begin_attempt();
+        retrier.notify_failure(&FdbBindingError::ReferenceToTransactionKept, true);
+        assert_eq!(attempts.load(Ordering::SeqCst), 2);
+        assert_eq!(failures.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn test_custom_errors() {
+        let custom = |message: &str| FdbBindingError::CustomError(message.into());
+
+        let policy = RetryPolicy::new();
+        let retrier = Retrier::new(&policy);
+        assert!(!retrier.retries_custom_error(&custom("busy")));
+
+        let policy = RetryPolicy::new().retry_custom_errors(|err| err.to_string() == "busy");
+        let retrier = Retrier::new(&policy);
+        assert!(retrier.retries_custom_error(&custom("busy")));
+        assert!(!retrier.retries_custom_error(&custom("invalid")));
+        assert!(!retrier.retries_custom_error(&FdbBindingError::ReferenceToTransactionKept));
+        assert!(!retrier.fails_maybe_committed());
+        assert!(retrier.reconcile().is_none());
+        assert!(retrier.idempotency().is_none());
+    }
+}
@@ -0,0 +1,408 @@
+// Copyright 2022 foundationdb-rs developers, https://github.com/Clikengo/foundationdb-rs/graphs/contributors
+// Copyright 2013-2018 Apple, Inc and the FoundationDB project authors.
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+//! Typed model of the status of a cluster, as read from the `\xff\xff/status/json` special key.
+//!
+//! Only a subset of the status document is modeled: the fields which are not are ignored, and all
+//! the fields are optional, as most of them are missing when the cluster is unavailable.
+//!
+//! More info can be found in the [relevant documentation](https://apple.github.io/foundationdb/mr-status.html).
+//!
+//! ```
+//! use foundationdb::status::Status;
+//!
+//! let status = Status::from_slice(br#"{
+//!     "client": { "database_status": { "available": true, "healthy": true } },
+//!     "cluster": { "database_available": true, "data": { "state": { "healthy": true } } }
+//! }"#)?;
+//! assert!(status.is_available());
+//! assert!(status.is_healthy());
+//! # Ok::<(), serde_json::Error>(())
+//! ```
+
+use std::collections::HashMap;
+
+use serde::Deserialize;
+
+use crate::{Database, FdbBindingError, Transaction};
+
+const STATUS_KEY: &[u8] = b"\xff\xff/status/json";
+
+/// The status of a cluster
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct Status {
+    /// The status of the cluster, as seen by the client
+    pub client: ClientStatus,
+    /// The status of the cluster, as reported by the cluster controller
+    pub cluster: ClusterStatus,
+}
+
+impl Status {
+    /// Deserializes the JSON status document.
+    pub fn from_slice(json: &[u8]) -> serde_json::Result<Status> {
+        serde_json::from_slice(json)
+    }
+
+    /// Reads the status of the cluster with the given transaction.
+    pub async fn read(trx: &Transaction) -> Result<Status, FdbBindingError> {
+        let json = trx.get(STATUS_KEY, false).await?;
+        let json = json.as_deref().unwrap_or(b"{}");
+        Status::from_slice(json).map_err(|err| FdbBindingError::CustomError(Box::new(err)))
+    }
+
+    /// `true` if the database accepts reads and writes
+    pub fn is_available(&self) -> bool {
+        self.cluster
+            .database_available
+            .or(self.client.database_status.available)
+            .unwrap_or(false)
+    }
+
+    /// `true` if the database is available and its data has the configured replication
+    pub fn is_healthy(&self) -> bool {
+        let data_healthy = self.cluster.data.state.healthy;
+        let healthy = match self.client.database_status.healthy {
+            Some(healthy) => healthy && data_healthy != Some(false),
+            None => data_healthy.unwrap_or(false),
+        };
+        self.is_available() && healthy
+    }
+
+    /// The messages reported by the client and the cluster
+    pub fn messages(&self) -> impl Iterator<Item = &Message> {
+        self.client.messages.iter().chain(&self.cluster.messages)
+    }
+}
+
+/// The status of the cluster, as seen by the client
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct ClientStatus {
+    /// The availability of the database
+    pub database_status: DatabaseStatus,
+    /// The reachability of the coordinators
+    pub coordinators: Coordinators,
+    /// The issues seen by the client
+    pub messages: Vec<Message>,
+    /// The time of the status, in seconds since the epoch
+    pub timestamp: Option<i64>,
+}
+
+/// The availability of the database, as seen by the client
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct DatabaseStatus {
+    /// `true` if the database accepts reads and writes
+    pub available: Option<bool>,
+    /// `true` if the database is available and healthy
+    pub healthy: Option<bool>,
+}
+
+/// The coordinators of the cluster
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct Coordinators {
+    /// The coordinators of the connection string
+    pub coordinators: Vec<Coordinator>,
+    /// `true` if a majority of the coordinators are reachable
+    pub quorum_reachable: Option<bool>,
+}
+
+/// A coordinator of the cluster
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct Coordinator {
+    /// The address of the coordinator
+    pub address: String,
+    /// `true` if the client can reach the coordinator
+    pub reachable: Option<bool>,
+}
+
+/// An issue reported in the status
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct Message {
+    /// The identifier of the issue, such as `"unreachable_processes"`
+    pub name: String,
+    /// A human readable description of the issue
+    pub description: Option<String>,
+}
+
+/// The status of the cluster, as reported by the cluster controller
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct ClusterStatus {
+    /// `true` if the database accepts reads and writes
+    pub database_available: Option<bool>,
+    /// The connection string of the cluster
+    pub connection_string: Option<String>,
+    /// The generation of the cluster, incremented on each recovery
+    pub generation: Option<i64>,
+    /// The protocol version of the cluster, in hexadecimal
+    pub protocol_version: Option<String>,
+    /// `true` if all the data is replicated in every region
+    pub full_replication: Option<bool>,
+    /// The state of the recovery of the transaction system
+    pub recovery_state: RecoveryState,
+    /// The failures the cluster can tolerate
+    pub fault_tolerance: FaultTolerance,
+    /// The state of the data distribution
+    pub data: DataStatus,
+    /// The state of the ratekeeper
+    pub qos: Qos,
+    /// The latencies measured by the cluster controller
+    pub latency_probe: LatencyProbe,
+    /// The processes of the cluster, by process id
+    pub processes: HashMap<String, Process>,
+    /// The issues of the cluster
+    pub messages: Vec<Message>,
+}
+
+/// The state of the recovery of the transaction system
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct RecoveryState {
+    /// The name of the state, `"fully_recovered"` once the recovery is done
+    pub name: Option<String>,
+    /// A human readable description of the state
+    pub description: Option<String>,
+}
+
+/// The failures the cluster can tolerate
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct FaultTolerance {
+    /// The number of zones which can fail without losing availability
+    pub max_zone_failures_without_losing_availability: Option<i64>,
+    /// The number of zones which can fail without losing data
+    pub max_zone_failures_without_losing_data: Option<i64>,
+}
+
+/// The state of the data distribution
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct DataStatus {
+    /// The replication state of the data
+    pub state: DataState,
+    /// The logical size of the key-value pairs
+    pub total_kv_size_bytes: Option<i64>,
+    /// The size of the data on the disks, replicas included
+    pub total_disk_used_bytes: Option<i64>,
+    /// The number of shards
+    pub partitions_count: Option<i64>,
+    /// The average size of a shard
+    pub average_partition_size_bytes: Option<i64>,
+    /// The data being moved between storage servers
+    pub moving_data: MovingData,
+}
+
+/// The replication state of the data
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct DataState {
+    /// `true` if all the data has the configured replication
+    pub healthy: Option<bool>,
+    /// The name of the state, such as `"healthy"` or `"healing"`
+    pub name: Option<String>,
+    /// A human readable description of the state
+    pub description: Option<String>,
+    /// The number of replicas of the least replicated data
+    pub min_replicas_remaining: Option<i64>,
+}
+
+/// The data being moved between storage servers
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct MovingData {
+    /// The data being moved
+    pub in_flight_bytes: Option<i64>,
+    /// The data waiting to be moved
+    pub in_queue_bytes: Option<i64>,
+    /// The data moved since the data distributor started
+    pub total_written_bytes: Option<i64>,
+}
+
+/// The state of the ratekeeper
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct Qos {
+    /// What limits the rate of the transactions
+    pub performance_limited_by: PerformanceLimitedBy,
+    /// What limits the rate of the batch priority transactions
+    pub batch_performance_limited_by: PerformanceLimitedBy,
+    /// The rate of transactions allowed by the ratekeeper
+    pub transactions_per_second_limit: Option<f64>,
+    /// The rate of batch priority transactions allowed by the ratekeeper
+    pub batch_transactions_per_second_limit: Option<f64>,
+    /// The rate of transactions started
+    pub released_transactions_per_second: Option<f64>,
+    /// The largest queue of a log server
+    pub worst_queue_bytes_log_server: Option<i64>,
+    /// The largest queue of a storage server
+    pub worst_queue_bytes_storage_server: Option<i64>,
+    /// The largest lag of a storage server behind the log servers
+    pub worst_data_lag_storage_server: Lag,
+    /// The largest lag of the durable version of a storage server
+    pub worst_durability_lag_storage_server: Lag,
+}
+
+/// What limits the rate of the transactions
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct PerformanceLimitedBy {
+    /// The identifier of the limit, `"workload"` if the rate is not limited
+    pub name: Option<String>,
+    /// A human readable description of the limit
+    pub description: Option<String>,
+}
+
+/// A lag of a server
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct Lag {
+    /// The lag in versions
+    pub versions: Option<i64>,
+    /// The lag in seconds
+    pub seconds: Option<f64>,
+}
+
+/// The latencies measured by the cluster controller, in seconds
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct LatencyProbe {
+    /// The time to get a read version at the default priority
+    pub transaction_start_seconds: Option<f64>,
+    /// The time to get a read version at the immediate priority
+    pub immediate_priority_transaction_start_seconds: Option<f64>,
+    /// The time to get a read version at the batch priority
+    pub batch_priority_transaction_start_seconds: Option<f64>,
+    /// The time to read a key
+    pub read_seconds: Option<f64>,
+    /// The time to commit a transaction
+    pub commit_seconds: Option<f64>,
+}
+
+/// A process of the cluster
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct Process {
+    /// The address of the process
+    pub address: String,
+    /// The class of the process, such as `"storage"` or `"unset"`
+    pub class_type: Option<String>,
+    /// The id of the machine of the process
+    pub machine_id: Option<String>,
+    /// The fault domain of the process
+    pub fault_domain: Option<String>,
+    /// The version of FoundationDB run by the process
+    pub version: Option<String>,
+    /// The time since the process started
+    pub uptime_seconds: Option<f64>,
+    /// `true` if the process is excluded from the cluster
+    pub excluded: Option<bool>,
+    /// `true` if the process is considered degraded
+    pub degraded: Option<bool>,
+    /// The roles of the process
+    pub roles: Vec<Role>,
+    /// The issues of the process
+    pub messages: Vec<Message>,
+}
+
+impl Process {
+    /// `true` if the process has the given role, such as `"storage"` or `"log"`
+    pub fn has_role(&self, role: &str) -> bool {
+        self.roles.iter().any(|r| r.role == role)
+    }
+}
+
+/// A role of a process
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct Role {
+    /// The name of the role, such as `"storage"`, `"log"` or `"commit_proxy"`
+    pub role: String,
+    /// The id of the role
+    pub id: Option<String>,
+}
+
+impl Database {
+    /// Reads the status of the cluster, from the `\xff\xff/status/json` special key.
+    pub async fn get_status(&self) -> Result<Status, FdbBindingError> {
+        self.run(|trx, _maybe_committed| async move { Status::read(&trx).await })
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_status_unavailable() {
+        let status = Status::from_slice(
+            br#"{
+                "client": {
+                    "coordinators": {
+                        "coordinators": [{ "address": "127.0.0.1:4500", "reachable": false }],
+                        "quorum_reachable": false
+                    },
+                    "database_status": { "available": false, "healthy": false },
+                    "messages": [{ "name": "no_cluster_controller", "description": "..." }]
+                },
+                "cluster": {}
+            }"#,
+        )
+        .unwrap();
+        assert!(!status.is_available());
+        assert!(!status.is_healthy());
+        assert_eq!(status.messages().count(), 1);
+        assert_eq!(status.client.coordinators.quorum_reachable, Some(false));
+    }
+
+    #[test]
+    fn test_status_cluster() {
+        let status = Status::from_slice(
+            br#"{
+                "client": { "database_status": { "available": true, "healthy": true } },
+                "cluster": {
+                    "database_available": true,
+                    "unknown_field": [1, 2, 3],
+                    "data": {
+                        "state": { "healthy": false, "name": "healing", "min_replicas_remaining": 1 },
+                        "moving_data": { "in_flight_bytes": 1024 }
+                    },
+                    "qos": {
+                        "performance_limited_by": { "name": "workload" },
+                        "worst_data_lag_storage_server": { "versions": 1000, "seconds": 0.001 }
+                    },
+                    "latency_probe": { "commit_seconds": 0.002 },
+                    "processes": {
+                        "abcd": {
+                            "address": "127.0.0.1:4500",
+                            "roles": [{ "role": "storage", "id": "1234" }, { "role": "log" }]
+                        }
+                    }
+                }
+            }"#,
+        )
+        .unwrap();
+        assert!(status.is_available());
+        assert!(!status.is_healthy());
+        assert_eq!(status.cluster.data.state.name.as_deref(), Some("healing"));
+        assert_eq!(status.cluster.data.moving_data.in_flight_bytes, Some(1024));
+        assert_eq!(
+            status.cluster.qos.worst_data_lag_storage_server.versions,
+            Some(1000)
+        );
+        assert_eq!(status.cluster.latency_probe.commit_seconds, Some(0.002));
+        let process = &status.cluster.processes["abcd"];
+        assert!(process.has_role("log"));
+        assert!(!process.has_role("commit_proxy"));
+    }
+}
@@ -0,0 +1,175 @@
+// Copyright 2022 foundationdb-rs developers, https://github.com/Clikengo/foundationdb-rs/graphs/contributors
+// Copyright 2013-2018 Apple, Inc and the FoundationDB project authors.
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+//! Tenants, used in api version 710 and more.
+//!
+//! A tenant is a named key-space of the cluster. The transactions created from a [`Tenant`] can
+//! only read and write the keys of this tenant, the isolation being enforced by the cluster.
+//!
+//! Tenants are experimental in FDB 7.1, and need the cluster to be configured with a
+//! `tenant_mode`. They are created, deleted and listed through the
+//! `\xff\xff/management/tenant_map/` special keys.
+//!
+//! More info can be found in the [relevant documentation](https://apple.github.io/foundationdb/tenants.html).
+
+use std::ptr::NonNull;
+
+use foundationdb_sys as fdb_sys;
+use futures::Future;
+
+use crate::database::run_retryable;
+use crate::options::TransactionOption;
+use crate::transaction::fdb_len;
+use crate::{
+    error, Database, FdbBindingError, FdbError, FdbErrorKind, FdbResult, RangeOption,
+    RetryableTransaction, Transaction,
+};
+
+const TENANT_MAP_PREFIX: &[u8] = b"\xff\xff/management/tenant_map/";
+
+/// Represents a FoundationDB tenant
+///
+/// The transactions of a tenant operate on the key-space of this tenant, the keys being relative
+/// to it.
+pub struct Tenant {
+    inner: NonNull<fdb_sys::FDBTenant>,
+}
+unsafe impl Send for Tenant {}
+unsafe impl Sync for Tenant {}
+impl Drop for Tenant {
+    fn drop(&mut self) {
+        unsafe {
+            fdb_sys::fdb_tenant_destroy(self.inner.as_ptr());
+        }
+    }
+}
+
+impl Tenant {
+    /// Creates a new transaction on the given tenant.
+    pub fn create_trx(&self) -> FdbResult<Transaction> {
+        let mut trx: *mut fdb_sys::FDBTransaction = std::ptr::null_mut();
+        let err = unsafe { fdb_sys::fdb_tenant_create_transaction(self.inner.as_ptr(), &mut trx) };
+        error::eval(err)?;
+        Ok(Transaction::new(NonNull::new(trx).expect(
+            "fdb_tenant_create_transaction to not return null if there is no error",
+        )))
+    }
+
+    fn create_retryable_trx(&self) -> FdbResult<RetryableTransaction> {
+        Ok(RetryableTransaction::new(self.create_trx()?))
+    }
+
+    /// Runs a transactional function against this tenant with retry logic.
+    ///
+    /// See `Database::run`.
+    pub async fn run<F, Fut, T>(&self, closure: F) -> Result<T, FdbBindingError>
+    where
+        F: Fn(RetryableTransaction, bool) -> Fut,
+        Fut: Future<Output = Result<T, FdbBindingError>>,
+    {
+        run_retryable(self.create_retryable_trx()?, closure).await
+    }
+}
+
+impl Database {
+    /// Opens a tenant of the database.
+    ///
+    /// The tenant is not checked to exist, using its transactions fails with `tenant_not_found`
+    /// if it does not.
+    pub fn open_tenant(&self, tenant_name: &[u8]) -> FdbResult<Tenant> {
+        let mut tenant: *mut fdb_sys::FDBTenant = std::ptr::null_mut();
+        let err = unsafe {
+            fdb_sys::fdb_database_open_tenant(
+                self.inner.as_ptr(),
+                tenant_name.as_ptr(),
+                fdb_len(tenant_name.len(), "tenant_name"),
+                &mut tenant,
+            )
+        };
+        error::eval(err)?;
+        Ok(Tenant {
+            inner: NonNull::new(tenant)
+                .expect("fdb_database_open_tenant to not return null if there is no error"),
+        })
+    }
+
+    /// Creates a tenant, failing with `tenant_already_exists` if it exists.
+    pub async fn create_tenant(&self, tenant_name: &[u8]) -> Result<(), FdbBindingError> {
+        let key = tenant_key(tenant_name);
+        self.run(|trx, maybe_committed| {
+            let key = &key;
+            async move {
+                trx.set_option(TransactionOption::SpecialKeySpaceEnableWrites)?;
+                // a previous attempt may have created it
+                if trx.get(key, false).await?.is_some() && !maybe_committed {
+                    return Err(FdbError::from(FdbErrorKind::TenantAlreadyExists).into());
+                }
+                trx.set(key, &[]);
+                Ok(())
+            }
+        })
+        .await
+    }
+
+    /// Deletes a tenant, failing with `tenant_not_found` if it does not exist.
+    ///
+    /// The tenant must be empty, otherwise this fails with `tenant_not_empty`.
+    pub async fn delete_tenant(&self, tenant_name: &[u8]) -> Result<(), FdbBindingError> {
+        let key = tenant_key(tenant_name);
+        self.run(|trx, maybe_committed| {
+            let key = &key;
+            async move {
+                trx.set_option(TransactionOption::SpecialKeySpaceEnableWrites)?;
+                // a previous attempt may have deleted it
+                if trx.get(key, false).await?.is_none() && !maybe_committed {
+                    return Err(FdbError::from(FdbErrorKind::TenantNotFound).into());
+                }
+                trx.clear(key);
+                Ok(())
+            }
+        })
+        .await
+    }
+
+    /// Lists the tenants whose names are in the range `[begin, end)`.
+    ///
+    /// Returns up to `limit` pairs of a tenant name and its metadata, encoded in JSON.
+    pub async fn list_tenants(
+        &self,
+        begin: &[u8],
+        end: &[u8],
+        limit: usize,
+    ) -> Result<Vec<(Vec<u8>, Vec<u8>)>, FdbBindingError> {
+        let opt = RangeOption {
+            limit: Some(limit),
+            ..RangeOption::from((tenant_key(begin), tenant_key(end)))
+        };
+        self.run(|trx, _maybe_committed| {
+            let opt = opt.clone();
+            async move {
+                let tenants = trx.get_range(&opt, 1, false).await?;
+                Ok(tenants
+                    .iter()
+                    .map(|tenant| {
+                        (
+                            tenant.key()[TENANT_MAP_PREFIX.len()..].to_vec(),
+                            tenant.value().to_vec(),
+                        )
+                    })
+                    .collect())
+            }
+        })
+        .await
+    }
+}
+
+fn tenant_key(tenant_name: &[u8]) -> Vec<u8> {
+    let mut key = TENANT_MAP_PREFIX.to_vec();
+    key.extend_from_slice(tenant_name);
+    key
+}
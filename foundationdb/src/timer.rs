@@ -0,0 +1,183 @@
+// Copyright 2018 foundationdb-rs developers, https://github.com/Clikengo/foundationdb-rs/graphs/contributors
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+//! Delays which do not depend on a particular executor
+//!
+//! The backoffs of the retry loops and the periodic tasks of the crate wait on a single timer
+//! thread, started by the first delay, which wakes each of them once its deadline is reached.
+
+use std::cmp::Ordering as CmpOrdering;
+use std::collections::BinaryHeap;
+use std::future::Future;
+use std::sync::atomic::{AtomicPtr, Ordering};
+use std::sync::{Condvar, Mutex, MutexGuard};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use futures::channel::oneshot;
+use futures::future::{self, Either, FutureExt};
+
+/// The timer thread, leaked once started
+static TIMER: AtomicPtr<Timer> = AtomicPtr::new(std::ptr::null_mut());
+
+/// A pending delay, ordered by deadline, the earliest being the greatest
+struct Deadline {
+    instant: Instant,
+    id: u64,
+    sender: oneshot::Sender<()>,
+}
+
+impl PartialEq for Deadline {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == CmpOrdering::Equal
+    }
+}
+
+impl Eq for Deadline {}
+
+impl PartialOrd for Deadline {
+    fn partial_cmp(&self, other: &Self) -> Option<CmpOrdering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Deadline {
+    fn cmp(&self, other: &Self) -> CmpOrdering {
+        (other.instant, other.id).cmp(&(self.instant, self.id))
+    }
+}
+
+#[derive(Default)]
+struct Deadlines {
+    heap: BinaryHeap<Deadline>,
+    next_id: u64,
+}
+
+struct Timer {
+    deadlines: Mutex<Deadlines>,
+    cond: Condvar,
+}
+
+impl Timer {
+    /// The timer thread, started if it is not yet
+    fn get() -> &'static Timer {
+        let timer = TIMER.load(Ordering::Acquire);
+        if !timer.is_null() {
+            return unsafe { &*timer };
+        }
+
+        let new_timer = Box::into_raw(Box::new(Timer {
+            deadlines: Mutex::new(Deadlines::default()),
+            cond: Condvar::new(),
+        }));
+        match TIMER.compare_exchange(
+            std::ptr::null_mut(),
+            new_timer,
+            Ordering::AcqRel,
+            Ordering::Acquire,
+        ) {
+            Ok(_) => {
+                let timer: &'static Timer = unsafe { &*new_timer };
+                thread::Builder::new()
+                    .name("fdb-timer".to_string())
+                    .spawn(move || timer.run())
+                    .expect("failed to start the timer thread");
+                timer
+            }
+            // another thread started the timer first
+            Err(timer) => {
+                drop(unsafe { Box::from_raw(new_timer) });
+                unsafe { &*timer }
+            }
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Deadlines> {
+        self.deadlines.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Registers a deadline, waking the timer thread if it is the earliest one
+    fn add(&self, instant: Instant) -> oneshot::Receiver<()> {
+        let (sender, receiver) = oneshot::channel();
+        let mut deadlines = self.lock();
+        let id = deadlines.next_id;
+        deadlines.next_id += 1;
+        let is_earliest = deadlines
+            .heap
+            .peek()
+            .map_or(true, |earliest| instant < earliest.instant);
+        deadlines.heap.push(Deadline {
+            instant,
+            id,
+            sender,
+        });
+        if is_earliest {
+            self.cond.notify_one();
+        }
+        receiver
+    }
+
+    fn run(&self) {
+        let mut deadlines = self.lock();
+        loop {
+            let now = Instant::now();
+            while deadlines
+                .heap
+                .peek()
+                .map_or(false, |earliest| earliest.instant <= now)
+            {
+                if let Some(deadline) = deadlines.heap.pop() {
+                    // the delay may have been dropped
+                    let _ = deadline.sender.send(());
+                }
+            }
+
+            deadlines = match deadlines.heap.peek() {
+                Some(earliest) => {
+                    let timeout = earliest.instant - now;
+                    self.cond
+                        .wait_timeout(deadlines, timeout)
+                        .unwrap_or_else(|e| e.into_inner())
+                        .0
+                }
+                None => self.cond.wait(deadlines).unwrap_or_else(|e| e.into_inner()),
+            };
+        }
+    }
+}
+
+/// Resolves after `duration`, without depending on a particular executor
+pub(crate) fn delay(duration: Duration) -> impl Future<Output = ()> {
+    match Instant::now().checked_add(duration) {
+        Some(instant) => Either::Left(Timer::get().add(instant).map(|_| ())),
+        // never reached
+        None => Either::Right(future::pending()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::stream::{FuturesUnordered, StreamExt};
+
+    #[test]
+    fn test_delay() {
+        let start = Instant::now();
+        futures::executor::block_on(delay(Duration::from_millis(50)));
+        assert!(start.elapsed() >= Duration::from_millis(50));
+    }
+
+    #[test]
+    fn test_delays_resolve_in_order() {
+        let delays: FuturesUnordered<_> = [30u64, 10, 20, 0]
+            .iter()
+            .map(|&ms| delay(Duration::from_millis(ms)).map(move |()| ms))
+            .collect();
+        let resolved: Vec<u64> = futures::executor::block_on(delays.collect());
+        assert_eq!(resolved, vec![0, 10, 20, 30]);
+    }
+}
@@ -0,0 +1,255 @@
+// Copyright 2018 foundationdb-rs developers, https://github.com/Clikengo/foundationdb-rs/graphs/contributors
+// Copyright 2013-2018 Apple, Inc and the FoundationDB project authors.
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+//! Instrumentation of the runs and of the operations of the transactions.
+//!
+//! With the `tracing` feature, `Database::run` and its variants are wrapped in a `run` span, with
+//! an `attempt` child span for each attempt. The `get`, `get_range`, `commit` and `on_error`
+//! operations emit `DEBUG` events recording their key sizes, byte counts, snapshot flag, error
+//! code and latency in microseconds.
+//!
+//! Without the feature, the spans are empty and the futures are returned as they are.
+
+use std::future::Future;
+
+use crate::future::{FdbFuture, FdbSlice, FdbValues};
+use crate::{FdbBindingError, FdbError, RangeOption, RunOutcome};
+
+#[cfg(feature = "tracing")]
+use crate::FdbResult;
+#[cfg(feature = "tracing")]
+use futures::FutureExt;
+#[cfg(feature = "tracing")]
+use std::time::Instant;
+#[cfg(feature = "tracing")]
+use tracing::{field, instrument::Instrumented, Instrument};
+
+/// The span of a run or of one of its attempts
+#[derive(Clone)]
+pub(crate) struct Span {
+    #[cfg(feature = "tracing")]
+    inner: tracing::Span,
+}
+
+#[cfg(feature = "tracing")]
+impl Span {
+    /// The span of a new run
+    pub(crate) fn run() -> Self {
+        Self {
+            inner: tracing::info_span!(
+                "run",
+                attempts = field::Empty,
+                committed_version = field::Empty,
+                error = field::Empty,
+            ),
+        }
+    }
+
+    /// The span of an attempt of the run
+    pub(crate) fn attempt(&self, attempt: u32, maybe_committed: bool) -> Self {
+        Self {
+            inner: tracing::debug_span!(
+                parent: &self.inner,
+                "attempt",
+                attempt,
+                maybe_committed,
+                trace_id = field::Empty,
+            ),
+        }
+    }
+
+    /// Calls `f` with the span entered
+    pub(crate) fn in_scope<T>(&self, f: impl FnOnce() -> T) -> T {
+        self.inner.in_scope(f)
+    }
+
+    /// Records the trace id given to FoundationDB
+    pub(crate) fn record_trace_id(&self, trace_id: &str) {
+        self.inner.record("trace_id", trace_id);
+    }
+
+    /// Records the result of the run
+    pub(crate) fn record_outcome<T>(&self, outcome: &Result<RunOutcome<T>, FdbBindingError>) {
+        match outcome {
+            Ok(outcome) => {
+                self.inner.record("attempts", outcome.attempts);
+                if let Some(committed_version) = outcome.committed_version {
+                    self.inner.record("committed_version", committed_version);
+                }
+            }
+            Err(err) => {
+                self.inner.record("error", field::display(err));
+            }
+        }
+    }
+
+    /// Polls `future` in the span
+    pub(crate) fn instrument<F: Future>(&self, future: F) -> Instrumented<F> {
+        future.instrument(self.inner.clone())
+    }
+}
+
+#[cfg(not(feature = "tracing"))]
+impl Span {
+    pub(crate) fn run() -> Self {
+        Self {}
+    }
+
+    pub(crate) fn attempt(&self, _attempt: u32, _maybe_committed: bool) -> Self {
+        Self {}
+    }
+
+    pub(crate) fn record_outcome<T>(&self, _outcome: &Result<RunOutcome<T>, FdbBindingError>) {}
+
+    pub(crate) fn instrument<F: Future>(&self, future: F) -> F {
+        future
+    }
+}
+
+/// The latency of an operation started at `start`, in microseconds
+#[cfg(feature = "tracing")]
+fn latency_us(start: Instant) -> u64 {
+    start.elapsed().as_micros() as u64
+}
+
+/// Emits an event once the read of `key` completes
+#[cfg(feature = "tracing")]
+pub(crate) fn get(
+    future: FdbFuture<Option<FdbSlice>>,
+    key: &[u8],
+    snapshot: bool,
+) -> impl Future<Output = FdbResult<Option<FdbSlice>>> + Send + Sync + Unpin {
+    let key_size = key.len();
+    let start = Instant::now();
+    future.map(move |result| {
+        match &result {
+            Ok(value) => tracing::debug!(
+                key_size,
+                value_size = value.as_ref().map(|value| value.len()),
+                snapshot,
+                latency_us = latency_us(start),
+                "get"
+            ),
+            Err(err) => tracing::debug!(
+                key_size,
+                snapshot,
+                error_code = err.code(),
+                latency_us = latency_us(start),
+                "get"
+            ),
+        }
+        result
+    })
+}
+
+/// Emits an event once the read of the range of `opt` completes
+#[cfg(feature = "tracing")]
+pub(crate) fn get_range(
+    future: FdbFuture<FdbValues>,
+    opt: &RangeOption,
+    iteration: usize,
+    snapshot: bool,
+) -> impl Future<Output = FdbResult<FdbValues>> + Send + Sync + Unpin {
+    let begin_key_size = opt.begin.key().len();
+    let end_key_size = opt.end.key().len();
+    let limit = opt.limit;
+    let start = Instant::now();
+    future.map(move |result| {
+        match &result {
+            Ok(values) => tracing::debug!(
+                begin_key_size,
+                end_key_size,
+                limit,
+                iteration,
+                snapshot,
+                key_values = values.len(),
+                bytes = values
+                    .iter()
+                    .map(|kv| kv.key().len() + kv.value().len())
+                    .sum::<usize>(),
+                more = values.more(),
+                latency_us = latency_us(start),
+                "get_range"
+            ),
+            Err(err) => tracing::debug!(
+                begin_key_size,
+                end_key_size,
+                limit,
+                iteration,
+                snapshot,
+                error_code = err.code(),
+                latency_us = latency_us(start),
+                "get_range"
+            ),
+        }
+        result
+    })
+}
+
+/// Emits an event once the commit completes
+#[cfg(feature = "tracing")]
+pub(crate) fn commit(
+    future: FdbFuture<()>,
+) -> impl Future<Output = FdbResult<()>> + Send + Sync + Unpin {
+    let start = Instant::now();
+    future.map(move |result| {
+        tracing::debug!(
+            error_code = result.as_ref().err().map(|err| err.code()),
+            latency_us = latency_us(start),
+            "commit"
+        );
+        result
+    })
+}
+
+/// Emits an event once `on_error` decided whether `err` can be retried
+#[cfg(feature = "tracing")]
+pub(crate) fn on_error(
+    future: FdbFuture<()>,
+    err: FdbError,
+) -> impl Future<Output = FdbResult<()>> + Send + Sync + Unpin {
+    let start = Instant::now();
+    future.map(move |result| {
+        tracing::debug!(
+            error_code = err.code(),
+            retryable = result.is_ok(),
+            latency_us = latency_us(start),
+            "on_error"
+        );
+        result
+    })
+}
+
+#[cfg(not(feature = "tracing"))]
+pub(crate) fn get(
+    future: FdbFuture<Option<FdbSlice>>,
+    _key: &[u8],
+    _snapshot: bool,
+) -> FdbFuture<Option<FdbSlice>> {
+    future
+}
+
+#[cfg(not(feature = "tracing"))]
+pub(crate) fn get_range(
+    future: FdbFuture<FdbValues>,
+    _opt: &RangeOption,
+    _iteration: usize,
+    _snapshot: bool,
+) -> FdbFuture<FdbValues> {
+    future
+}
+
+#[cfg(not(feature = "tracing"))]
+pub(crate) fn commit(future: FdbFuture<()>) -> FdbFuture<()> {
+    future
+}
+
+#[cfg(not(feature = "tracing"))]
+pub(crate) fn on_error(future: FdbFuture<()>, _err: FdbError) -> FdbFuture<()> {
+    future
+}
@@ -19,8 +19,9 @@ use std::sync::Arc;
 use crate::future::*;
 use crate::keyselector::*;
 use crate::options;
+use crate::trace;
 
-use crate::{error, FdbError, FdbResult};
+use crate::{error, FdbError, FdbResult, KeyValue, KeyValues};
 use foundationdb_macros::cfg_api_versions;
 
 use crate::error::FdbBindingError;
@@ -29,6 +30,14 @@ use futures::{
     future, future::Either, stream, Future, FutureExt, Stream, TryFutureExt, TryStreamExt,
 };
 
+#[cfg_api_versions(min = 710)]
+use crate::blob_granules::{GranuleContext, GranuleLoader, GranuleValues};
+#[cfg_api_versions(min = 630)]
+use crate::FdbErrorKind;
+
+#[cfg_api_versions(min = 630)]
+const CONFLICTING_KEYS_PREFIX: &[u8] = b"\xff\xff/transaction/conflicting_keys/";
+
 #[cfg_api_versions(min = 610)]
 const METADATA_VERSION_KEY: &[u8] = b"\xff/metadataVersion";
 
@@ -93,10 +102,7 @@ impl TransactionCommitError {
     /// You should not call this method most of the times and use `Database::transact` which
     /// implements a retry loop strategy for you.
     pub fn on_error(self) -> impl Future<Output = FdbResult<Transaction>> {
-        FdbFuture::<()>::new(unsafe {
-            fdb_sys::fdb_transaction_on_error(self.tr.inner.as_ptr(), self.err.code())
-        })
-        .map_ok(|()| self.tr)
+        trace::on_error(self.tr.on_error_future(self.err), self.err).map_ok(|()| self.tr)
     }
 
     /// Reset the transaction to its initial state.
@@ -106,6 +112,24 @@ impl TransactionCommitError {
         self.tr.reset();
         self.tr
     }
+
+    /// The transaction which failed to commit
+    pub fn transaction(&self) -> &Transaction {
+        &self.tr
+    }
+
+    /// Reads the key ranges which conflicted with other transactions, if the commit failed with
+    /// `not_committed`.
+    ///
+    /// Returns an empty list for the other errors, or if `TransactionOption::ReportConflictingKeys`
+    /// was not set before the commit.
+    #[cfg_api_versions(min = 630)]
+    pub async fn conflicting_keys(&self) -> FdbResult<Vec<Range<Vec<u8>>>> {
+        if self.err.kind() != FdbErrorKind::NotCommitted {
+            return Ok(Vec::new());
+        }
+        self.tr.get_conflicting_keys().await
+    }
 }
 
 impl Deref for TransactionCommitError {
@@ -177,7 +201,7 @@ unsafe impl Sync for Transaction {}
 
 /// Converts Rust `bool` into `fdb_sys::fdb_bool_t`
 #[inline]
-fn fdb_bool(v: bool) -> fdb_sys::fdb_bool_t {
+pub(crate) fn fdb_bool(v: bool) -> fdb_sys::fdb_bool_t {
     if v {
         1
     } else {
@@ -185,7 +209,7 @@ fn fdb_bool(v: bool) -> fdb_sys::fdb_bool_t {
     }
 }
 #[inline]
-fn fdb_len(len: usize, context: &'static str) -> std::os::raw::c_int {
+pub(crate) fn fdb_len(len: usize, context: &'static str) -> std::os::raw::c_int {
     assert!(
         len <= i32::max_value() as usize,
         "{}.len() > i32::max_value()",
@@ -252,7 +276,13 @@ impl<'a> RangeOption<'a> {
         self
     }
 
-    pub fn next_range(mut self, kvs: &FdbValues) -> Option<Self> {
+    /// Returns the range after the key-value pairs returned by a read of this range, or `None` if
+    /// the read returned the end of the range.
+    pub fn next_range<V, KV>(mut self, kvs: &V) -> Option<Self>
+    where
+        V: KeyValues + Deref<Target = [KV]>,
+        KV: KeyValue,
+    {
         if !kvs.more() {
             return None;
         }
@@ -439,6 +469,10 @@ impl Transaction {
         key: &[u8],
         snapshot: bool,
     ) -> impl Future<Output = FdbResult<Option<FdbSlice>>> + Send + Sync + Unpin {
+        trace::get(self.get_future(key, snapshot), key, snapshot)
+    }
+
+    pub(crate) fn get_future(&self, key: &[u8], snapshot: bool) -> FdbFuture<Option<FdbSlice>> {
         FdbFuture::new(unsafe {
             fdb_sys::fdb_transaction_get(
                 self.inner.as_ptr(),
@@ -501,6 +535,14 @@ impl Transaction {
         selector: &KeySelector,
         snapshot: bool,
     ) -> impl Future<Output = FdbResult<FdbSlice>> + Send + Sync + Unpin {
+        self.get_key_future(selector, snapshot)
+    }
+
+    pub(crate) fn get_key_future(
+        &self,
+        selector: &KeySelector,
+        snapshot: bool,
+    ) -> FdbFuture<FdbSlice> {
         let key = selector.key();
         FdbFuture::new(unsafe {
             fdb_sys::fdb_transaction_get_key(
@@ -588,6 +630,20 @@ impl Transaction {
         iteration: usize,
         snapshot: bool,
     ) -> impl Future<Output = FdbResult<FdbValues>> + Send + Sync + Unpin {
+        trace::get_range(
+            self.get_range_future(opt, iteration, snapshot),
+            opt,
+            iteration,
+            snapshot,
+        )
+    }
+
+    pub(crate) fn get_range_future(
+        &self,
+        opt: &RangeOption,
+        iteration: usize,
+        snapshot: bool,
+    ) -> FdbFuture<FdbValues> {
         let begin = &opt.begin;
         let end = &opt.end;
         let key_begin = begin.key();
@@ -751,6 +807,32 @@ impl Transaction {
         })
     }
 
+    /// Reads the key ranges which conflicted with other transactions, after the commit of this
+    /// transaction failed with `not_committed`.
+    ///
+    /// `TransactionOption::ReportConflictingKeys` must be set before the commit, the ranges being
+    /// read from the `\xff\xff/transaction/conflicting_keys/` special keys.
+    #[cfg_api_versions(min = 630)]
+    pub async fn get_conflicting_keys(&self) -> FdbResult<Vec<Range<Vec<u8>>>> {
+        let mut end = CONFLICTING_KEYS_PREFIX.to_vec();
+        end.push(0xff);
+        let opt = RangeOption::from((CONFLICTING_KEYS_PREFIX, end.as_slice()));
+        let keys: Vec<FdbValue> = self.get_ranges_keyvalues(opt, false).try_collect().await?;
+
+        // a range starts at a key whose value is "1", and ends at the next key, whose value is "0"
+        let mut ranges = Vec::new();
+        let mut begin = None;
+        for kv in &keys {
+            let key = kv.key()[CONFLICTING_KEYS_PREFIX.len()..].to_vec();
+            match (kv.value(), begin.take()) {
+                (b"1", _) => begin = Some(key),
+                (_, Some(begin)) => ranges.push(begin..key),
+                _ => {}
+            }
+        }
+        Ok(ranges)
+    }
+
     /// Attempts to commit the sets and clears previously applied to the database snapshot
     /// represented by transaction to the actual database.
     ///
@@ -775,12 +857,14 @@ impl Transaction {
     /// snapshot reads or the transaction option for disabling “read-your-writes” has been invoked,
     /// any outstanding reads will immediately return errors.
     pub fn commit(self) -> impl Future<Output = TransactionResult> + Send + Sync + Unpin {
-        FdbFuture::<()>::new(unsafe { fdb_sys::fdb_transaction_commit(self.inner.as_ptr()) }).map(
-            move |r| match r {
-                Ok(()) => Ok(TransactionCommitted { tr: self }),
-                Err(err) => Err(TransactionCommitError { tr: self, err }),
-            },
-        )
+        trace::commit(self.commit_future()).map(move |r| match r {
+            Ok(()) => Ok(TransactionCommitted { tr: self }),
+            Err(err) => Err(TransactionCommitError { tr: self, err }),
+        })
+    }
+
+    pub(crate) fn commit_future(&self) -> FdbFuture<()> {
+        FdbFuture::new(unsafe { fdb_sys::fdb_transaction_commit(self.inner.as_ptr()) })
     }
 
     /// Implements the recommended retry and backoff behavior for a transaction. This function knows
@@ -799,10 +883,13 @@ impl Transaction {
         self,
         err: FdbError,
     ) -> impl Future<Output = FdbResult<Transaction>> + Send + Sync + Unpin {
-        FdbFuture::<()>::new(unsafe {
+        trace::on_error(self.on_error_future(err), err).map_ok(|()| self)
+    }
+
+    pub(crate) fn on_error_future(&self, err: FdbError) -> FdbFuture<()> {
+        FdbFuture::new(unsafe {
             fdb_sys::fdb_transaction_on_error(self.inner.as_ptr(), err.code())
         })
-        .map_ok(|()| self)
     }
 
     /// Cancels the transaction. All pending or future uses of the transaction will return a
@@ -897,6 +984,76 @@ impl Transaction {
         })
     }
 
+    /// Gets the ranges of the blob granules covering the range `[begin, end)`.
+    #[cfg_api_versions(min = 710)]
+    pub fn get_blob_granule_ranges(
+        &self,
+        begin: &[u8],
+        end: &[u8],
+    ) -> impl Future<Output = FdbResult<FdbKeyRanges>> + Send + Sync + Unpin {
+        FdbFuture::<FdbKeyRanges>::new(unsafe {
+            fdb_sys::fdb_transaction_get_blob_granule_ranges(
+                self.inner.as_ptr(),
+                begin.as_ptr(),
+                fdb_len(begin.len(), "begin"),
+                end.as_ptr(),
+                fdb_len(end.len(), "end"),
+            )
+        })
+    }
+
+    /// Reads the range `[begin, end)` from the blob granules, the granule files being read with
+    /// `loader`.
+    ///
+    /// Only the mutations after `begin_version` are read, `0` reading the whole range. The range
+    /// is read at `read_version`, or at the read version of the transaction if `None`.
+    ///
+    /// This call is synchronous: it blocks the calling thread until the granule files are loaded
+    /// and the range is materialized, so it should not be called from an async executor thread.
+    /// A failed load fails the read with a `FdbBindingError::CustomError` of the `io::Error`.
+    ///
+    /// # Panics
+    ///
+    /// A panic of `loader` is caught in the callback of the client, and resumed once the read
+    /// returns.
+    #[cfg_api_versions(min = 710)]
+    pub fn read_blob_granules<L: GranuleLoader>(
+        &self,
+        begin: &[u8],
+        end: &[u8],
+        begin_version: i64,
+        read_version: Option<i64>,
+        loader: &L,
+    ) -> Result<GranuleValues, FdbBindingError> {
+        // -2 is latestVersion, the read version of the transaction
+        const LATEST_VERSION: i64 = -2;
+
+        let context = GranuleContext::new(loader);
+        let result = unsafe {
+            fdb_sys::fdb_transaction_read_blob_granules(
+                self.inner.as_ptr(),
+                begin.as_ptr(),
+                fdb_len(begin.len(), "begin"),
+                end.as_ptr(),
+                fdb_len(end.len(), "end"),
+                begin_version,
+                read_version.unwrap_or(LATEST_VERSION),
+                context.as_raw(),
+            )
+        };
+        let result =
+            NonNull::new(result).expect("fdb_transaction_read_blob_granules to not return null");
+        let values = GranuleValues::new(result);
+        if let Some(payload) = context.take_panic() {
+            drop(values);
+            std::panic::resume_unwind(payload);
+        }
+        if let Some(err) = context.take_error() {
+            return Err(FdbBindingError::CustomError(Box::new(err)));
+        }
+        Ok(values?)
+    }
+
     /// Returns an FDBFuture which will be set to the versionstamp which was used by any
     /// versionstamp operations in this transaction.
     ///
@@ -917,6 +1074,10 @@ impl Transaction {
     /// compromised by transaction options) is guaranteed to represent all transactions which were
     /// reported committed before that call.
     pub fn get_read_version(&self) -> impl Future<Output = FdbResult<i64>> + Send + Sync + Unpin {
+        self.get_read_version_future()
+    }
+
+    pub(crate) fn get_read_version_future(&self) -> FdbFuture<i64> {
         FdbFuture::new(unsafe { fdb_sys::fdb_transaction_get_read_version(self.inner.as_ptr()) })
     }
 
@@ -1049,6 +1210,12 @@ impl RetryableTransaction {
         Arc::try_unwrap(self.inner).map_err(|_| FdbBindingError::ReferenceToTransactionKept)
     }
 
+    pub(crate) fn reset(self) -> Result<RetryableTransaction, FdbBindingError> {
+        let mut trx = self.take()?;
+        trx.reset();
+        Ok(RetryableTransaction::new(trx))
+    }
+
     pub(crate) async fn on_error(
         self,
         err: FdbError,
@@ -0,0 +1,274 @@
+// Copyright 2018 foundationdb-rs developers, https://github.com/Clikengo/foundationdb-rs/graphs/contributors
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+//! Traits abstracting over the transaction types
+//!
+//! Layers taking a `&T` where `T: ReadTransaction` or `T: WriteTransaction` can be used with a
+//! [`Transaction`], a [`RetryableTransaction`], a [`MemoryTransaction`](crate::memory::MemoryTransaction),
+//! or any wrapper around them (instrumented, read-only, caching, ...).
+//!
+//! Reads return their future before it is polled, as the inherent methods of `Transaction` do:
+//! the read is issued when the method is called, so it observes the writes made before the call
+//! and not those made before the future is awaited.
+
+use std::ops::Deref;
+
+use futures::future::BoxFuture;
+use futures::FutureExt;
+
+use crate::future::{FdbKeyValue, FdbSlice, FdbValue, FdbValues};
+use crate::options::{ConflictRangeType, MutationType, TransactionOption};
+use crate::tuple::Subspace;
+use crate::{FdbResult, KeySelector, RangeOption, RetryableTransaction, Transaction};
+
+/// A key-value pair returned by a range read
+pub trait KeyValue {
+    /// key
+    fn key(&self) -> &[u8];
+
+    /// value
+    fn value(&self) -> &[u8];
+}
+
+/// The key-value pairs returned by a single range read
+pub trait KeyValues {
+    /// `true` if there is another range after this one
+    fn more(&self) -> bool;
+}
+
+/// The read operations of a transaction
+pub trait ReadTransaction: Send + Sync {
+    /// A value or key read from the database
+    type Value: Deref<Target = [u8]> + Send + Sync;
+    /// A key-value pair read by `get_range`
+    type KeyValue: KeyValue;
+    /// The result of `get_range`
+    type Values: KeyValues + Deref<Target = [Self::KeyValue]> + Send + Sync;
+
+    /// Called to set an option on the transaction.
+    fn set_option(&self, opt: TransactionOption) -> FdbResult<()>;
+
+    /// Reads a value from the database snapshot represented by transaction.
+    ///
+    /// See `Transaction::get`.
+    fn get<'a>(
+        &'a self,
+        key: &[u8],
+        snapshot: bool,
+    ) -> BoxFuture<'a, FdbResult<Option<Self::Value>>>;
+
+    /// Resolves a key selector against the keys in the database snapshot represented by
+    /// transaction.
+    ///
+    /// See `Transaction::get_key`.
+    fn get_key<'a>(
+        &'a self,
+        selector: &KeySelector,
+        snapshot: bool,
+    ) -> BoxFuture<'a, FdbResult<Self::Value>>;
+
+    /// Reads the key-value pairs of a range.
+    ///
+    /// See `Transaction::get_range`.
+    fn get_range<'a>(
+        &'a self,
+        opt: &RangeOption,
+        iteration: usize,
+        snapshot: bool,
+    ) -> BoxFuture<'a, FdbResult<Self::Values>>;
+
+    /// Gets the read version of the transaction.
+    ///
+    /// See `Transaction::get_read_version`.
+    fn get_read_version(&self) -> BoxFuture<'_, FdbResult<i64>>;
+}
+
+/// The write operations of a transaction
+pub trait WriteTransaction: ReadTransaction {
+    /// Modify the database snapshot represented by transaction to change the given
+    /// key to have the given value.
+    fn set(&self, key: &[u8], value: &[u8]);
+
+    /// Modify the database snapshot represented by transaction to remove the given key from the
+    /// database.
+    fn clear(&self, key: &[u8]);
+
+    /// Modify the database snapshot represented by transaction to remove all keys (if any) which
+    /// are lexicographically greater than or equal to the given begin key and lexicographically
+    /// less than the given end_key.
+    fn clear_range(&self, begin: &[u8], end: &[u8]);
+
+    /// Modify the database snapshot represented by transaction to remove every key of the
+    /// subspace.
+    fn clear_subspace_range(&self, subspace: &Subspace) {
+        let (begin, end) = subspace.range();
+        self.clear_range(&begin, &end)
+    }
+
+    /// Modify the database snapshot represented by transaction to perform the operation indicated
+    /// by `op_type` with operand `param` to the value stored by the given key.
+    ///
+    /// See `Transaction::atomic_op`.
+    fn atomic_op(&self, key: &[u8], param: &[u8], op_type: MutationType);
+
+    /// Adds a conflict range to a transaction without performing the associated read or write.
+    fn add_conflict_range(&self, begin: &[u8], end: &[u8], ty: ConflictRangeType) -> FdbResult<()>;
+
+    /// Returns a future which will return the versionstamp which was used by any versionstamp
+    /// operations in this transaction, once it is committed.
+    ///
+    /// See `Transaction::get_versionstamp`.
+    fn get_versionstamp(&self) -> BoxFuture<'_, FdbResult<Self::Value>>;
+}
+
+impl KeyValue for FdbKeyValue {
+    fn key(&self) -> &[u8] {
+        FdbKeyValue::key(self)
+    }
+
+    fn value(&self) -> &[u8] {
+        FdbKeyValue::value(self)
+    }
+}
+
+impl KeyValue for FdbValue {
+    fn key(&self) -> &[u8] {
+        self.deref().key()
+    }
+
+    fn value(&self) -> &[u8] {
+        self.deref().value()
+    }
+}
+
+impl KeyValues for FdbValues {
+    fn more(&self) -> bool {
+        FdbValues::more(self)
+    }
+}
+
+impl ReadTransaction for Transaction {
+    type Value = FdbSlice;
+    type KeyValue = FdbKeyValue;
+    type Values = FdbValues;
+
+    fn set_option(&self, opt: TransactionOption) -> FdbResult<()> {
+        Transaction::set_option(self, opt)
+    }
+
+    fn get<'a>(&'a self, key: &[u8], snapshot: bool) -> BoxFuture<'a, FdbResult<Option<FdbSlice>>> {
+        Transaction::get(self, key, snapshot).boxed()
+    }
+
+    fn get_key<'a>(
+        &'a self,
+        selector: &KeySelector,
+        snapshot: bool,
+    ) -> BoxFuture<'a, FdbResult<FdbSlice>> {
+        Transaction::get_key(self, selector, snapshot).boxed()
+    }
+
+    fn get_range<'a>(
+        &'a self,
+        opt: &RangeOption,
+        iteration: usize,
+        snapshot: bool,
+    ) -> BoxFuture<'a, FdbResult<FdbValues>> {
+        Transaction::get_range(self, opt, iteration, snapshot).boxed()
+    }
+
+    fn get_read_version(&self) -> BoxFuture<'_, FdbResult<i64>> {
+        Transaction::get_read_version(self).boxed()
+    }
+}
+
+impl WriteTransaction for Transaction {
+    fn set(&self, key: &[u8], value: &[u8]) {
+        Transaction::set(self, key, value)
+    }
+
+    fn clear(&self, key: &[u8]) {
+        Transaction::clear(self, key)
+    }
+
+    fn clear_range(&self, begin: &[u8], end: &[u8]) {
+        Transaction::clear_range(self, begin, end)
+    }
+
+    fn atomic_op(&self, key: &[u8], param: &[u8], op_type: MutationType) {
+        Transaction::atomic_op(self, key, param, op_type)
+    }
+
+    fn add_conflict_range(&self, begin: &[u8], end: &[u8], ty: ConflictRangeType) -> FdbResult<()> {
+        Transaction::add_conflict_range(self, begin, end, ty)
+    }
+
+    fn get_versionstamp(&self) -> BoxFuture<'_, FdbResult<FdbSlice>> {
+        Transaction::get_versionstamp(self).boxed()
+    }
+}
+
+impl ReadTransaction for RetryableTransaction {
+    type Value = FdbSlice;
+    type KeyValue = FdbKeyValue;
+    type Values = FdbValues;
+
+    fn set_option(&self, opt: TransactionOption) -> FdbResult<()> {
+        ReadTransaction::set_option(self.deref(), opt)
+    }
+
+    fn get<'a>(&'a self, key: &[u8], snapshot: bool) -> BoxFuture<'a, FdbResult<Option<FdbSlice>>> {
+        ReadTransaction::get(self.deref(), key, snapshot)
+    }
+
+    fn get_key<'a>(
+        &'a self,
+        selector: &KeySelector,
+        snapshot: bool,
+    ) -> BoxFuture<'a, FdbResult<FdbSlice>> {
+        ReadTransaction::get_key(self.deref(), selector, snapshot)
+    }
+
+    fn get_range<'a>(
+        &'a self,
+        opt: &RangeOption,
+        iteration: usize,
+        snapshot: bool,
+    ) -> BoxFuture<'a, FdbResult<FdbValues>> {
+        ReadTransaction::get_range(self.deref(), opt, iteration, snapshot)
+    }
+
+    fn get_read_version(&self) -> BoxFuture<'_, FdbResult<i64>> {
+        ReadTransaction::get_read_version(self.deref())
+    }
+}
+
+impl WriteTransaction for RetryableTransaction {
+    fn set(&self, key: &[u8], value: &[u8]) {
+        WriteTransaction::set(self.deref(), key, value)
+    }
+
+    fn clear(&self, key: &[u8]) {
+        WriteTransaction::clear(self.deref(), key)
+    }
+
+    fn clear_range(&self, begin: &[u8], end: &[u8]) {
+        WriteTransaction::clear_range(self.deref(), begin, end)
+    }
+
+    fn atomic_op(&self, key: &[u8], param: &[u8], op_type: MutationType) {
+        WriteTransaction::atomic_op(self.deref(), key, param, op_type)
+    }
+
+    fn add_conflict_range(&self, begin: &[u8], end: &[u8], ty: ConflictRangeType) -> FdbResult<()> {
+        WriteTransaction::add_conflict_range(self.deref(), begin, end, ty)
+    }
+
+    fn get_versionstamp(&self) -> BoxFuture<'_, FdbResult<FdbSlice>> {
+        WriteTransaction::get_versionstamp(self.deref())
+    }
+}
@@ -110,7 +110,7 @@ impl HighContentionAllocator {
     /// Returns a byte string that
     ///   1) has never and will never be returned by another call to this method on the same subspace
     ///   2) is nearly as short as possible given the above
-    pub async fn allocate(&self, trx: &Transaction) -> Result<i64, HcaError> {
+    pub async fn allocate<T: WriteTransaction>(&self, trx: &T) -> Result<i64, HcaError> {
         let (begin, end) = self.counters.range();
         let begin = KeySelector::first_greater_or_equal(begin);
         let end = KeySelector::first_greater_than(end);
@@ -0,0 +1,310 @@
+// Copyright 2018 foundationdb-rs developers, https://github.com/Clikengo/foundationdb-rs/graphs/contributors
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+//! Secondary indexes maintained alongside the records of a subspace.
+//!
+//! An [`IndexedSubspace`] stores a record at "primary : (primary_key,)", and for each of its
+//! [`Index`]es, an empty entry at "index : (indexed_value, primary_key)", where `indexed_value` is
+//! extracted from the value of the record. Tuples are packed as nested tuples, so an entry always
+//! has two elements after the prefix of the index.
+//!
+//! Writing a record through the `IndexedSubspace` reads its previous value, so the entries of the
+//! previous value are cleared in the same transaction.
+//!
+//! ```
+//! use foundationdb::memory::MemoryDatabase;
+//! use foundationdb::tuple::index::{Index, IndexedSubspace};
+//! use foundationdb::tuple::{unpack, Subspace};
+//!
+//! # futures::executor::block_on(async {
+//! let by_zipcode = Index::new(Subspace::all().subspace(&"zipcode"), |value| {
+//!     unpack::<(String, String)>(value).ok().map(|(zipcode, _name)| zipcode)
+//! });
+//! let users = IndexedSubspace::new(Subspace::all().subspace(&"user")).with_index(by_zipcode);
+//!
+//! let db = MemoryDatabase::new();
+//! let trx = db.create_trx()?;
+//! users.set(&trx, &"001", &foundationdb::tuple::pack(&("20500", "Barack"))).await?;
+//! users.set(&trx, &"101", &foundationdb::tuple::pack(&("SW1A 1AA", "Elizabeth"))).await?;
+//!
+//! let index = &users.indexes()[0];
+//! let found = users.query(&trx, index, index.range(&"20500"), false).await?;
+//! assert_eq!(found.len(), 1);
+//! assert_eq!(found[0].0, users.primary().pack(&("001",)));
+//! # Ok::<(), foundationdb::FdbBindingError>(())
+//! # }).unwrap();
+//! ```
+
+use std::fmt;
+use std::sync::Arc;
+
+use futures::future;
+
+#[cfg_api_versions(min = 710)]
+use std::borrow::Cow;
+
+#[cfg_api_versions(min = 710)]
+use crate::tuple::unpack;
+use crate::tuple::{pack, Element, Subspace, TuplePack};
+use crate::*;
+use foundationdb_macros::cfg_api_versions;
+
+type Extract = dyn Fn(&[u8]) -> Option<Vec<u8>> + Send + Sync;
+
+/// The definition of a secondary index: where its entries are stored, and how the indexed value
+/// is extracted from the value of a record
+#[derive(Clone)]
+pub struct Index {
+    subspace: Subspace,
+    extract: Arc<Extract>,
+}
+
+impl fmt::Debug for Index {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Index")
+            .field("subspace", &self.subspace)
+            .finish()
+    }
+}
+
+impl Index {
+    /// Constructs an index storing its entries in the given subspace.
+    ///
+    /// `extract` returns the indexed value of a record, or `None` if the record is not indexed.
+    /// The given subspace should not be used by anything other than the index
+    pub fn new<K, F>(subspace: Subspace, extract: F) -> Index
+    where
+        K: TuplePack,
+        F: Fn(&[u8]) -> Option<K> + Send + Sync + 'static,
+    {
+        Index {
+            subspace,
+            extract: Arc::new(move |value| extract(value).map(|key| pack(&(key,)))),
+        }
+    }
+
+    /// The subspace of the entries of the index
+    pub fn subspace(&self) -> &Subspace {
+        &self.subspace
+    }
+
+    /// The range of the entries whose indexed value is `value`
+    pub fn range<K: TuplePack>(&self, value: &K) -> RangeOption<'static> {
+        let mut prefix = self.subspace.bytes().to_vec();
+        prefix.extend_from_slice(&pack(&(value,)));
+        RangeOption::from(&Subspace::from_bytes(prefix))
+    }
+
+    /// The key of the entry of a record, `None` if the record is not indexed
+    fn entry(&self, primary_key: &[u8], value: &[u8]) -> Option<Vec<u8>> {
+        let indexed = (self.extract)(value)?;
+        let mut key = self.subspace.bytes().to_vec();
+        key.extend_from_slice(&indexed);
+        key.extend_from_slice(primary_key);
+        Some(key)
+    }
+}
+
+/// Represents records stored in a subspace, indexed by secondary indexes
+#[derive(Clone, Debug)]
+pub struct IndexedSubspace {
+    primary: Subspace,
+    indexes: Vec<Index>,
+}
+
+impl IndexedSubspace {
+    /// Constructs a set of records stored in the given subspace, without any index.
+    pub fn new(primary: Subspace) -> IndexedSubspace {
+        IndexedSubspace {
+            primary,
+            indexes: Vec::new(),
+        }
+    }
+
+    /// Adds an index to maintain when the records are written.
+    ///
+    /// The index only has entries for the records written after it was added.
+    pub fn with_index(mut self, index: Index) -> Self {
+        self.indexes.push(index);
+        self
+    }
+
+    /// The subspace of the records
+    pub fn primary(&self) -> &Subspace {
+        &self.primary
+    }
+
+    /// The indexes maintained when the records are written
+    pub fn indexes(&self) -> &[Index] {
+        &self.indexes
+    }
+
+    /// Reads the value of a record.
+    pub async fn get<T: ReadTransaction, P: TuplePack>(
+        &self,
+        trx: &T,
+        primary_key: &P,
+        snapshot: bool,
+    ) -> FdbResult<Option<T::Value>> {
+        trx.get(&self.primary.pack(&(primary_key,)), snapshot).await
+    }
+
+    /// Writes the value of a record, and updates its index entries.
+    pub async fn set<T: WriteTransaction, P: TuplePack>(
+        &self,
+        trx: &T,
+        primary_key: &P,
+        value: &[u8],
+    ) -> FdbResult<()> {
+        let primary_key = pack(&(primary_key,));
+        self.update(trx, &primary_key, Some(value)).await
+    }
+
+    /// Removes a record, and its index entries.
+    pub async fn clear<T: WriteTransaction, P: TuplePack>(
+        &self,
+        trx: &T,
+        primary_key: &P,
+    ) -> FdbResult<()> {
+        let primary_key = pack(&(primary_key,));
+        self.update(trx, &primary_key, None).await
+    }
+
+    async fn update<T: WriteTransaction>(
+        &self,
+        trx: &T,
+        primary_key: &[u8],
+        value: Option<&[u8]>,
+    ) -> FdbResult<()> {
+        let mut key = self.primary.bytes().to_vec();
+        key.extend_from_slice(primary_key);
+
+        let previous = trx.get(&key, false).await?;
+        for index in &self.indexes {
+            let previous_entry = previous
+                .as_deref()
+                .and_then(|previous| index.entry(primary_key, previous));
+            let entry = value.and_then(|value| index.entry(primary_key, value));
+            if previous_entry == entry {
+                continue;
+            }
+            if let Some(previous_entry) = previous_entry {
+                trx.clear(&previous_entry);
+            }
+            if let Some(entry) = entry {
+                trx.set(&entry, &[]);
+            }
+        }
+
+        match value {
+            Some(value) => trx.set(&key, value),
+            None => trx.clear(&key),
+        }
+        Ok(())
+    }
+
+    /// Reads the records of a range of index entries, as `(key, value)` pairs in the order of the
+    /// index.
+    ///
+    /// `opt` is a range of `index.subspace()`, see `Index::range`. An entry is read and then its
+    /// record, the records being read concurrently.
+    pub async fn query<T: ReadTransaction>(
+        &self,
+        trx: &T,
+        index: &Index,
+        opt: RangeOption<'_>,
+        snapshot: bool,
+    ) -> Result<Vec<(Vec<u8>, Vec<u8>)>, FdbBindingError> {
+        let mut keys = Vec::new();
+        let mut opt = Some(opt);
+        let mut iteration = 1;
+        while let Some(range) = opt.take() {
+            let entries = trx.get_range(&range, iteration, snapshot).await?;
+            for entry in entries.iter() {
+                keys.push(self.record_key(index, entry.key())?);
+            }
+            opt = range.next_range(&entries);
+            iteration += 1;
+        }
+
+        let values = future::try_join_all(keys.iter().map(|key| trx.get(key, snapshot))).await?;
+        Ok(keys
+            .into_iter()
+            .zip(values)
+            .filter_map(|(key, value)| value.map(|value| (key, value.to_vec())))
+            .collect())
+    }
+
+    /// Reads the records of a range of index entries with `Transaction::get_mapped_range`, so
+    /// each read fetches the entries and their records in a single round trip.
+    ///
+    /// The mapper is built from the prefixes of the subspaces, which must then be packed tuples,
+    /// as the subspaces created with `Subspace::all().subspace(..)`. Otherwise, this falls back to
+    /// `IndexedSubspace::query`.
+    ///
+    /// See `IndexedSubspace::query`, and `Transaction::get_mapped_range` for the restrictions of
+    /// mapped reads.
+    #[cfg_api_versions(min = 710)]
+    pub async fn query_mapped(
+        &self,
+        trx: &Transaction,
+        index: &Index,
+        opt: RangeOption<'_>,
+        snapshot: bool,
+    ) -> Result<Vec<(Vec<u8>, Vec<u8>)>, FdbBindingError> {
+        let mapper = match self.mapper(index) {
+            Some(mapper) => mapper,
+            None => return self.query(trx, index, opt, snapshot).await,
+        };
+
+        let mut records = Vec::new();
+        let mut opt = Some(opt);
+        let mut iteration = 1;
+        while let Some(range) = opt.take() {
+            let mapped = trx
+                .get_mapped_range(&range, &mapper, iteration, snapshot)
+                .await?;
+            for mapped_key_value in mapped.iter() {
+                if let Some(record) = mapped_key_value.key_values().first() {
+                    records.push((record.key().to_vec(), record.value().to_vec()));
+                }
+            }
+            opt = range.next_mapped_range(&mapped);
+            iteration += 1;
+        }
+        Ok(records)
+    }
+
+    /// The key of the record of an index entry
+    fn record_key(&self, index: &Index, entry: &[u8]) -> Result<Vec<u8>, FdbBindingError> {
+        let (_, primary_key): (Element, Element) = index.subspace.unpack(entry)?;
+        Ok(self.primary.pack(&(primary_key,)))
+    }
+
+    /// The mapper from an index entry to its record, `None` if a prefix is not a packed tuple
+    #[cfg_api_versions(min = 710)]
+    fn mapper(&self, index: &Index) -> Option<Vec<u8>> {
+        let index_prefix: Vec<Element> = unpack(index.subspace.bytes()).ok()?;
+        let primary_prefix: Vec<Element> = unpack(self.primary.bytes()).ok()?;
+
+        let mut mapper: Vec<Element> = primary_prefix
+            .into_iter()
+            .map(|element| match element {
+                // braces are escaped by doubling them
+                Element::String(s) if s.contains(|c| c == '{' || c == '}') => {
+                    Element::String(Cow::Owned(s.replace('{', "{{").replace('}', "}}")))
+                }
+                element => element,
+            })
+            .collect();
+        mapper.push(Element::String(Cow::Owned(format!(
+            "{{K[{}]}}",
+            index_prefix.len() + 1
+        ))));
+        Some(pack(&mapper))
+    }
+}
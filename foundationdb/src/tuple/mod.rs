@@ -4,7 +4,11 @@
 
 mod element;
 pub mod hca;
+pub mod index;
 mod pack;
+pub mod queue;
+#[cfg(feature = "serde")]
+pub mod serde;
 mod subspace;
 mod versionstamp;
 
@@ -18,7 +22,8 @@ use std::result;
 pub use uuid::Uuid;
 
 pub use element::Element;
-pub use pack::{TuplePack, TupleUnpack, VersionstampOffset};
+pub use foundationdb_macros::{TuplePack, TupleUnpack};
+pub use pack::{FlatTuplePack, FlatTupleUnpack, TuplePack, TupleUnpack, VersionstampOffset};
 pub use subspace::Subspace;
 pub use versionstamp::Versionstamp;
 
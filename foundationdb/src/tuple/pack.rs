@@ -119,6 +119,67 @@ pub trait TupleUnpack<'de>: Sized {
     }
 }
 
+/// A tuple-like type that can be packed as a sequence of elements
+///
+/// This is what `#[tuple(flatten)]` relies on to inline the elements of a field into the tuple
+/// of its parent, and is implemented by `#[derive(TuplePack)]` and Rust tuples.
+pub trait FlatTuplePack {
+    /// Pack the elements one after the other, each of them at `tuple_depth`
+    fn pack_flat<W: io::Write>(
+        &self,
+        w: &mut W,
+        tuple_depth: TupleDepth,
+    ) -> io::Result<VersionstampOffset>;
+
+    /// Pack the elements as a tuple, nested if `tuple_depth` is not the root
+    fn pack_nested<W: io::Write>(
+        &self,
+        w: &mut W,
+        tuple_depth: TupleDepth,
+    ) -> io::Result<VersionstampOffset> {
+        let mut offset = VersionstampOffset::None { size: 0 };
+        if tuple_depth.depth() > 0 {
+            w.write_all(&[NESTED])?;
+            offset += 1;
+        }
+
+        offset += self.pack_flat(w, tuple_depth.increment())?;
+
+        if tuple_depth.depth() > 0 {
+            w.write_all(&[NIL])?;
+            offset += 1;
+        }
+        Ok(offset)
+    }
+}
+
+/// A tuple-like type that can be unpacked from a sequence of elements
+///
+/// This is the counterpart of [`FlatTuplePack`], implemented by `#[derive(TupleUnpack)]` and
+/// Rust tuples.
+pub trait FlatTupleUnpack<'de>: Sized {
+    /// Unpack the elements one after the other, each of them at `tuple_depth`
+    fn unpack_flat(input: &'de [u8], tuple_depth: TupleDepth) -> PackResult<(&'de [u8], Self)>;
+
+    /// Unpack the elements from a tuple, nested if `tuple_depth` is not the root
+    fn unpack_nested(input: &'de [u8], tuple_depth: TupleDepth) -> PackResult<(&'de [u8], Self)> {
+        let input = if tuple_depth.depth() > 0 {
+            parse_code(input, NESTED)?
+        } else {
+            input
+        };
+
+        let (input, this) = Self::unpack_flat(input, tuple_depth.increment())?;
+
+        let input = if tuple_depth.depth() > 0 {
+            parse_code(input, NIL)?
+        } else {
+            input
+        };
+        Ok((input, this))
+    }
+}
+
 impl<'a, T> TuplePack for &'a T
 where
     T: TuplePack,
@@ -133,7 +194,7 @@ where
 }
 
 #[inline]
-fn parse_bytes(input: &[u8], num: usize) -> PackResult<(&[u8], &[u8])> {
+pub(super) fn parse_bytes(input: &[u8], num: usize) -> PackResult<(&[u8], &[u8])> {
     if input.len() < num {
         Err(PackError::MissingBytes)
     } else {
@@ -142,7 +203,7 @@ fn parse_bytes(input: &[u8], num: usize) -> PackResult<(&[u8], &[u8])> {
 }
 
 #[inline]
-fn parse_byte(input: &[u8]) -> PackResult<(&[u8], u8)> {
+pub(super) fn parse_byte(input: &[u8]) -> PackResult<(&[u8], u8)> {
     if input.is_empty() {
         Err(PackError::MissingBytes)
     } else {
@@ -150,7 +211,7 @@ fn parse_byte(input: &[u8]) -> PackResult<(&[u8], u8)> {
     }
 }
 
-fn parse_code(input: &[u8], expected: u8) -> PackResult<&[u8]> {
+pub(super) fn parse_code(input: &[u8], expected: u8) -> PackResult<&[u8]> {
     let (input, found) = parse_byte(input)?;
     if found == expected {
         Ok(input)
@@ -286,6 +347,33 @@ macro_rules! tuple_impls {
                     Ok((input, tuple))
                 }
             }
+
+            impl<$($name),+> FlatTuplePack for ($($name,)+)
+            where
+                $($name: TuplePack,)+
+            {
+                fn pack_flat<W: io::Write>(&self, w: &mut W, tuple_depth: TupleDepth) -> io::Result<VersionstampOffset> {
+                    let mut offset = VersionstampOffset::None { size: 0 };
+                    $(
+                        offset += self.$n.pack(w, tuple_depth)?;
+                    )*
+                    Ok(offset)
+                }
+            }
+
+            impl<'de, $($name),+> FlatTupleUnpack<'de> for ($($name,)+)
+            where
+                $($name: TupleUnpack<'de>,)+
+            {
+                fn unpack_flat(input: &'de [u8], tuple_depth: TupleDepth) -> PackResult<(&'de [u8], Self)> {
+                    $(
+                        let (input, $v) = $name::unpack(input, tuple_depth)?;
+                    )*
+
+                    let tuple = ( $($v,)* );
+                    Ok((input, tuple))
+                }
+            }
         )+
     }
 }
@@ -755,7 +843,7 @@ where
     }
 }
 
-fn is_end_of_tuple(input: &[u8], nested: bool) -> bool {
+pub(super) fn is_end_of_tuple(input: &[u8], nested: bool) -> bool {
     match input.first() {
         None => true,
         _ if !nested => false,
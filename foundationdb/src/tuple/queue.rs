@@ -0,0 +1,339 @@
+// Copyright 2018 foundationdb-rs developers, https://github.com/Clikengo/foundationdb-rs/graphs/contributors
+// Copyright 2013-2018 Apple, Inc and the FoundationDB project authors.
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+//! A durable FIFO queue stored in a subspace.
+//!
+//! The queue uses three subspaces, derived from the subspace used to create it: "items",
+//! "requests" and "results".
+//!
+//! "items" contains the values of the queue : "items : (versionstamp, nonce)". Pushing writes the
+//! key with a `SetVersionstampedKey` operation, so producers never conflict with each other, and
+//! the items are ordered by commit version.
+//!
+//! Consumers popping the first items of the queue all conflict with each other. `Queue::pop`
+//! first tries to do so, and when its transaction conflicts, falls back to the pop requests of the
+//! high contention queue recipe:
+//!
+//! 1. The consumer registers a request : "requests : (versionstamp, nonce)", whose value is the
+//!    maximum number of items it wants. The key is also versionstamped, so registering does not
+//!    conflict either, and the requests are ordered.
+//!
+//! 2. Any consumer fulfills the pending requests in order: the first items are moved to
+//!    "results : (versionstamp, nonce) : index", and the request is cleared. Concurrent consumers
+//!    doing so conflict, but any of them committing makes progress for all of them.
+//!
+//! 3. The consumer takes the results of its request once it is cleared, or cancels the request if
+//!    the queue is empty.
+//!
+//! While its request is pending, a consumer waits a jittered backoff between its attempts, which
+//! doubles up to a second while no request is fulfilled, and is reset once values are moved.
+
+use std::convert::TryFrom;
+use std::sync::atomic::{AtomicU16, Ordering};
+use std::time::Duration;
+
+use futures::TryStreamExt;
+use rand::Rng;
+
+use crate::options::MutationType;
+use crate::timer::delay;
+use crate::tuple::{self, Subspace, Versionstamp};
+use crate::*;
+
+/// Maximum number of pop requests fulfilled by a single transaction
+const MAX_FULFILLED_REQUESTS: usize = 100;
+
+/// Initial backoff of a consumer waiting for its pop request to be fulfilled
+const MIN_POP_BACKOFF: Duration = Duration::from_millis(10);
+
+/// Maximum backoff of a consumer waiting for its pop request to be fulfilled
+const MAX_POP_BACKOFF: Duration = Duration::from_secs(1);
+
+/// Represents a FIFO queue for a given subspace
+#[derive(Debug)]
+pub struct Queue {
+    subspace: Subspace,
+    items: Subspace,
+    requests: Subspace,
+    results: Subspace,
+    user_version: AtomicU16,
+}
+
+impl Queue {
+    /// Constructs a queue that will store its items in the input subspace.
+    /// The given subspace should not be used by anything other than the queue
+    pub fn new(subspace: Subspace) -> Queue {
+        Queue {
+            items: subspace.subspace(&0i64),
+            requests: subspace.subspace(&1i64),
+            results: subspace.subspace(&2i64),
+            subspace,
+            user_version: AtomicU16::new(0),
+        }
+    }
+
+    /// Adds a value at the end of the queue.
+    ///
+    /// The position of the value is determined by the commit version of the transaction, values
+    /// pushed by a single transaction are in the order of the calls.
+    pub fn push<T: WriteTransaction>(&self, trx: &T, value: &[u8]) {
+        let user_version = self.user_version.fetch_add(1, Ordering::Relaxed);
+        let mut key = self.items.bytes().to_vec();
+        tuple::pack_into_with_versionstamp(
+            &(
+                Versionstamp::incomplete(user_version),
+                rand::random::<u32>(),
+            ),
+            &mut key,
+        );
+        trx.atomic_op(&key, value, MutationType::SetVersionstampedKey);
+    }
+
+    /// Reads the first value of the queue, without removing it.
+    ///
+    /// The read is a snapshot read, so it does not conflict with consumers.
+    pub async fn peek<T: ReadTransaction>(&self, trx: &T) -> FdbResult<Option<Vec<u8>>> {
+        Ok(self.peek_batch(trx, 1).await?.pop())
+    }
+
+    /// Reads up to `max` values from the start of the queue, without removing them.
+    ///
+    /// The read is a snapshot read, so it does not conflict with consumers.
+    pub async fn peek_batch<T: ReadTransaction>(
+        &self,
+        trx: &T,
+        max: usize,
+    ) -> FdbResult<Vec<Vec<u8>>> {
+        if max == 0 {
+            return Ok(Vec::new());
+        }
+        let items = trx.get_range(&self.first_items(max), 1, true).await?;
+        Ok(items.iter().map(|item| item.value().to_vec()).collect())
+    }
+
+    /// Removes up to `max` values from the start of the queue, and returns them.
+    ///
+    /// The transaction conflicts with any other transaction popping from the queue, see
+    /// `Queue::pop` to have several consumers.
+    pub async fn pop_batch<T: WriteTransaction>(
+        &self,
+        trx: &T,
+        max: usize,
+    ) -> FdbResult<Vec<Vec<u8>>> {
+        if max == 0 {
+            return Ok(Vec::new());
+        }
+        let items = trx.get_range(&self.first_items(max), 1, false).await?;
+        Ok(items
+            .iter()
+            .map(|item| {
+                trx.clear(item.key());
+                item.value().to_vec()
+            })
+            .collect())
+    }
+
+    /// Returns `true` if the queue has no values.
+    pub async fn is_empty<T: ReadTransaction>(&self, trx: &T) -> FdbResult<bool> {
+        let items = trx.get_range(&self.first_items(1), 1, false).await?;
+        Ok(items.is_empty())
+    }
+
+    /// Removes every value of the queue, and the pending pop requests.
+    pub fn clear<T: WriteTransaction>(&self, trx: &T) {
+        trx.clear_subspace_range(&self.subspace)
+    }
+
+    /// Removes up to `max` values from the start of the queue, and returns them.
+    ///
+    /// The values are popped in their own transactions, and an empty vector is returned when the
+    /// queue is empty. Consumers popping concurrently get distinct values: when they conflict,
+    /// they register pop requests which are fulfilled in order.
+    ///
+    /// # Warning
+    ///
+    /// When a commit result is unknown, see `FdbError::is_maybe_committed`, the popped values may
+    /// be lost.
+    pub async fn pop(&self, db: &Database, max: usize) -> FdbResult<Vec<Vec<u8>>> {
+        if max == 0 {
+            return Ok(Vec::new());
+        }
+
+        let mut trx = db.create_trx()?;
+        loop {
+            let values = match self.pop_batch(&trx, max).await {
+                Ok(values) => values,
+                Err(err) => {
+                    trx = trx.on_error(err).await?;
+                    continue;
+                }
+            };
+            match trx.commit().await {
+                Ok(_) => return Ok(values),
+                Err(err) if err.kind() == FdbErrorKind::NotCommitted => break,
+                Err(err) => trx = err.on_error().await?,
+            }
+        }
+
+        let request = self.add_pop_request(db, max).await?;
+        let mut backoff = MIN_POP_BACKOFF;
+        loop {
+            let fulfilled = self.fulfill_pop_requests(db).await?;
+            if let Some(values) = self.take_pop_results(db, &request).await? {
+                return Ok(values);
+            }
+
+            backoff = if fulfilled {
+                MIN_POP_BACKOFF
+            } else {
+                (backoff * 2).min(MAX_POP_BACKOFF)
+            };
+            let jitter = rand::thread_rng().gen_range(0.5..1.0);
+            delay(backoff.mul_f64(jitter)).await;
+        }
+    }
+
+    fn first_items(&self, max: usize) -> RangeOption<'static> {
+        RangeOption {
+            limit: Some(max),
+            ..RangeOption::from(&self.items)
+        }
+    }
+
+    /// The subspace of the values given to a pop request
+    fn results_of(&self, request: &[u8]) -> Subspace {
+        let mut prefix = self.results.bytes().to_vec();
+        prefix.extend_from_slice(&request[self.requests.bytes().len()..]);
+        Subspace::from_bytes(prefix)
+    }
+
+    /// Registers a pop request for `max` values, returning its key
+    async fn add_pop_request(&self, db: &Database, max: usize) -> FdbResult<Vec<u8>> {
+        let nonce = rand::random::<u32>();
+        let max = u32::try_from(max).unwrap_or(u32::MAX);
+        let mut key = self.requests.bytes().to_vec();
+        tuple::pack_into_with_versionstamp(&(Versionstamp::incomplete(0), nonce), &mut key);
+
+        let mut trx = db.create_trx()?;
+        loop {
+            trx.atomic_op(&key, &max.to_le_bytes(), MutationType::SetVersionstampedKey);
+            let versionstamp = trx.get_versionstamp();
+            match trx.commit().await {
+                Ok(_) => {
+                    let versionstamp = versionstamp.await?;
+                    let mut tr_version = [0; 10];
+                    tr_version.copy_from_slice(&versionstamp[..10]);
+                    return Ok(self
+                        .requests
+                        .pack(&(Versionstamp::complete(tr_version, 0), nonce)));
+                }
+                Err(err) => trx = err.on_error().await?,
+            }
+        }
+    }
+
+    /// Gives the first values of the queue to the pending pop requests, in order.
+    ///
+    /// A conflict means another consumer fulfilled them, so the transaction is not retried.
+    /// Returns `true` if this transaction gave values to at least one request.
+    async fn fulfill_pop_requests(&self, db: &Database) -> FdbResult<bool> {
+        let trx = db.create_trx()?;
+        let fulfilled = match self.fulfill_pop_requests_with(&trx).await {
+            Ok(fulfilled) => fulfilled,
+            Err(err) => {
+                trx.on_error(err).await?;
+                return Ok(false);
+            }
+        };
+        if let Err(err) = trx.commit().await {
+            err.on_error().await?;
+            return Ok(false);
+        }
+        Ok(fulfilled)
+    }
+
+    async fn fulfill_pop_requests_with(&self, trx: &Transaction) -> FdbResult<bool> {
+        let requests_range = RangeOption {
+            limit: Some(MAX_FULFILLED_REQUESTS),
+            ..RangeOption::from(&self.requests)
+        };
+        let requests = trx.get_range(&requests_range, 1, false).await?;
+        let wanted: Vec<usize> = requests
+            .iter()
+            .map(|request| {
+                <[u8; 4]>::try_from(request.value())
+                    .map_or(1, |max| u32::from_le_bytes(max) as usize)
+            })
+            .collect();
+        let total = wanted
+            .iter()
+            .fold(0usize, |total, max| total.saturating_add(*max));
+        if total == 0 {
+            return Ok(false);
+        }
+
+        let items = trx.get_range(&self.first_items(total), 1, false).await?;
+        let mut items = items.iter();
+        let mut fulfilled = false;
+        for (request, max) in requests.iter().zip(wanted) {
+            let results = self.results_of(request.key());
+            let mut count = 0usize;
+            for item in items.by_ref().take(max) {
+                trx.set(&results.pack(&count), item.value());
+                trx.clear(item.key());
+                count += 1;
+            }
+            if count == 0 {
+                break;
+            }
+            trx.clear(request.key());
+            fulfilled = true;
+        }
+        Ok(fulfilled)
+    }
+
+    /// Takes the values given to a pop request, or cancels it if the queue is empty.
+    ///
+    /// Returns `None` while the request is waiting for values.
+    async fn take_pop_results(
+        &self,
+        db: &Database,
+        request: &[u8],
+    ) -> FdbResult<Option<Vec<Vec<u8>>>> {
+        let mut trx = db.create_trx()?;
+        loop {
+            let result = async {
+                if trx.get(request, false).await?.is_some() {
+                    if !self.is_empty(&trx).await? {
+                        return Ok(None);
+                    }
+                    trx.clear(request);
+                    return Ok(Some(Vec::new()));
+                }
+                let results = self.results_of(request);
+                let values: Vec<Vec<u8>> = trx
+                    .get_ranges_keyvalues(RangeOption::from(&results), false)
+                    .map_ok(|result| result.value().to_vec())
+                    .try_collect()
+                    .await?;
+                trx.clear_subspace_range(&results);
+                Ok(Some(values))
+            }
+            .await;
+
+            match result {
+                Ok(None) => return Ok(None),
+                Ok(Some(values)) => match trx.commit().await {
+                    Ok(_) => return Ok(Some(values)),
+                    Err(err) => trx = err.on_error().await?,
+                },
+                Err(err) => trx = trx.on_error(err).await?,
+            }
+        }
+    }
+}
@@ -0,0 +1,1053 @@
+// Copyright 2018 foundationdb-rs developers, https://github.com/Clikengo/foundationdb-rs/graphs/contributors
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+//! [Serde](https://serde.rs) support for the tuple layer
+//!
+//! The serde data model is mapped onto the existing tuple typecodes, so the produced bytes are
+//! regular tuples that any other binding can read, and that sort like the equivalent [`Element`]:
+//!
+//! - `bool`, integers, floats, strings, chars and byte buffers use their tuple typecode,
+//! - `None` is packed as `Nil`, `Some(v)` is packed as `v`,
+//! - `()` and unit structs are packed as an empty tuple,
+//! - newtype structs are transparent,
+//! - sequences, tuples and structs are packed as a tuple of their fields, in declaration order,
+//! - maps are packed as a tuple of `(key, value)` tuples,
+//! - unit variants are packed as their variant index, other variants as a tuple starting with
+//!   their variant index followed by their fields.
+//!
+//! Field names are never packed, renaming a field is free but reordering fields is not.
+//!
+//! ```
+//! use foundationdb::tuple::{serde::Serde, Subspace};
+//! use serde::{Deserialize, Serialize};
+//!
+//! #[derive(Debug, PartialEq, Serialize, Deserialize)]
+//! struct UserKey {
+//!     tenant: String,
+//!     id: u64,
+//! }
+//!
+//! let subspace = Subspace::all().subspace(&"users");
+//! let key = UserKey {
+//!     tenant: "acme".to_string(),
+//!     id: 42,
+//! };
+//!
+//! let packed = subspace.pack(&Serde(&key));
+//! assert_eq!(packed, subspace.pack(&("acme", 42)));
+//!
+//! let Serde(unpacked): Serde<UserKey> = subspace.unpack(&packed).unwrap();
+//! assert_eq!(unpacked, key);
+//! ```
+
+use super::pack::{is_end_of_tuple, parse_code};
+use super::*;
+
+use ::serde::de::{self, IntoDeserializer};
+use ::serde::ser::{self, Serialize};
+use ::serde::Deserialize;
+
+/// Serialize the given value as a tuple
+pub fn to_bytes<T: Serialize + ?Sized>(value: &T) -> PackResult<Vec<u8>> {
+    let mut output = Vec::new();
+    value.serialize(&mut Serializer::new(&mut output))?;
+    Ok(output)
+}
+
+/// Deserialize an instance of type `T` from a packed tuple
+pub fn from_bytes<'de, T: Deserialize<'de>>(input: &'de [u8]) -> PackResult<T> {
+    let mut deserializer = Deserializer::new(input);
+    let value = T::deserialize(&mut deserializer)?;
+    deserializer.end()?;
+    Ok(value)
+}
+
+/// Wraps a serde type so it can be used wherever a [`TuplePack`] or a [`TupleUnpack`] is
+/// expected, like [`Subspace::pack`] or inside a Rust tuple.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Serde<T>(pub T);
+
+impl<T: Serialize> TuplePack for Serde<T> {
+    fn pack<W: io::Write>(
+        &self,
+        w: &mut W,
+        tuple_depth: TupleDepth,
+    ) -> io::Result<VersionstampOffset> {
+        let mut serializer = Serializer {
+            writer: w,
+            tuple_depth,
+        };
+        self.0.serialize(&mut serializer).map_err(|err| match err {
+            PackError::IoError(err) => err,
+            err => io::Error::new(io::ErrorKind::InvalidData, err),
+        })
+    }
+}
+
+impl<'de, T: Deserialize<'de>> TupleUnpack<'de> for Serde<T> {
+    fn unpack(input: &'de [u8], tuple_depth: TupleDepth) -> PackResult<(&'de [u8], Self)> {
+        let mut deserializer = Deserializer { input, tuple_depth };
+        let value = T::deserialize(&mut deserializer)?;
+        Ok((deserializer.input, Serde(value)))
+    }
+}
+
+impl ser::Error for PackError {
+    fn custom<T: Display>(msg: T) -> Self {
+        PackError::Message(msg.to_string().into_boxed_str())
+    }
+}
+
+impl de::Error for PackError {
+    fn custom<T: Display>(msg: T) -> Self {
+        PackError::Message(msg.to_string().into_boxed_str())
+    }
+}
+
+/// A serde `Serializer` writing the tuple encoding into an `io::Write`
+pub struct Serializer<W> {
+    writer: W,
+    tuple_depth: TupleDepth,
+}
+
+impl<W: io::Write> Serializer<W> {
+    /// Creates a serializer packing a root tuple into `writer`
+    pub fn new(writer: W) -> Self {
+        Self {
+            writer,
+            tuple_depth: TupleDepth::new(),
+        }
+    }
+
+    /// Unwraps the underlying writer
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+
+    fn pack<T: TuplePack>(&mut self, v: &T) -> PackResult<VersionstampOffset> {
+        Ok(v.pack(&mut self.writer, self.tuple_depth)?)
+    }
+}
+
+impl<'a, W: io::Write> ser::Serializer for &'a mut Serializer<W> {
+    type Ok = VersionstampOffset;
+    type Error = PackError;
+
+    type SerializeSeq = Compound<'a, W>;
+    type SerializeTuple = Compound<'a, W>;
+    type SerializeTupleStruct = Compound<'a, W>;
+    type SerializeTupleVariant = Compound<'a, W>;
+    type SerializeMap = Compound<'a, W>;
+    type SerializeStruct = Compound<'a, W>;
+    type SerializeStructVariant = Compound<'a, W>;
+
+    fn is_human_readable(&self) -> bool {
+        false
+    }
+
+    fn serialize_bool(self, v: bool) -> PackResult<VersionstampOffset> {
+        self.pack(&v)
+    }
+
+    fn serialize_i8(self, v: i8) -> PackResult<VersionstampOffset> {
+        self.pack(&i64::from(v))
+    }
+
+    fn serialize_i16(self, v: i16) -> PackResult<VersionstampOffset> {
+        self.pack(&v)
+    }
+
+    fn serialize_i32(self, v: i32) -> PackResult<VersionstampOffset> {
+        self.pack(&v)
+    }
+
+    fn serialize_i64(self, v: i64) -> PackResult<VersionstampOffset> {
+        self.pack(&v)
+    }
+
+    fn serialize_i128(self, v: i128) -> PackResult<VersionstampOffset> {
+        self.pack(&v)
+    }
+
+    fn serialize_u8(self, v: u8) -> PackResult<VersionstampOffset> {
+        self.pack(&u64::from(v))
+    }
+
+    fn serialize_u16(self, v: u16) -> PackResult<VersionstampOffset> {
+        self.pack(&v)
+    }
+
+    fn serialize_u32(self, v: u32) -> PackResult<VersionstampOffset> {
+        self.pack(&v)
+    }
+
+    fn serialize_u64(self, v: u64) -> PackResult<VersionstampOffset> {
+        self.pack(&v)
+    }
+
+    fn serialize_u128(self, v: u128) -> PackResult<VersionstampOffset> {
+        self.pack(&v)
+    }
+
+    fn serialize_f32(self, v: f32) -> PackResult<VersionstampOffset> {
+        self.pack(&v)
+    }
+
+    fn serialize_f64(self, v: f64) -> PackResult<VersionstampOffset> {
+        self.pack(&v)
+    }
+
+    fn serialize_char(self, v: char) -> PackResult<VersionstampOffset> {
+        let mut buf = [0u8; 4];
+        self.pack(&&*v.encode_utf8(&mut buf))
+    }
+
+    fn serialize_str(self, v: &str) -> PackResult<VersionstampOffset> {
+        self.pack(&v)
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> PackResult<VersionstampOffset> {
+        self.pack(&Bytes::from(v))
+    }
+
+    fn serialize_none(self) -> PackResult<VersionstampOffset> {
+        self.pack(&Option::<()>::None)
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> PackResult<VersionstampOffset> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> PackResult<VersionstampOffset> {
+        self.pack(&())
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> PackResult<VersionstampOffset> {
+        self.serialize_unit()
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        variant_index: u32,
+        _variant: &'static str,
+    ) -> PackResult<VersionstampOffset> {
+        self.serialize_u32(variant_index)
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> PackResult<VersionstampOffset> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        variant_index: u32,
+        _variant: &'static str,
+        value: &T,
+    ) -> PackResult<VersionstampOffset> {
+        let mut compound = Compound::begin(self)?;
+        compound.element(&variant_index)?;
+        compound.element(value)?;
+        compound.finish()
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> PackResult<Self::SerializeSeq> {
+        Compound::begin(self)
+    }
+
+    fn serialize_tuple(self, _len: usize) -> PackResult<Self::SerializeTuple> {
+        Compound::begin(self)
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> PackResult<Self::SerializeTupleStruct> {
+        Compound::begin(self)
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> PackResult<Self::SerializeTupleVariant> {
+        let mut compound = Compound::begin(self)?;
+        compound.element(&variant_index)?;
+        Ok(compound)
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> PackResult<Self::SerializeMap> {
+        Compound::begin(self)
+    }
+
+    fn serialize_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> PackResult<Self::SerializeStruct> {
+        Compound::begin(self)
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> PackResult<Self::SerializeStructVariant> {
+        let mut compound = Compound::begin(self)?;
+        compound.element(&variant_index)?;
+        Ok(compound)
+    }
+}
+
+/// Serializes the elements of a tuple, nesting them if the tuple is not the root one
+pub struct Compound<'a, W> {
+    ser: &'a mut Serializer<W>,
+    offset: VersionstampOffset,
+    tuple_depth: TupleDepth,
+}
+
+impl<'a, W: io::Write> Compound<'a, W> {
+    fn begin(ser: &'a mut Serializer<W>) -> PackResult<Self> {
+        let tuple_depth = ser.tuple_depth;
+        let mut offset = VersionstampOffset::None { size: 0 };
+        if tuple_depth.depth() > 0 {
+            ser.writer.write_all(&[NESTED])?;
+            offset += 1;
+        }
+        ser.tuple_depth = tuple_depth.increment();
+        Ok(Self {
+            ser,
+            offset,
+            tuple_depth,
+        })
+    }
+
+    fn element<T: Serialize + ?Sized>(&mut self, value: &T) -> PackResult<()> {
+        self.offset += value.serialize(&mut *self.ser)?;
+        Ok(())
+    }
+
+    fn finish(self) -> PackResult<VersionstampOffset> {
+        let mut offset = self.offset;
+        self.ser.tuple_depth = self.tuple_depth;
+        if self.tuple_depth.depth() > 0 {
+            self.ser.writer.write_all(&[NIL])?;
+            offset += 1;
+        }
+        Ok(offset)
+    }
+}
+
+impl<'a, W: io::Write> ser::SerializeSeq for Compound<'a, W> {
+    type Ok = VersionstampOffset;
+    type Error = PackError;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> PackResult<()> {
+        self.element(value)
+    }
+
+    fn end(self) -> PackResult<VersionstampOffset> {
+        self.finish()
+    }
+}
+
+impl<'a, W: io::Write> ser::SerializeTuple for Compound<'a, W> {
+    type Ok = VersionstampOffset;
+    type Error = PackError;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> PackResult<()> {
+        self.element(value)
+    }
+
+    fn end(self) -> PackResult<VersionstampOffset> {
+        self.finish()
+    }
+}
+
+impl<'a, W: io::Write> ser::SerializeTupleStruct for Compound<'a, W> {
+    type Ok = VersionstampOffset;
+    type Error = PackError;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> PackResult<()> {
+        self.element(value)
+    }
+
+    fn end(self) -> PackResult<VersionstampOffset> {
+        self.finish()
+    }
+}
+
+impl<'a, W: io::Write> ser::SerializeTupleVariant for Compound<'a, W> {
+    type Ok = VersionstampOffset;
+    type Error = PackError;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> PackResult<()> {
+        self.element(value)
+    }
+
+    fn end(self) -> PackResult<VersionstampOffset> {
+        self.finish()
+    }
+}
+
+impl<'a, W: io::Write> ser::SerializeMap for Compound<'a, W> {
+    type Ok = VersionstampOffset;
+    type Error = PackError;
+
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> PackResult<()> {
+        // every entry is a nested `(key, value)` tuple
+        self.ser.writer.write_all(&[NESTED])?;
+        self.offset += 1;
+        self.ser.tuple_depth = self.tuple_depth.increment().increment();
+        self.element(key)
+    }
+
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> PackResult<()> {
+        self.element(value)?;
+        self.ser.tuple_depth = self.tuple_depth.increment();
+        self.ser.writer.write_all(&[NIL])?;
+        self.offset += 1;
+        Ok(())
+    }
+
+    fn end(self) -> PackResult<VersionstampOffset> {
+        self.finish()
+    }
+}
+
+impl<'a, W: io::Write> ser::SerializeStruct for Compound<'a, W> {
+    type Ok = VersionstampOffset;
+    type Error = PackError;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        _key: &'static str,
+        value: &T,
+    ) -> PackResult<()> {
+        self.element(value)
+    }
+
+    fn end(self) -> PackResult<VersionstampOffset> {
+        self.finish()
+    }
+}
+
+impl<'a, W: io::Write> ser::SerializeStructVariant for Compound<'a, W> {
+    type Ok = VersionstampOffset;
+    type Error = PackError;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        _key: &'static str,
+        value: &T,
+    ) -> PackResult<()> {
+        self.element(value)
+    }
+
+    fn end(self) -> PackResult<VersionstampOffset> {
+        self.finish()
+    }
+}
+
+/// A serde `Deserializer` reading the tuple encoding from a byte slice
+pub struct Deserializer<'de> {
+    input: &'de [u8],
+    tuple_depth: TupleDepth,
+}
+
+impl<'de> Deserializer<'de> {
+    /// Creates a deserializer unpacking a root tuple from `input`
+    pub fn new(input: &'de [u8]) -> Self {
+        Self {
+            input,
+            tuple_depth: TupleDepth::new(),
+        }
+    }
+
+    /// Checks that the whole input has been consumed
+    pub fn end(&self) -> PackResult<()> {
+        if self.input.is_empty() {
+            Ok(())
+        } else {
+            Err(PackError::TrailingBytes)
+        }
+    }
+
+    fn unpack<T: TupleUnpack<'de>>(&mut self) -> PackResult<T> {
+        let (input, v) = T::unpack(self.input, self.tuple_depth)?;
+        self.input = input;
+        Ok(v)
+    }
+
+    fn peek(&self) -> PackResult<u8> {
+        self.input.first().copied().ok_or(PackError::MissingBytes)
+    }
+
+    /// Enters a tuple, returning the depth to restore once the tuple is consumed
+    fn begin_tuple(&mut self) -> PackResult<TupleDepth> {
+        let tuple_depth = self.tuple_depth;
+        if tuple_depth.depth() > 0 {
+            self.input = parse_code(self.input, NESTED)?;
+        }
+        self.tuple_depth = tuple_depth.increment();
+        Ok(tuple_depth)
+    }
+
+    fn end_tuple(&mut self, tuple_depth: TupleDepth) -> PackResult<()> {
+        self.tuple_depth = tuple_depth;
+        if tuple_depth.depth() > 0 {
+            self.input = parse_code(self.input, NIL)?;
+        }
+        Ok(())
+    }
+
+    fn at_end_of_tuple(&self) -> bool {
+        is_end_of_tuple(self.input, self.tuple_depth.depth() > 1)
+    }
+
+    fn deserialize_integer<V: de::Visitor<'de>>(&mut self, visitor: V) -> PackResult<V::Value> {
+        if self.peek()? < INTZERO {
+            match self.unpack::<i64>() {
+                Ok(v) => visitor.visit_i64(v),
+                Err(PackError::UnsupportedIntLength) => visitor.visit_i128(self.unpack()?),
+                Err(err) => Err(err),
+            }
+        } else {
+            match self.unpack::<u64>() {
+                Ok(v) => visitor.visit_u64(v),
+                Err(PackError::UnsupportedIntLength) => visitor.visit_u128(self.unpack()?),
+                Err(err) => Err(err),
+            }
+        }
+    }
+
+    fn deserialize_tuple_of<V: de::Visitor<'de>>(&mut self, visitor: V) -> PackResult<V::Value> {
+        let tuple_depth = self.begin_tuple()?;
+        let value = visitor.visit_seq(Access::new(self))?;
+        self.end_tuple(tuple_depth)?;
+        Ok(value)
+    }
+}
+
+impl<'de> de::Deserializer<'de> for &mut Deserializer<'de> {
+    type Error = PackError;
+
+    fn is_human_readable(&self) -> bool {
+        false
+    }
+
+    fn deserialize_any<V: de::Visitor<'de>>(self, visitor: V) -> PackResult<V::Value> {
+        if self.tuple_depth.depth() == 0 {
+            // The root tuple is only visited as a sequence if it holds more than one element
+            if self.input.is_empty() {
+                return visitor.visit_unit();
+            }
+            let (input, _) = Element::unpack(self.input, self.tuple_depth.increment())?;
+            if !input.is_empty() {
+                return self.deserialize_tuple_of(visitor);
+            }
+        }
+
+        match self.peek()? {
+            NIL => self.deserialize_option(visitor),
+            BYTES => self.deserialize_byte_buf(visitor),
+            STRING => self.deserialize_string(visitor),
+            NESTED => self.deserialize_tuple_of(visitor),
+            NEGINTSTART..=POSINTEND => self.deserialize_integer(visitor),
+            FLOAT => self.deserialize_f32(visitor),
+            DOUBLE => self.deserialize_f64(visitor),
+            FALSE | TRUE => self.deserialize_bool(visitor),
+            VERSIONSTAMP => {
+                let versionstamp: Versionstamp = self.unpack()?;
+                visitor.visit_bytes(versionstamp.as_bytes())
+            }
+            #[cfg(feature = "uuid")]
+            UUID => {
+                let uuid: Uuid = self.unpack()?;
+                visitor.visit_bytes(uuid.as_bytes())
+            }
+            found => Err(PackError::BadCode {
+                found,
+                expected: None,
+            }),
+        }
+    }
+
+    fn deserialize_bool<V: de::Visitor<'de>>(self, visitor: V) -> PackResult<V::Value> {
+        visitor.visit_bool(self.unpack()?)
+    }
+
+    fn deserialize_i8<V: de::Visitor<'de>>(self, visitor: V) -> PackResult<V::Value> {
+        self.deserialize_integer(visitor)
+    }
+
+    fn deserialize_i16<V: de::Visitor<'de>>(self, visitor: V) -> PackResult<V::Value> {
+        self.deserialize_integer(visitor)
+    }
+
+    fn deserialize_i32<V: de::Visitor<'de>>(self, visitor: V) -> PackResult<V::Value> {
+        self.deserialize_integer(visitor)
+    }
+
+    fn deserialize_i64<V: de::Visitor<'de>>(self, visitor: V) -> PackResult<V::Value> {
+        self.deserialize_integer(visitor)
+    }
+
+    fn deserialize_i128<V: de::Visitor<'de>>(self, visitor: V) -> PackResult<V::Value> {
+        visitor.visit_i128(self.unpack()?)
+    }
+
+    fn deserialize_u8<V: de::Visitor<'de>>(self, visitor: V) -> PackResult<V::Value> {
+        self.deserialize_integer(visitor)
+    }
+
+    fn deserialize_u16<V: de::Visitor<'de>>(self, visitor: V) -> PackResult<V::Value> {
+        self.deserialize_integer(visitor)
+    }
+
+    fn deserialize_u32<V: de::Visitor<'de>>(self, visitor: V) -> PackResult<V::Value> {
+        self.deserialize_integer(visitor)
+    }
+
+    fn deserialize_u64<V: de::Visitor<'de>>(self, visitor: V) -> PackResult<V::Value> {
+        self.deserialize_integer(visitor)
+    }
+
+    fn deserialize_u128<V: de::Visitor<'de>>(self, visitor: V) -> PackResult<V::Value> {
+        visitor.visit_u128(self.unpack()?)
+    }
+
+    fn deserialize_f32<V: de::Visitor<'de>>(self, visitor: V) -> PackResult<V::Value> {
+        visitor.visit_f32(self.unpack()?)
+    }
+
+    fn deserialize_f64<V: de::Visitor<'de>>(self, visitor: V) -> PackResult<V::Value> {
+        visitor.visit_f64(self.unpack()?)
+    }
+
+    fn deserialize_char<V: de::Visitor<'de>>(self, visitor: V) -> PackResult<V::Value> {
+        self.deserialize_str(visitor)
+    }
+
+    fn deserialize_str<V: de::Visitor<'de>>(self, visitor: V) -> PackResult<V::Value> {
+        match self.unpack::<Cow<'de, str>>()? {
+            Cow::Borrowed(v) => visitor.visit_borrowed_str(v),
+            Cow::Owned(v) => visitor.visit_string(v),
+        }
+    }
+
+    fn deserialize_string<V: de::Visitor<'de>>(self, visitor: V) -> PackResult<V::Value> {
+        self.deserialize_str(visitor)
+    }
+
+    fn deserialize_bytes<V: de::Visitor<'de>>(self, visitor: V) -> PackResult<V::Value> {
+        match self.unpack::<Bytes<'de>>()?.0 {
+            Cow::Borrowed(v) => visitor.visit_borrowed_bytes(v),
+            Cow::Owned(v) => visitor.visit_byte_buf(v),
+        }
+    }
+
+    fn deserialize_byte_buf<V: de::Visitor<'de>>(self, visitor: V) -> PackResult<V::Value> {
+        self.deserialize_bytes(visitor)
+    }
+
+    fn deserialize_option<V: de::Visitor<'de>>(self, visitor: V) -> PackResult<V::Value> {
+        // Inside a nested tuple, a lone NIL is the end-of-tuple marker, not a `None`
+        let is_none = self.input.first() == Some(&NIL)
+            && (self.tuple_depth.depth() <= 1 || self.input.get(1) == Some(&ESCAPE));
+        if is_none {
+            self.unpack::<Option<()>>()?;
+            visitor.visit_none()
+        } else {
+            visitor.visit_some(self)
+        }
+    }
+
+    fn deserialize_unit<V: de::Visitor<'de>>(self, visitor: V) -> PackResult<V::Value> {
+        self.unpack::<()>()?;
+        visitor.visit_unit()
+    }
+
+    fn deserialize_unit_struct<V: de::Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> PackResult<V::Value> {
+        self.deserialize_unit(visitor)
+    }
+
+    fn deserialize_newtype_struct<V: de::Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> PackResult<V::Value> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_seq<V: de::Visitor<'de>>(self, visitor: V) -> PackResult<V::Value> {
+        self.deserialize_tuple_of(visitor)
+    }
+
+    fn deserialize_tuple<V: de::Visitor<'de>>(
+        self,
+        _len: usize,
+        visitor: V,
+    ) -> PackResult<V::Value> {
+        self.deserialize_tuple_of(visitor)
+    }
+
+    fn deserialize_tuple_struct<V: de::Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _len: usize,
+        visitor: V,
+    ) -> PackResult<V::Value> {
+        self.deserialize_tuple_of(visitor)
+    }
+
+    fn deserialize_map<V: de::Visitor<'de>>(self, visitor: V) -> PackResult<V::Value> {
+        let tuple_depth = self.begin_tuple()?;
+        let value = visitor.visit_map(Access::new(self))?;
+        self.end_tuple(tuple_depth)?;
+        Ok(value)
+    }
+
+    fn deserialize_struct<V: de::Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> PackResult<V::Value> {
+        self.deserialize_tuple_of(visitor)
+    }
+
+    fn deserialize_enum<V: de::Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> PackResult<V::Value> {
+        if self.tuple_depth.depth() > 0 && self.input.first() != Some(&NESTED) {
+            // unit variants are packed as their bare variant index
+            return visitor.visit_enum(Variant {
+                de: self,
+                nested: false,
+            });
+        }
+
+        let tuple_depth = self.begin_tuple()?;
+        let value = visitor.visit_enum(Variant {
+            de: &mut *self,
+            nested: true,
+        })?;
+        self.end_tuple(tuple_depth)?;
+        Ok(value)
+    }
+
+    fn deserialize_identifier<V: de::Visitor<'de>>(self, visitor: V) -> PackResult<V::Value> {
+        self.deserialize_any(visitor)
+    }
+
+    fn deserialize_ignored_any<V: de::Visitor<'de>>(self, visitor: V) -> PackResult<V::Value> {
+        let (input, _) = Element::unpack(self.input, self.tuple_depth)?;
+        self.input = input;
+        visitor.visit_unit()
+    }
+}
+
+/// Gives access to the elements of a tuple
+struct Access<'a, 'de> {
+    de: &'a mut Deserializer<'de>,
+    tuple_depth: TupleDepth,
+}
+
+impl<'a, 'de> Access<'a, 'de> {
+    fn new(de: &'a mut Deserializer<'de>) -> Self {
+        let tuple_depth = de.tuple_depth;
+        Self { de, tuple_depth }
+    }
+}
+
+impl<'a, 'de> de::SeqAccess<'de> for Access<'a, 'de> {
+    type Error = PackError;
+
+    fn next_element_seed<T: de::DeserializeSeed<'de>>(
+        &mut self,
+        seed: T,
+    ) -> PackResult<Option<T::Value>> {
+        if self.de.at_end_of_tuple() {
+            return Ok(None);
+        }
+        seed.deserialize(&mut *self.de).map(Some)
+    }
+}
+
+impl<'a, 'de> de::MapAccess<'de> for Access<'a, 'de> {
+    type Error = PackError;
+
+    fn next_key_seed<K: de::DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> PackResult<Option<K::Value>> {
+        if self.de.at_end_of_tuple() {
+            return Ok(None);
+        }
+        self.de.input = parse_code(self.de.input, NESTED)?;
+        self.de.tuple_depth = self.tuple_depth.increment();
+        seed.deserialize(&mut *self.de).map(Some)
+    }
+
+    fn next_value_seed<V: de::DeserializeSeed<'de>>(&mut self, seed: V) -> PackResult<V::Value> {
+        let value = seed.deserialize(&mut *self.de)?;
+        self.de.tuple_depth = self.tuple_depth;
+        self.de.input = parse_code(self.de.input, NIL)?;
+        Ok(value)
+    }
+}
+
+/// Gives access to an enum variant, `nested` is false for bare unit variants
+struct Variant<'a, 'de> {
+    de: &'a mut Deserializer<'de>,
+    nested: bool,
+}
+
+impl<'a, 'de> Variant<'a, 'de> {
+    fn expect_fields(&self) -> PackResult<()> {
+        if self.nested {
+            Ok(())
+        } else {
+            Err(de::Error::invalid_type(
+                de::Unexpected::UnitVariant,
+                &"a variant with fields",
+            ))
+        }
+    }
+}
+
+impl<'a, 'de> de::EnumAccess<'de> for Variant<'a, 'de> {
+    type Error = PackError;
+    type Variant = Self;
+
+    fn variant_seed<V: de::DeserializeSeed<'de>>(
+        self,
+        seed: V,
+    ) -> PackResult<(V::Value, Self::Variant)> {
+        let variant_index: u32 = self.de.unpack()?;
+        let deserializer: de::value::U32Deserializer<PackError> = variant_index.into_deserializer();
+        let value = seed.deserialize(deserializer)?;
+        Ok((value, self))
+    }
+}
+
+impl<'a, 'de> de::VariantAccess<'de> for Variant<'a, 'de> {
+    type Error = PackError;
+
+    fn unit_variant(self) -> PackResult<()> {
+        Ok(())
+    }
+
+    fn newtype_variant_seed<T: de::DeserializeSeed<'de>>(self, seed: T) -> PackResult<T::Value> {
+        self.expect_fields()?;
+        seed.deserialize(self.de)
+    }
+
+    fn tuple_variant<V: de::Visitor<'de>>(self, _len: usize, visitor: V) -> PackResult<V::Value> {
+        self.expect_fields()?;
+        visitor.visit_seq(Access::new(self.de))
+    }
+
+    fn struct_variant<V: de::Visitor<'de>>(
+        self,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> PackResult<V::Value> {
+        self.expect_fields()?;
+        visitor.visit_seq(Access::new(self.de))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ::serde::de::DeserializeOwned;
+    use ::serde::{Deserialize, Serialize};
+    use std::collections::BTreeMap;
+    use std::fmt::Debug;
+
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+    struct Unit;
+
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+    struct Newtype(i64);
+
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+    struct Record {
+        name: String,
+        id: u32,
+        tags: Vec<String>,
+        parent: Option<i64>,
+    }
+
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+    enum Event {
+        Created,
+        Renamed(String),
+        Moved(i64, i64),
+        Deleted { by: String, soft: bool },
+    }
+
+    /// Checks that serde and the native `TuplePack` produce the same bytes
+    fn test_same<S, T>(serde_value: S, native: T)
+    where
+        S: Serialize + DeserializeOwned + Debug + PartialEq,
+        T: TuplePack,
+    {
+        let packed = to_bytes(&serde_value).unwrap();
+        assert_eq!(Bytes::from(packed.as_slice()), Bytes::from(pack(&native)));
+        assert_eq!(from_bytes::<S>(&packed).unwrap(), serde_value);
+    }
+
+    #[test]
+    fn test_primitives() {
+        test_same(true, true);
+        test_same(-5i8, -5i64);
+        test_same(255u8, 255u64);
+        test_same(-10000i16, -10000i16);
+        test_same(i64::MIN, i64::MIN);
+        test_same(u64::MAX, u64::MAX);
+        test_same(u128::MAX, u128::MAX);
+        test_same(-42f32, -42f32);
+        test_same(1.5f64, 1.5f64);
+        test_same('é', "é");
+        test_same("FÔO\x00bar".to_string(), "FÔO\x00bar");
+        test_same(Option::<i64>::None, Option::<i64>::None);
+        test_same(Some(7i64), 7i64);
+        test_same((), ());
+        test_same(Unit, ());
+        test_same(Newtype(-3), -3i64);
+    }
+
+    #[test]
+    fn test_compounds() {
+        test_same((1i64, "two".to_string()), (1i64, "two"));
+        test_same(vec![1i64, 2, 3], vec![1i64, 2, 3]);
+        test_same(
+            ((1i64, None::<i64>), vec![Some(2i64), None]),
+            ((1i64, None::<i64>), vec![Some(2i64), None]),
+        );
+
+        let record = Record {
+            name: "foo\x00bar".to_string(),
+            id: 42,
+            tags: vec!["a".to_string(), "b".to_string()],
+            parent: None,
+        };
+        test_same(record, ("foo\x00bar", 42, vec!["a", "b"], None::<i64>));
+
+        let mut map = BTreeMap::new();
+        map.insert("a".to_string(), 1i64);
+        map.insert("b".to_string(), 2i64);
+        test_same(map, (("a", 1i64), ("b", 2i64)));
+    }
+
+    #[test]
+    fn test_enums() {
+        test_same(Event::Created, 0u32);
+        test_same(Event::Renamed("foo".to_string()), (1u32, "foo"));
+        test_same(Event::Moved(-1, 1), (2u32, -1i64, 1i64));
+        test_same(
+            Event::Deleted {
+                by: "me".to_string(),
+                soft: true,
+            },
+            (3u32, "me", true),
+        );
+        test_same(
+            vec![Event::Created, Event::Renamed("foo".to_string())],
+            (0u32, (1u32, "foo")),
+        );
+        test_same(
+            (Some(Event::Moved(1, 2)), Event::Created),
+            ((2u32, 1i64, 2i64), 0u32),
+        );
+    }
+
+    #[test]
+    fn test_serde_wrapper() {
+        let subspace = Subspace::from("prefix");
+        let event = Event::Moved(3, 4);
+
+        let packed = subspace.pack(&(Serde(&event), 5i64));
+        assert_eq!(packed, subspace.pack(&((2u32, 3i64, 4i64), 5i64)));
+
+        let (Serde(unpacked), n): (Serde<Event>, i64) = subspace.unpack(&packed).unwrap();
+        assert_eq!(unpacked, event);
+        assert_eq!(n, 5);
+    }
+
+    #[test]
+    fn test_errors() {
+        assert!(matches!(
+            from_bytes::<(i64, i64)>(&pack(&(1i64, 2i64, 3i64))),
+            Err(PackError::TrailingBytes)
+        ));
+        assert!(matches!(
+            from_bytes::<u8>(&pack(&256i64)),
+            Err(PackError::Message(_))
+        ));
+        assert!(matches!(
+            from_bytes::<Event>(&pack(&9u32)),
+            Err(PackError::Message(_))
+        ));
+        assert!(matches!(
+            from_bytes::<(Event,)>(&pack(&(1u32,))),
+            Err(PackError::Message(_))
+        ));
+    }
+
+    #[test]
+    fn test_ordering() {
+        let values = [
+            Event::Created,
+            Event::Renamed("a".to_string()),
+            Event::Renamed("b".to_string()),
+            Event::Moved(-1, 0),
+            Event::Moved(1, -10),
+            Event::Moved(1, 10),
+            Event::Deleted {
+                by: "a".to_string(),
+                soft: false,
+            },
+            Event::Deleted {
+                by: "a".to_string(),
+                soft: true,
+            },
+        ];
+        let packed: Vec<Vec<u8>> = values
+            .iter()
+            .map(|v| to_bytes(&(v, Some(v), 1i64)).unwrap())
+            .collect();
+
+        for a in packed.iter() {
+            for b in packed.iter() {
+                let element_a: Element = unpack(a).unwrap();
+                let element_b: Element = unpack(b).unwrap();
+                assert_eq!(a.cmp(b), element_a.cmp(&element_b));
+            }
+        }
+    }
+}
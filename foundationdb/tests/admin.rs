@@ -0,0 +1,34 @@
+// Copyright 2018 foundationdb-rs developers, https://github.com/Clikengo/foundationdb-rs/graphs/contributors
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+#![cfg(any(feature = "fdb-7_0", feature = "fdb-7_1"))]
+
+use foundationdb::FdbResult;
+
+mod common;
+
+#[test]
+fn test_admin() {
+    let _guard = unsafe { foundationdb::boot() };
+    futures::executor::block_on(test_admin_async()).expect("failed to run");
+}
+
+async fn test_admin_async() -> FdbResult<()> {
+    let db = common::database().await?;
+
+    let protocol = db.get_server_protocol(None).await?;
+    assert!(protocol.is_compatible(protocol));
+    assert!(!protocol.to_string().is_empty());
+
+    let busyness = db.get_main_thread_busyness();
+    assert!((0.0..=1.0).contains(&busyness));
+
+    // an unknown worker is not rebooted
+    assert!(!db.reboot_worker("127.0.0.1:1", true, 0).await?);
+
+    Ok(())
+}
@@ -5,6 +5,8 @@
 // http://opensource.org/licenses/MIT>, at your option. This file may not be
 // copied, modified, or distributed except according to those terms.
 use byteorder::ByteOrder;
+use common::TestDatabase;
+use foundationdb::memory::MemoryDatabase;
 use foundationdb::*;
 use futures::future::*;
 
@@ -13,10 +15,17 @@ mod common;
 #[test]
 fn test_atomic() {
     let _guard = unsafe { foundationdb::boot() };
-    futures::executor::block_on(test_atomic_async()).expect("failed to run");
+    let db = futures::executor::block_on(common::database()).expect("cannot open fdb");
+    futures::executor::block_on(test_atomic_async(&db)).expect("failed to run");
 }
 
-async fn atomic_add(db: &Database, key: &[u8], value: i64) -> FdbResult<()> {
+#[test]
+fn test_atomic_memory() {
+    let db = MemoryDatabase::new();
+    futures::executor::block_on(test_atomic_async(&db)).expect("failed to run");
+}
+
+async fn atomic_add<D: TestDatabase>(db: &D, key: &[u8], value: i64) -> FdbResult<()> {
     let trx = db.create_trx()?;
 
     let val = {
@@ -26,28 +35,26 @@ async fn atomic_add(db: &Database, key: &[u8], value: i64) -> FdbResult<()> {
     };
     trx.atomic_op(key, &val, options::MutationType::Add);
 
-    trx.commit().await?;
+    D::commit(trx).await?;
     Ok(())
 }
 
-async fn test_atomic_async() -> FdbResult<()> {
+async fn test_atomic_async<D: TestDatabase>(db: &D) -> FdbResult<()> {
     const KEY: &[u8] = b"test-atomic";
 
-    let db = common::database().await?;
-
     println!("clear!");
     {
         let trx = db.create_trx()?;
         trx.clear(KEY);
-        trx.commit().await?;
+        D::commit(trx).await?;
     }
 
     println!("concurrent!");
     {
         let n = 1000usize;
 
-        let fut_add = try_join_all((0..n).map(|_| atomic_add(&db, KEY, 1)));
-        let fut_sub = try_join_all((0..n).map(|_| atomic_add(&db, KEY, -1)));
+        let fut_add = try_join_all((0..n).map(|_| atomic_add(db, KEY, 1)));
+        let fut_sub = try_join_all((0..n).map(|_| atomic_add(db, KEY, -1)));
 
         // Wait for all atomic operations
         try_join(fut_add, fut_sub).await?;
@@ -0,0 +1,49 @@
+// Copyright 2018 foundationdb-rs developers, https://github.com/Clikengo/foundationdb-rs/graphs/contributors
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+#![cfg(feature = "fdb-7_1")]
+
+use foundationdb::blob_granules::LocalGranuleLoader;
+use foundationdb::{FdbBindingError, FdbError};
+
+mod common;
+
+#[test]
+fn test_blob_granules() {
+    let _guard = unsafe { foundationdb::boot() };
+    futures::executor::block_on(test_blob_granules_async()).expect("failed to run");
+}
+
+async fn test_blob_granules_async() -> Result<(), FdbBindingError> {
+    let db = common::database().await?;
+
+    let trx = db.create_trx()?;
+    trx.set(b"test-blob-granules", b"value");
+    trx.commit().await.map_err(FdbError::from)?;
+
+    // the range has no granules unless blobbified, which the tests do not configure
+    let trx = db.create_trx()?;
+    let ranges = trx
+        .get_blob_granule_ranges(b"test-blob-granules", b"test-blob-granules\xff")
+        .await?;
+    for range in &ranges {
+        assert!(range.begin_key() < range.end_key());
+    }
+
+    if !ranges.is_empty() {
+        let loader = LocalGranuleLoader::new(std::env::temp_dir());
+        let _ = trx.read_blob_granules(
+            b"test-blob-granules",
+            b"test-blob-granules\xff",
+            0,
+            None,
+            &loader,
+        );
+    }
+
+    Ok(())
+}
@@ -0,0 +1,116 @@
+// Copyright 2018 foundationdb-rs developers, https://github.com/Clikengo/foundationdb-rs/graphs/contributors
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+use foundationdb::blocking::Database;
+use foundationdb::options::MutationType;
+use foundationdb::{FdbBindingError, FdbError, FdbErrorKind, KeySelector, RangeOption};
+
+mod common;
+
+#[test]
+fn test_blocking() {
+    let _guard = unsafe { foundationdb::boot() };
+    let db = futures::executor::block_on(common::database())
+        .map(Database::from)
+        .expect("failed to open the database");
+    test_get(&db).expect("failed to run");
+    test_get_ranges(&db).expect("failed to run");
+    test_run(&db).expect("failed to run");
+}
+
+fn test_get(db: &Database) -> Result<(), FdbError> {
+    let trx = db.create_trx()?;
+    trx.set(b"test-blocking-get", b"world");
+    trx.commit()?;
+
+    let trx = db.create_trx()?;
+    assert!(trx.get_read_version()? > 0);
+    assert_eq!(
+        trx.get(b"test-blocking-get", false)?.as_deref(),
+        Some(&b"world"[..])
+    );
+    let key = trx.get_key(
+        &KeySelector::first_greater_or_equal(&b"test-blocking-ge"[..]),
+        false,
+    )?;
+    assert_eq!(&*key, b"test-blocking-get");
+
+    trx.clear(b"test-blocking-get");
+    assert!(trx.get(b"test-blocking-get", false)?.is_none());
+    trx.commit()?;
+
+    Ok(())
+}
+
+fn test_get_ranges(db: &Database) -> Result<(), FdbError> {
+    const N: usize = 1000;
+
+    let trx = db.create_trx()?;
+    trx.clear_range(b"test-blocking-range-", b"test-blocking-range.");
+    for i in 0..N {
+        let key = format!("test-blocking-range-{:04}", i);
+        trx.set(key.as_bytes(), common::random_str(100).as_bytes());
+    }
+    trx.commit()?;
+
+    let trx = db.create_trx()?;
+    let opt = RangeOption::from((&b"test-blocking-range-"[..], &b"test-blocking-range."[..]));
+
+    let mut batches = 0;
+    let mut count = 0;
+    for values in trx.get_ranges(opt.clone(), false) {
+        batches += 1;
+        count += values?.len();
+    }
+    assert_eq!(count, N);
+    assert!(batches > 1, "the range should be read in several batches");
+
+    let keys = trx
+        .get_ranges_keyvalues(opt, false)
+        .map(|kv| kv.map(|kv| kv.key().to_vec()))
+        .collect::<Result<Vec<_>, _>>()?;
+    assert_eq!(keys.len(), N);
+    for (i, key) in keys.iter().enumerate() {
+        assert_eq!(key, format!("test-blocking-range-{:04}", i).as_bytes());
+    }
+
+    Ok(())
+}
+
+fn test_run(db: &Database) -> Result<(), FdbBindingError> {
+    const KEY: &[u8] = b"test-blocking-run";
+
+    db.run(|trx, _maybe_committed| {
+        trx.clear(KEY);
+        Ok(())
+    })?;
+
+    let mut attempts = 0;
+    db.run(|trx, _maybe_committed| {
+        attempts += 1;
+        trx.atomic_op(KEY, &1i64.to_le_bytes(), MutationType::Add);
+        if attempts == 1 {
+            // not_committed is retryable, the closure is called again
+            return Err(FdbError::from(FdbErrorKind::NotCommitted).into());
+        }
+        Ok(())
+    })?;
+    assert_eq!(attempts, 2);
+
+    let value = db.run(|trx, _maybe_committed| Ok(trx.get(KEY, false)?))?;
+    assert_eq!(value.as_deref(), Some(&1i64.to_le_bytes()[..]));
+
+    // errors which are not from fdb are not retried
+    let result: Result<(), _> =
+        db.run(|_trx, _maybe_committed| Err(FdbBindingError::ReferenceToTransactionKept));
+    assert!(matches!(
+        result,
+        Err(FdbBindingError::ReferenceToTransactionKept)
+    ));
+
+    Ok(())
+}
@@ -0,0 +1,162 @@
+// Copyright 2018 foundationdb-rs developers, https://github.com/Clikengo/foundationdb-rs/graphs/contributors
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+#![cfg(any(
+    feature = "fdb-6_2",
+    feature = "fdb-6_3",
+    feature = "fdb-7_0",
+    feature = "fdb-7_1"
+))]
+
+use foundationdb::bulk_load::{BulkLoadOptions, BulkLoadProgress, Mutation};
+use foundationdb::options::MutationType;
+use foundationdb::tuple::Subspace;
+use foundationdb::{FdbBindingError, FdbError, RangeOption};
+use futures::prelude::*;
+use std::time::Duration;
+
+mod common;
+
+#[test]
+fn test_bulk_load() {
+    let _guard = unsafe { foundationdb::boot() };
+    futures::executor::block_on(test_bulk_load_async()).expect("failed to run");
+    futures::executor::block_on(test_bulk_load_resume_async()).expect("failed to run");
+    futures::executor::block_on(test_bulk_load_too_large_async()).expect("failed to run");
+}
+
+fn mutations(subspace: &Subspace, n: u32) -> impl Stream<Item = Mutation> + '_ {
+    stream::iter(0..n).map(move |i| Mutation::Set(subspace.pack(&i), vec![0xaa; 100]))
+}
+
+async fn test_bulk_load_async() -> Result<(), FdbBindingError> {
+    const N: u32 = 1000;
+    let db = common::database().await?;
+    let subspace = Subspace::from_bytes(b"test-bulk-load");
+
+    let trx = db.create_trx()?;
+    trx.clear_subspace_range(&subspace);
+    trx.commit().await.map_err(FdbError::from)?;
+
+    let options = BulkLoadOptions {
+        target_bytes: 10_000,
+        size_check_interval: 10,
+        ..BulkLoadOptions::default()
+    };
+    let mut reported = Vec::new();
+    let counter = stream::once(future::ready(Mutation::AtomicOp(
+        subspace.pack(&"counter"),
+        1i64.to_le_bytes().to_vec(),
+        MutationType::Add,
+    )));
+    let progress = db
+        .bulk_load(
+            mutations(&subspace, N).chain(counter),
+            options,
+            |progress| reported.push(progress.clone()),
+        )
+        .await?;
+
+    assert_eq!(progress.mutations, u64::from(N) + 1);
+    assert!(progress.batches > 1);
+    assert_eq!(reported.len() as u64, progress.batches);
+    assert_eq!(reported.last(), Some(&progress));
+    assert!(reported.windows(2).all(
+        |w| w[0].mutations < w[1].mutations && w[0].committed_version < w[1].committed_version
+    ));
+
+    let trx = db.create_trx()?;
+    let count = trx
+        .get_ranges_keyvalues(RangeOption::from(&subspace), false)
+        .try_fold(0u32, |count, _| future::ok(count + 1))
+        .await?;
+    assert_eq!(count, N + 1);
+
+    Ok(())
+}
+
+async fn test_bulk_load_resume_async() -> Result<(), FdbBindingError> {
+    const N: u32 = 100;
+    let db = common::database().await?;
+    let subspace = Subspace::from_bytes(b"test-bulk-load-resume");
+
+    let trx = db.create_trx()?;
+    trx.clear_subspace_range(&subspace);
+    trx.commit().await.map_err(FdbError::from)?;
+
+    // a previous load committed the first half of the stream
+    let options = BulkLoadOptions {
+        resume_from: Some(BulkLoadProgress {
+            batches: 1,
+            mutations: u64::from(N / 2),
+            bytes: 0,
+            committed_version: 0,
+        }),
+        ..BulkLoadOptions::default()
+    };
+    let progress = db
+        .bulk_load(mutations(&subspace, N), options, |_| {})
+        .await?;
+    assert_eq!(progress.batches, 2);
+    assert_eq!(progress.mutations, u64::from(N));
+
+    let trx = db.create_trx()?;
+    assert!(trx
+        .get(&subspace.pack(&(N / 2 - 1)), false)
+        .await?
+        .is_none());
+    assert!(trx.get(&subspace.pack(&(N / 2)), false).await?.is_some());
+    assert!(trx.get(&subspace.pack(&(N - 1)), false).await?.is_some());
+
+    Ok(())
+}
+
+async fn test_bulk_load_too_large_async() -> Result<(), FdbBindingError> {
+    // 15MB of values, over the 10MB limit of a transaction
+    const N: u32 = 150;
+    let db = common::database().await?;
+    let subspace = Subspace::from_bytes(b"test-bulk-load-too-large");
+
+    let trx = db.create_trx()?;
+    trx.clear_subspace_range(&subspace);
+    trx.commit().await.map_err(FdbError::from)?;
+
+    // the size is never checked, the whole stream is applied to a single transaction
+    let options = BulkLoadOptions {
+        target_bytes: i64::MAX,
+        size_check_interval: usize::MAX,
+        max_batch_duration: Duration::from_secs(3600),
+        ..BulkLoadOptions::default()
+    };
+    let mutations =
+        stream::iter(0..N).map(|i| Mutation::Set(subspace.pack(&i), vec![0xaa; 100_000]));
+    let mut reported = Vec::new();
+    let progress = db
+        .bulk_load(mutations, options, |progress| {
+            reported.push(progress.clone())
+        })
+        .await?;
+
+    // the batch is split until its parts fit in a transaction
+    assert!(progress.batches > 1);
+    assert_eq!(progress.mutations, u64::from(N));
+    assert_eq!(reported.len() as u64, progress.batches);
+    assert!(reported.windows(2).all(|w| w[0].mutations < w[1].mutations));
+
+    let trx = db.create_trx()?;
+    let count = trx
+        .get_ranges_keyvalues(RangeOption::from(&subspace), false)
+        .try_fold(0u32, |count, _| future::ok(count + 1))
+        .await?;
+    assert_eq!(count, N);
+
+    let trx = db.create_trx()?;
+    trx.clear_subspace_range(&subspace);
+    trx.commit().await.map_err(FdbError::from)?;
+
+    Ok(())
+}
@@ -1,4 +1,8 @@
 use foundationdb as fdb;
+use foundationdb::memory::{MemoryDatabase, MemoryTransaction, MemoryValues};
+use futures::future::BoxFuture;
+use futures::stream::BoxStream;
+use futures::{FutureExt, StreamExt, TryFutureExt};
 use rand::distributions::Alphanumeric;
 use rand::thread_rng;
 use rand::Rng;
@@ -19,3 +23,77 @@ pub fn random_str(len: usize) -> String {
 pub async fn database() -> fdb::FdbResult<fdb::Database> {
     fdb::Database::new_compat(None).await
 }
+
+/// A database the integration tests can run against: FoundationDB, or the in-memory backend of
+/// `foundationdb::memory` which needs no cluster.
+///
+/// The suites written against this trait have a `_memory` variant running in plain `cargo test`.
+#[allow(unused)]
+pub trait TestDatabase {
+    type Trx: fdb::WriteTransaction + 'static;
+
+    fn create_trx(&self) -> fdb::FdbResult<Self::Trx>;
+
+    fn commit(trx: Self::Trx) -> BoxFuture<'static, fdb::FdbResult<()>>;
+
+    /// The versionstamp of the transaction, which does not borrow it until it is committed
+    fn get_versionstamp(trx: &Self::Trx) -> BoxFuture<'static, fdb::FdbResult<Vec<u8>>>;
+
+    fn get_ranges<'a>(
+        trx: &'a Self::Trx,
+        opt: fdb::RangeOption<'a>,
+        snapshot: bool,
+    ) -> BoxStream<'a, fdb::FdbResult<<Self::Trx as fdb::ReadTransaction>::Values>>;
+}
+
+impl TestDatabase for fdb::Database {
+    type Trx = fdb::Transaction;
+
+    fn create_trx(&self) -> fdb::FdbResult<Self::Trx> {
+        fdb::Database::create_trx(self)
+    }
+
+    fn commit(trx: Self::Trx) -> BoxFuture<'static, fdb::FdbResult<()>> {
+        trx.commit()
+            .map(|result| result.map(drop).map_err(fdb::FdbError::from))
+            .boxed()
+    }
+
+    fn get_versionstamp(trx: &Self::Trx) -> BoxFuture<'static, fdb::FdbResult<Vec<u8>>> {
+        trx.get_versionstamp()
+            .map_ok(|versionstamp| versionstamp.to_vec())
+            .boxed()
+    }
+
+    fn get_ranges<'a>(
+        trx: &'a Self::Trx,
+        opt: fdb::RangeOption<'a>,
+        snapshot: bool,
+    ) -> BoxStream<'a, fdb::FdbResult<fdb::future::FdbValues>> {
+        trx.get_ranges(opt, snapshot).boxed()
+    }
+}
+
+impl TestDatabase for MemoryDatabase {
+    type Trx = MemoryTransaction;
+
+    fn create_trx(&self) -> fdb::FdbResult<Self::Trx> {
+        MemoryDatabase::create_trx(self)
+    }
+
+    fn commit(trx: Self::Trx) -> BoxFuture<'static, fdb::FdbResult<()>> {
+        trx.commit().boxed()
+    }
+
+    fn get_versionstamp(trx: &Self::Trx) -> BoxFuture<'static, fdb::FdbResult<Vec<u8>>> {
+        trx.get_versionstamp().boxed()
+    }
+
+    fn get_ranges<'a>(
+        trx: &'a Self::Trx,
+        opt: fdb::RangeOption<'a>,
+        snapshot: bool,
+    ) -> BoxStream<'a, fdb::FdbResult<MemoryValues>> {
+        trx.get_ranges(opt, snapshot).boxed()
+    }
+}
@@ -0,0 +1,76 @@
+// Copyright 2018 foundationdb-rs developers, https://github.com/Clikengo/foundationdb-rs/graphs/contributors
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+#![cfg(any(feature = "fdb-6_3", feature = "fdb-7_0", feature = "fdb-7_1"))]
+
+use foundationdb::options::TransactionOption;
+use foundationdb::*;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+mod common;
+
+#[test]
+fn test_conflicting_keys() {
+    let _guard = unsafe { foundationdb::boot() };
+    futures::executor::block_on(test_conflicting_keys_async()).expect("failed to run");
+    futures::executor::block_on(test_run_reporting_conflicts_async()).expect("failed to run");
+}
+
+async fn test_conflicting_keys_async() -> FdbResult<()> {
+    let db = common::database().await?;
+
+    let trx = db.create_trx()?;
+    trx.set_option(TransactionOption::ReportConflictingKeys)?;
+    trx.get(b"test-conflicting-keys", false).await?;
+    trx.set(b"test-conflicting-keys-other", b"");
+
+    // a concurrent write of the key read by `trx`
+    let other = db.create_trx()?;
+    other.set(b"test-conflicting-keys", b"");
+    other.commit().await?;
+
+    let err = trx.commit().await.expect_err("the commit should conflict");
+    assert_eq!(err.kind(), FdbErrorKind::NotCommitted);
+    let ranges = err.conflicting_keys().await?;
+    assert_eq!(ranges.len(), 1);
+    assert!(ranges[0].contains(&b"test-conflicting-keys".to_vec()));
+
+    Ok(())
+}
+
+async fn test_run_reporting_conflicts_async() -> Result<(), FdbBindingError> {
+    let db = common::database().await?;
+    let attempts = AtomicUsize::new(0);
+    let reported = Mutex::new(Vec::new());
+
+    db.run_reporting_conflicts(
+        |trx, _maybe_committed| {
+            let (db, attempts) = (&db, &attempts);
+            async move {
+                trx.get(b"test-run-reporting-conflicts", false).await?;
+                trx.set(b"test-run-reporting-conflicts-other", b"");
+                // the first attempt conflicts with a concurrent write
+                if attempts.fetch_add(1, Ordering::SeqCst) == 0 {
+                    let other = db.create_trx()?;
+                    other.set(b"test-run-reporting-conflicts", b"");
+                    other.commit().await.map_err(FdbError::from)?;
+                }
+                Ok(())
+            }
+        },
+        |ranges| reported.lock().unwrap().extend_from_slice(ranges),
+    )
+    .await?;
+
+    assert_eq!(attempts.load(Ordering::SeqCst), 2);
+    let reported = reported.into_inner().unwrap();
+    assert_eq!(reported.len(), 1);
+    assert!(reported[0].contains(&b"test-run-reporting-conflicts".to_vec()));
+
+    Ok(())
+}
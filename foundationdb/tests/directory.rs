@@ -5,10 +5,10 @@
 // http://opensource.org/licenses/MIT>, at your option. This file may not be
 // copied, modified, or distributed except according to those terms.
 
-use foundationdb::directory::DirectoryLayer;
-
+use common::TestDatabase;
 use foundationdb::directory::Directory;
-
+use foundationdb::directory::DirectoryLayer;
+use foundationdb::memory::MemoryDatabase;
 use foundationdb::*;
 
 mod common;
@@ -18,32 +18,31 @@ mod common;
 fn test_directory() {
     let _guard = unsafe { foundationdb::boot() };
     let db = futures::executor::block_on(common::database()).expect("cannot open fdb");
+    futures::executor::block_on(test_directory_async(&db)).expect("failed to run");
+}
 
+#[test]
+fn test_directory_memory() {
+    let db = MemoryDatabase::new();
+    futures::executor::block_on(test_directory_async(&db)).expect("failed to run");
+}
+
+async fn test_directory_async<D: TestDatabase>(db: &D) -> FdbResult<()> {
     eprintln!("clearing all keys");
-    let trx = db.create_trx().expect("cannot create txn");
+    let trx = db.create_trx()?;
     trx.clear_range(b"", b"\xff");
-    futures::executor::block_on(trx.commit()).expect("could not clear keys");
+    D::commit(trx).await?;
 
     eprintln!("creating directories");
     let directory = DirectoryLayer::default();
 
-    futures::executor::block_on(test_create_then_open_then_delete(
-        &db,
-        &directory,
-        vec![String::from("application")],
-    ))
-    .expect("failed to run");
-
-    futures::executor::block_on(test_create_then_open_then_delete(
-        &db,
-        &directory,
-        vec![String::from("1"), String::from("2")],
-    ))
-    .expect("failed to run");
+    test_create_then_open_then_delete(db, &directory, vec![String::from("application")]).await?;
+    test_create_then_open_then_delete(db, &directory, vec![String::from("1"), String::from("2")])
+        .await
 }
 
-async fn test_create_then_open_then_delete(
-    db: &Database,
+async fn test_create_then_open_then_delete<D: TestDatabase>(
+    db: &D,
     directory: &DirectoryLayer,
     path: Vec<String>,
 ) -> FdbResult<()> {
@@ -56,7 +55,7 @@ async fn test_create_then_open_then_delete(
         "cannot create: {:?}",
         create_output.err().unwrap()
     );
-    trx.commit().await.expect("cannot commit");
+    D::commit(trx).await.expect("cannot commit");
     let trx = db.create_trx()?;
 
     eprintln!("opening {:?}", &path);
@@ -71,7 +70,7 @@ async fn test_create_then_open_then_delete(
         create_output.unwrap().bytes().unwrap(),
         open_output.unwrap().bytes().unwrap()
     );
-    trx.commit().await.expect("cannot commit");
+    D::commit(trx).await.expect("cannot commit");
 
     // removing folder
     Ok(())
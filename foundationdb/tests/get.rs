@@ -5,6 +5,8 @@
 // http://opensource.org/licenses/MIT>, at your option. This file may not be
 // copied, modified, or distributed except according to those terms.
 
+use common::TestDatabase;
+use foundationdb::memory::MemoryDatabase;
 use foundationdb::*;
 use foundationdb_macros::cfg_api_versions;
 use futures::future::*;
@@ -16,15 +18,17 @@ mod common;
 #[test]
 fn test_get() {
     let _guard = unsafe { foundationdb::boot() };
-    futures::executor::block_on(test_set_get_async()).expect("failed to run");
-    futures::executor::block_on(test_get_multi_async()).expect("failed to run");
+    let db = futures::executor::block_on(common::database()).expect("cannot open fdb");
+    futures::executor::block_on(test_set_get_async(&db)).expect("failed to run");
+    futures::executor::block_on(test_get_multi_async(&db)).expect("failed to run");
     futures::executor::block_on(test_set_conflict_async()).expect("failed to run");
-    futures::executor::block_on(test_set_conflict_snapshot_async()).expect("failed to run");
+    futures::executor::block_on(test_conflict_kind_async(&db)).expect("failed to run");
+    futures::executor::block_on(test_set_conflict_snapshot_async(&db)).expect("failed to run");
     futures::executor::block_on(test_transact_async()).expect("failed to run");
     futures::executor::block_on(test_transact_limit()).expect("failed to run");
     futures::executor::block_on(test_transact_timeout()).expect("failed to run");
-    futures::executor::block_on(test_versionstamp_async()).expect("failed to run");
-    futures::executor::block_on(test_read_version_async()).expect("failed to run");
+    futures::executor::block_on(test_versionstamp_async(&db)).expect("failed to run");
+    futures::executor::block_on(test_read_version_async(&db)).expect("failed to run");
     futures::executor::block_on(test_set_read_version_async()).expect("failed to run");
     futures::executor::block_on(test_get_addresses_for_key_async()).expect("failed to run");
     #[cfg(any(
@@ -37,19 +41,28 @@ fn test_get() {
     futures::executor::block_on(test_metadata_version()).expect("failed to run");
 }
 
-async fn test_set_get_async() -> FdbResult<()> {
-    let db = common::database().await?;
+#[test]
+fn test_get_memory() {
+    let db = MemoryDatabase::new();
+    futures::executor::block_on(test_set_get_async(&db)).expect("failed to run");
+    futures::executor::block_on(test_get_multi_async(&db)).expect("failed to run");
+    futures::executor::block_on(test_conflict_kind_async(&db)).expect("failed to run");
+    futures::executor::block_on(test_set_conflict_snapshot_async(&db)).expect("failed to run");
+    futures::executor::block_on(test_versionstamp_async(&db)).expect("failed to run");
+    futures::executor::block_on(test_read_version_async(&db)).expect("failed to run");
+}
 
+async fn test_set_get_async<D: TestDatabase>(db: &D) -> FdbResult<()> {
     let trx = db.create_trx()?;
     trx.set(b"hello", b"world");
-    trx.commit().await?;
+    D::commit(trx).await?;
 
     let trx = db.create_trx()?;
     let value = trx.get(b"hello", false).await?.unwrap();
     assert_eq!(value.deref(), b"world");
 
     trx.clear(b"hello");
-    trx.commit().await?;
+    D::commit(trx).await?;
 
     let trx = db.create_trx()?;
     assert!(trx.get(b"hello", false).await?.is_none());
@@ -57,9 +70,7 @@ async fn test_set_get_async() -> FdbResult<()> {
     Ok(())
 }
 
-async fn test_get_multi_async() -> FdbResult<()> {
-    let db = common::database().await?;
-
+async fn test_get_multi_async<D: TestDatabase>(db: &D) -> FdbResult<()> {
     let trx = db.create_trx()?;
     let keys: &[&[u8]] = &[b"hello", b"world", b"foo", b"bar"];
     let _results = try_join_all(keys.iter().map(|k| trx.get(k, false))).await?;
@@ -102,9 +113,26 @@ async fn test_set_conflict_async() -> FdbResult<()> {
     Ok(())
 }
 
-async fn test_set_conflict_snapshot_async() -> FdbResult<()> {
+// the conflict is reported as a retryable `not_committed` error, whatever the backend
+async fn test_conflict_kind_async<D: TestDatabase>(db: &D) -> FdbResult<()> {
+    let key = b"test_conflict_kind";
+
+    let trx1 = db.create_trx()?;
+    let trx2 = db.create_trx()?;
+    let _ = trx2.get(key, false).await?;
+
+    trx1.set(key, common::random_str(10).as_bytes());
+    D::commit(trx1).await?;
+
+    trx2.set(key, common::random_str(10).as_bytes());
+    let err = D::commit(trx2).await.unwrap_err();
+    assert_eq!(err.kind(), FdbErrorKind::NotCommitted);
+
+    Ok(())
+}
+
+async fn test_set_conflict_snapshot_async<D: TestDatabase>(db: &D) -> FdbResult<()> {
     let key = b"test_set_conflict_snapshot";
-    let db = common::database().await?;
 
     let trx1 = db.create_trx()?;
     let trx2 = db.create_trx()?;
@@ -115,12 +143,12 @@ async fn test_set_conflict_snapshot_async() -> FdbResult<()> {
 
     // commit first transaction
     trx1.set(key, common::random_str(10).as_bytes());
-    trx1.commit().await?;
+    D::commit(trx1).await?;
 
     // commit seconds transaction, which will *not* cause conflict because of
     // snapshot read
     trx2.set(key, common::random_str(10).as_bytes());
-    trx2.commit().await?;
+    D::commit(trx2).await?;
 
     Ok(())
 }
@@ -258,22 +286,19 @@ async fn test_transact_timeout() -> FdbResult<()> {
     Ok(())
 }
 
-async fn test_versionstamp_async() -> FdbResult<()> {
+async fn test_versionstamp_async<D: TestDatabase>(db: &D) -> FdbResult<()> {
     const KEY: &[u8] = b"test_versionstamp";
-    let db = common::database().await?;
 
     let trx = db.create_trx()?;
     trx.set(KEY, common::random_str(10).as_bytes());
-    let f_version = trx.get_versionstamp();
-    trx.commit().await?;
+    let f_version = D::get_versionstamp(&trx);
+    D::commit(trx).await?;
     f_version.await?;
 
     Ok(())
 }
 
-async fn test_read_version_async() -> FdbResult<()> {
-    let db = common::database().await?;
-
+async fn test_read_version_async<D: TestDatabase>(db: &D) -> FdbResult<()> {
     let trx = db.create_trx()?;
     trx.get_read_version().await?;
 
@@ -0,0 +1,85 @@
+// Copyright 2018 foundationdb-rs developers, https://github.com/Clikengo/foundationdb-rs/graphs/contributors
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+use foundationdb::idempotency::Idempotency;
+use foundationdb::tuple::Subspace;
+use foundationdb::*;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::time::Duration;
+
+mod common;
+
+#[test]
+fn test_idempotency() {
+    let _guard = unsafe { foundationdb::boot() };
+    futures::executor::block_on(test_run_idempotent_async()).expect("failed to run");
+    futures::executor::block_on(test_commit_unknown_result_async()).expect("failed to run");
+}
+
+async fn count_ids(db: &Database, idempotency: &Idempotency) -> Result<usize, FdbBindingError> {
+    let trx = db.create_trx()?;
+    let ids = trx
+        .get_range(&RangeOption::from(idempotency.subspace()), 1, false)
+        .await?;
+    Ok(ids.len())
+}
+
+async fn test_run_idempotent_async() -> Result<(), FdbBindingError> {
+    const KEY: &[u8] = b"test-idempotency";
+
+    let db = common::database().await?;
+    let idempotency = Idempotency::new(Subspace::from_bytes(&b"test-idempotency-ids"[..]));
+    idempotency.expire(&db, Duration::from_secs(0)).await?;
+
+    let value = db
+        .run_idempotent(&idempotency, |trx, _maybe_committed| async move {
+            trx.set(KEY, b"hello");
+            Ok(42)
+        })
+        .await?;
+    assert_eq!(value, 42);
+
+    // the id of the run is written with the transaction
+    assert_eq!(count_ids(&db, &idempotency).await?, 1);
+    let trx = db.create_trx()?;
+    assert_eq!(trx.get(KEY, false).await?.as_deref(), Some(&b"hello"[..]));
+
+    // the ids are kept until they are expired
+    idempotency.expire(&db, Duration::from_secs(3600)).await?;
+    assert_eq!(count_ids(&db, &idempotency).await?, 1);
+    idempotency.expire(&db, Duration::from_secs(0)).await?;
+    assert_eq!(count_ids(&db, &idempotency).await?, 0);
+
+    Ok(())
+}
+
+async fn test_commit_unknown_result_async() -> Result<(), FdbBindingError> {
+    let db = common::database().await?;
+    let idempotency = Idempotency::new(Subspace::from_bytes(&b"test-idempotency-unknown"[..]));
+
+    // an attempt failing with an unknown result before committing is retried, as its id is not
+    // written
+    let attempts = AtomicU32::new(0);
+    let value = db
+        .run_idempotent(&idempotency, |_trx, maybe_committed| {
+            let attempts = &attempts;
+            async move {
+                if attempts.fetch_add(1, Ordering::SeqCst) == 0 {
+                    return Err(FdbError::from(FdbErrorKind::CommitUnknownResult).into());
+                }
+                assert!(maybe_committed);
+                Ok("retried")
+            }
+        })
+        .await?;
+    assert_eq!(value, "retried");
+    assert_eq!(attempts.load(Ordering::SeqCst), 2);
+
+    idempotency.expire(&db, Duration::from_secs(0)).await?;
+
+    Ok(())
+}
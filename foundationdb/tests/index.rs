@@ -0,0 +1,119 @@
+// Copyright 2018 foundationdb-rs developers, https://github.com/Clikengo/foundationdb-rs/graphs/contributors
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+use foundationdb::tuple::index::{Index, IndexedSubspace};
+use foundationdb::tuple::{pack, unpack, Subspace};
+use foundationdb::*;
+use foundationdb_macros::cfg_api_versions;
+
+mod common;
+
+#[test]
+fn test_index() {
+    let _guard = unsafe { foundationdb::boot() };
+    futures::executor::block_on(test_index_async()).expect("failed to run");
+    #[cfg(feature = "fdb-7_1")]
+    {
+        futures::executor::block_on(test_index_mapped_async()).expect("failed to run");
+    }
+}
+
+fn users(name: &str) -> IndexedSubspace {
+    let root = Subspace::all().subspace(&name);
+    let by_zipcode = Index::new(root.subspace(&"zipcode"), |value| {
+        unpack::<(String, String)>(value)
+            .ok()
+            .map(|(zipcode, _name)| zipcode)
+    });
+    IndexedSubspace::new(root.subspace(&"user")).with_index(by_zipcode)
+}
+
+async fn populate(db: &Database, users: &IndexedSubspace) -> Result<(), FdbBindingError> {
+    let trx = db.create_trx()?;
+    trx.clear_subspace_range(users.primary());
+    trx.clear_subspace_range(users.indexes()[0].subspace());
+    users.set(&trx, &"001", &pack(&("20500", "Barack"))).await?;
+    users
+        .set(&trx, &"002", &pack(&("20500", "Michelle")))
+        .await?;
+    users.set(&trx, &"003", &pack(&("20500", "Sasha"))).await?;
+    users
+        .set(&trx, &"101", &pack(&("SW1A 1AA", "Elizabeth")))
+        .await?;
+    trx.commit().await.map_err(FdbError::from)?;
+    Ok(())
+}
+
+async fn test_index_async() -> Result<(), FdbBindingError> {
+    let db = common::database().await?;
+    let users = users("test-index");
+    let index = &users.indexes()[0];
+    populate(&db, &users).await?;
+
+    let trx = db.create_trx()?;
+    let found = users
+        .query(&trx, index, index.range(&"20500"), false)
+        .await?;
+    let names: Vec<(String, String)> = found
+        .iter()
+        .map(|(_, value)| unpack(value))
+        .collect::<Result<_, _>>()?;
+    assert_eq!(
+        names,
+        vec![
+            ("20500".to_string(), "Barack".to_string()),
+            ("20500".to_string(), "Michelle".to_string()),
+            ("20500".to_string(), "Sasha".to_string()),
+        ]
+    );
+    assert_eq!(found[0].0, users.primary().pack(&("001",)));
+
+    // an update moves the entry, a clear removes it
+    let trx = db.create_trx()?;
+    users
+        .set(&trx, &"002", &pack(&("SW1A 1AA", "Michelle")))
+        .await?;
+    users.clear(&trx, &"003").await?;
+    trx.commit().await.map_err(FdbError::from)?;
+
+    let trx = db.create_trx()?;
+    let found = users
+        .query(&trx, index, index.range(&"20500"), false)
+        .await?;
+    assert_eq!(found.len(), 1);
+    let found = users
+        .query(&trx, index, index.range(&"SW1A 1AA"), false)
+        .await?;
+    assert_eq!(found.len(), 2);
+    let entries = trx
+        .get_range(&RangeOption::from(index.subspace()), 1, false)
+        .await?;
+    assert_eq!(entries.len(), 3);
+    assert!(users.get(&trx, &"003", false).await?.is_none());
+
+    Ok(())
+}
+
+#[cfg_api_versions(min = 710)]
+async fn test_index_mapped_async() -> Result<(), FdbBindingError> {
+    let db = common::database().await?;
+    let users = users("test-index-mapped");
+    let index = &users.indexes()[0];
+    populate(&db, &users).await?;
+
+    let trx = db.create_trx()?;
+    let mapped = users
+        .query_mapped(&trx, index, index.range(&"20500"), true)
+        .await?;
+    let found = users
+        .query(&trx, index, index.range(&"20500"), true)
+        .await?;
+    assert_eq!(mapped.len(), 3);
+    assert_eq!(mapped, found);
+
+    Ok(())
+}
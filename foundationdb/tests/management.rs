@@ -0,0 +1,53 @@
+// Copyright 2018 foundationdb-rs developers, https://github.com/Clikengo/foundationdb-rs/graphs/contributors
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+#![cfg(all(feature = "management", any(feature = "fdb-7_0", feature = "fdb-7_1")))]
+
+use foundationdb::{management, FdbBindingError};
+
+mod common;
+
+#[test]
+fn test_management() {
+    let _guard = unsafe { foundationdb::boot() };
+    futures::executor::block_on(test_management_async()).expect("failed to run");
+}
+
+async fn test_management_async() -> Result<(), FdbBindingError> {
+    // an address without any server, so its exclusion does not move data
+    const ADDRESS: &str = "127.0.0.2:4500";
+
+    let db = common::database().await?;
+
+    let trx = db.create_trx()?;
+    management::set_consistency_check_suspended(&trx, true)?;
+    management::commit(trx).await?;
+    let trx = db.create_trx()?;
+    assert!(management::is_consistency_check_suspended(&trx).await?);
+    management::set_consistency_check_suspended(&trx, false)?;
+    management::commit(trx).await?;
+
+    let trx = db.create_trx()?;
+    management::exclude_servers(&trx, &[ADDRESS], true)?;
+    management::commit(trx).await?;
+    let trx = db.create_trx()?;
+    assert!(management::get_excluded(&trx)
+        .await?
+        .contains(&ADDRESS.to_string()));
+
+    management::include_servers(&trx, &[ADDRESS])?;
+    management::commit(trx).await?;
+    let trx = db.create_trx()?;
+    assert!(!management::get_excluded(&trx)
+        .await?
+        .contains(&ADDRESS.to_string()));
+
+    let trx = db.create_trx()?;
+    assert!(!management::get_worker_interfaces(&trx).await?.is_empty());
+
+    Ok(())
+}
@@ -0,0 +1,106 @@
+// Copyright 2018 foundationdb-rs developers, https://github.com/Clikengo/foundationdb-rs/graphs/contributors
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+use foundationdb::api::is_network_running;
+use foundationdb::FdbResult;
+use std::panic;
+use std::process::{Command, Output};
+
+mod common;
+
+/// Set in the child processes running a single test of this binary
+const CHILD_ENV: &str = "FDB_NETWORK_TEST_CHILD";
+
+/// Runs the test `name` in a child process, as the network can only be started once per process
+fn run_in_child(name: &str) -> Output {
+    let output = Command::new(std::env::current_exe().expect("failed to find the test binary"))
+        .args(["--exact", name, "--nocapture", "--test-threads=1"])
+        .env(CHILD_ENV, "1")
+        .output()
+        .expect("failed to run the child process");
+    assert!(
+        output.status.success(),
+        "child process failed with {}: {}",
+        output.status,
+        String::from_utf8_lossy(&output.stderr)
+    );
+    output
+}
+
+fn is_child() -> bool {
+    std::env::var_os(CHILD_ENV).is_some()
+}
+
+#[test]
+fn test_run_with_network() {
+    if is_child() {
+        return;
+    }
+    foundationdb::run_with_network(|| futures::executor::block_on(test_run_with_network_async()))
+        .expect("failed to run");
+    assert!(!is_network_running());
+}
+
+async fn test_run_with_network_async() -> FdbResult<()> {
+    let db = common::database().await?;
+    let trx = db.create_trx()?;
+    trx.set(b"test-run-with-network", b"1");
+    trx.commit().await?;
+
+    let trx = db.create_trx()?;
+    assert_eq!(
+        trx.get(b"test-run-with-network", false).await?.as_deref(),
+        Some(&b"1"[..])
+    );
+    Ok(())
+}
+
+#[test]
+fn test_run_with_network_panic() {
+    if !is_child() {
+        run_in_child("test_run_with_network_panic");
+        return;
+    }
+
+    let result = panic::catch_unwind(|| {
+        foundationdb::run_with_network(|| {
+            futures::executor::block_on(test_run_with_network_async()).expect("failed to run");
+            assert!(is_network_running());
+            panic!("panic in run_with_network");
+        })
+    });
+    let payload = result.expect_err("the panic to be propagated");
+    assert_eq!(
+        payload.downcast_ref::<&str>(),
+        Some(&"panic in run_with_network")
+    );
+    // the guard stopped the network while unwinding
+    assert!(!is_network_running());
+}
+
+/// Called after the `atexit` hook of `start_network`, which is registered after it
+extern "C" fn report_network_stopped() {
+    if !is_network_running() {
+        eprintln!("network stopped at exit");
+    }
+}
+
+#[test]
+fn test_process_exit_with_network() {
+    if !is_child() {
+        let output = run_in_child("test_process_exit_with_network");
+        assert!(String::from_utf8_lossy(&output.stderr).contains("network stopped at exit"));
+        return;
+    }
+
+    unsafe { libc::atexit(report_network_stopped) };
+    let _network = foundationdb::start_network();
+    futures::executor::block_on(test_run_with_network_async()).expect("failed to run");
+    assert!(is_network_running());
+    // the guard is not dropped, the network is stopped by the `atexit` hook
+    std::process::exit(0);
+}
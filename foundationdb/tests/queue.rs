@@ -0,0 +1,138 @@
+// Copyright 2018 foundationdb-rs developers, https://github.com/Clikengo/foundationdb-rs/graphs/contributors
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+use foundationdb::tuple::{queue::Queue, Subspace};
+use foundationdb::FdbResult;
+use futures::prelude::*;
+use std::collections::HashSet;
+
+mod common;
+
+#[test]
+fn test_queue() {
+    let _guard = unsafe { foundationdb::boot() };
+    futures::executor::block_on(test_queue_push_pop_async()).expect("failed to run");
+    futures::executor::block_on(test_queue_concurrent_pop_async()).expect("failed to run");
+    futures::executor::block_on(test_queue_no_starvation_async()).expect("failed to run");
+}
+
+async fn test_queue_push_pop_async() -> FdbResult<()> {
+    const KEY: &[u8] = b"test-queue";
+
+    let db = common::database().await?;
+    let queue = Queue::new(Subspace::from_bytes(KEY));
+
+    let trx = db.create_trx()?;
+    queue.clear(&trx);
+    trx.commit().await?;
+
+    let trx = db.create_trx()?;
+    assert!(queue.is_empty(&trx).await?);
+    queue.push(&trx, b"a");
+    queue.push(&trx, b"b");
+    trx.commit().await?;
+
+    let trx = db.create_trx()?;
+    queue.push(&trx, b"c");
+    trx.commit().await?;
+
+    let trx = db.create_trx()?;
+    assert_eq!(queue.peek(&trx).await?, Some(b"a".to_vec()));
+    assert_eq!(
+        queue.peek_batch(&trx, 10).await?,
+        vec![b"a".to_vec(), b"b".to_vec(), b"c".to_vec()]
+    );
+    assert_eq!(
+        queue.pop_batch(&trx, 2).await?,
+        vec![b"a".to_vec(), b"b".to_vec()]
+    );
+    trx.commit().await?;
+
+    assert_eq!(queue.pop(&db, 10).await?, vec![b"c".to_vec()]);
+    assert_eq!(queue.pop(&db, 10).await?, Vec::<Vec<u8>>::new());
+
+    let trx = db.create_trx()?;
+    assert!(queue.is_empty(&trx).await?);
+    assert_eq!(queue.peek(&trx).await?, None);
+
+    Ok(())
+}
+
+async fn test_queue_concurrent_pop_async() -> FdbResult<()> {
+    const N: usize = 100;
+    const KEY: &[u8] = b"test-queue-concurrent";
+
+    let db = common::database().await?;
+    let queue = Queue::new(Subspace::from_bytes(KEY));
+
+    let trx = db.create_trx()?;
+    queue.clear(&trx);
+    for i in 0..N {
+        queue.push(&trx, &i.to_le_bytes());
+    }
+    trx.commit().await?;
+
+    let consumers = (0..10).map(|_| async {
+        let mut popped = Vec::new();
+        loop {
+            let values = queue.pop(&db, 3).await?;
+            if values.is_empty() {
+                return Ok::<_, foundationdb::FdbError>(popped);
+            }
+            popped.extend(values);
+        }
+    });
+    let popped: Vec<Vec<Vec<u8>>> = future::try_join_all(consumers).await?;
+
+    let all: Vec<Vec<u8>> = popped.into_iter().flatten().collect();
+    let distinct: HashSet<&Vec<u8>> = all.iter().collect();
+    assert_eq!(all.len(), N);
+    assert_eq!(distinct.len(), N);
+
+    let trx = db.create_trx()?;
+    assert!(queue.is_empty(&trx).await?);
+
+    Ok(())
+}
+
+async fn test_queue_no_starvation_async() -> FdbResult<()> {
+    const CONSUMERS: usize = 10;
+    const SHARE: usize = 10;
+    const KEY: &[u8] = b"test-queue-starvation";
+
+    let db = common::database().await?;
+    let queue = Queue::new(Subspace::from_bytes(KEY));
+
+    let trx = db.create_trx()?;
+    queue.clear(&trx);
+    for i in 0..CONSUMERS * SHARE {
+        queue.push(&trx, &i.to_le_bytes());
+    }
+    trx.commit().await?;
+
+    // each consumer pops one value at a time until it has its share: one consumer starving the
+    // others would take more than its share, and leave another waiting on an empty queue
+    let consumers = (0..CONSUMERS).map(|_| async {
+        let mut popped = Vec::new();
+        while popped.len() < SHARE {
+            let values = queue.pop(&db, 1).await?;
+            assert!(!values.is_empty(), "a consumer starved");
+            popped.extend(values);
+        }
+        Ok::<_, foundationdb::FdbError>(popped)
+    });
+    let popped: Vec<Vec<Vec<u8>>> = future::try_join_all(consumers).await?;
+
+    assert!(popped.iter().all(|values| values.len() == SHARE));
+    let distinct: HashSet<&Vec<u8>> = popped.iter().flatten().collect();
+    assert_eq!(distinct.len(), CONSUMERS * SHARE);
+
+    let trx = db.create_trx()?;
+    assert!(queue.is_empty(&trx).await?);
+
+    Ok(())
+}
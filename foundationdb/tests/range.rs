@@ -8,21 +8,26 @@
 #[cfg_api_versions(min = 710)]
 use crate::tuple::Subspace;
 
+use common::TestDatabase;
+use foundationdb::memory::MemoryDatabase;
 use foundationdb::*;
 use foundationdb_macros::cfg_api_versions;
 use futures::future;
 use futures::prelude::*;
 
 use std::borrow::Cow;
+use std::sync::atomic::{AtomicBool, Ordering};
 
 mod common;
 
 #[test]
 fn test_range() {
     let _guard = unsafe { foundationdb::boot() };
+    let db = futures::executor::block_on(common::database()).expect("cannot open fdb");
     futures::executor::block_on(test_get_range_async()).expect("failed to run");
-    futures::executor::block_on(test_range_option_async()).expect("failed to run");
-    futures::executor::block_on(test_get_ranges_async()).expect("failed to run");
+    futures::executor::block_on(test_range_option_async(&db)).expect("failed to run");
+    futures::executor::block_on(test_get_ranges_async(&db)).expect("failed to run");
+    futures::executor::block_on(test_scan_ranges_async()).expect("failed to run");
     #[cfg(any(feature = "fdb-6_3", feature = "fdb-7_0", feature = "fdb-7_1"))]
     {
         futures::executor::block_on(test_get_estimate_range()).expect("failed to run");
@@ -30,6 +35,7 @@ fn test_range() {
     #[cfg(any(feature = "fdb-7_0", feature = "fdb-7_1"))]
     {
         futures::executor::block_on(test_get_range_split_points()).expect("failed to run");
+        futures::executor::block_on(test_parallel_scan()).expect("failed to run");
     }
     #[cfg(any(feature = "fdb-7_1",))]
     {
@@ -38,6 +44,13 @@ fn test_range() {
     }
 }
 
+#[test]
+fn test_range_memory() {
+    let db = MemoryDatabase::new();
+    futures::executor::block_on(test_range_option_async(&db)).expect("failed to run");
+    futures::executor::block_on(test_get_ranges_async(&db)).expect("failed to run");
+}
+
 async fn test_get_range_async() -> FdbResult<()> {
     const N: usize = 10000;
 
@@ -92,11 +105,9 @@ async fn test_get_range_async() -> FdbResult<()> {
     Ok(())
 }
 
-async fn test_get_ranges_async() -> FdbResult<()> {
+async fn test_get_ranges_async<D: TestDatabase>(db: &D) -> FdbResult<()> {
     const N: usize = 10000;
 
-    let db = common::database().await?;
-
     {
         let trx = db.create_trx()?;
         let key_begin = "test-ranges-";
@@ -117,8 +128,7 @@ async fn test_get_ranges_async() -> FdbResult<()> {
         let end = KeySelector::first_greater_than(Cow::Borrowed(key_end.as_bytes()));
         let opt = RangeOption::from((begin, end));
 
-        let count = trx
-            .get_ranges(opt, false)
+        let count = D::get_ranges(&trx, opt, false)
             .try_fold(0usize, |count, kvs| future::ok(count + kvs.as_ref().len()))
             .await?;
 
@@ -129,9 +139,86 @@ async fn test_get_ranges_async() -> FdbResult<()> {
     Ok(())
 }
 
-async fn test_range_option_async() -> FdbResult<()> {
+async fn test_scan_ranges_async() -> FdbResult<()> {
+    const N: usize = 10000;
+
     let db = common::database().await?;
+    let key_begin = "test-scan-ranges-";
+    let key_end = "test-scan-ranges.";
+    let k = |i: usize| format!("{}-{:010}", key_begin, i);
+
+    eprintln!("clearing...");
+    let trx = db.create_trx()?;
+    trx.clear_range(key_begin.as_bytes(), key_end.as_bytes());
+
+    eprintln!("inserting...");
+    for i in 0..N {
+        let value = common::random_str(10);
+        trx.set(k(i).as_bytes(), value.as_bytes());
+    }
+    trx.commit().await?;
+
+    eprintln!("counting...");
+    let opt = RangeOption::from((key_begin.as_bytes(), key_end.as_bytes()));
+    let keys: Vec<Vec<u8>> = db
+        .scan_ranges_keyvalues(opt, false)
+        .map_ok(|kv| kv.key().to_vec())
+        .try_collect()
+        .await?;
+    let expected: Vec<Vec<u8>> = (0..N).map(|i| k(i).into_bytes()).collect();
+    assert_eq!(keys, expected);
+
+    // a key written after the scan started is not seen at the pinned read version
+    let opt = RangeOption::from((key_begin.as_bytes(), key_end.as_bytes()));
+    let mut batches = db.scan_ranges(opt, true).boxed();
+    let first = batches.try_next().await?.expect("a first batch");
+    assert!(first.more());
+    let mut count = first.len();
 
+    let trx = db.create_trx()?;
+    trx.set(k(N).as_bytes(), b"late");
+    trx.commit().await?;
+
+    while let Some(values) = batches.try_next().await? {
+        count += values.len();
+    }
+    assert_eq!(count, N);
+
+    // nor after the transaction of the scan is reset by a retryable error
+    let trx = db.create_trx()?;
+    trx.clear(k(N).as_bytes());
+    trx.commit().await?;
+
+    let injected = AtomicBool::new(false);
+    let late_written = AtomicBool::new(false);
+    let opt = RangeOption::from((key_begin.as_bytes(), key_end.as_bytes()));
+    let mut batches = db
+        .scan_ranges_with(opt, true, |trx, opt, iteration| {
+            if late_written.load(Ordering::SeqCst) && !injected.swap(true, Ordering::SeqCst) {
+                return future::err(FdbError::from(FdbErrorKind::NotCommitted)).boxed();
+            }
+            trx.get_range(opt, iteration, true).boxed()
+        })
+        .boxed();
+    let first = batches.try_next().await?.expect("a first batch");
+    assert!(first.more());
+    let mut count = first.len();
+
+    let trx = db.create_trx()?;
+    trx.set(k(N).as_bytes(), b"late");
+    trx.commit().await?;
+    late_written.store(true, Ordering::SeqCst);
+
+    while let Some(values) = batches.try_next().await? {
+        count += values.len();
+    }
+    assert!(injected.load(Ordering::SeqCst));
+    assert_eq!(count, N);
+
+    Ok(())
+}
+
+async fn test_range_option_async<D: TestDatabase>(db: &D) -> FdbResult<()> {
     {
         let trx = db.create_trx()?;
         let key_begin = "test-rangeoption-";
@@ -147,7 +234,8 @@ async fn test_range_option_async() -> FdbResult<()> {
             trx.set(k(i).as_bytes(), value.as_bytes());
         }
         assert_eq!(
-            trx.get_ranges(
+            D::get_ranges(
+                &trx,
                 (KeySelector::first_greater_or_equal(k(100).into_bytes())
                     ..KeySelector::first_greater_or_equal(k(5000).as_bytes()))
                     .into(),
@@ -158,7 +246,8 @@ async fn test_range_option_async() -> FdbResult<()> {
             4900
         );
         assert_eq!(
-            trx.get_ranges(
+            D::get_ranges(
+                &trx,
                 (
                     KeySelector::first_greater_or_equal(k(100).into_bytes()),
                     KeySelector::first_greater_or_equal(k(5000).as_bytes())
@@ -171,32 +260,41 @@ async fn test_range_option_async() -> FdbResult<()> {
             4900
         );
         assert_eq!(
-            trx.get_ranges((k(100).into_bytes()..k(5000).into_bytes()).into(), false)
-                .try_fold(0usize, |count, kvs| future::ok(count + kvs.as_ref().len()))
-                .await?,
+            D::get_ranges(
+                &trx,
+                (k(100).into_bytes()..k(5000).into_bytes()).into(),
+                false
+            )
+            .try_fold(0usize, |count, kvs| future::ok(count + kvs.as_ref().len()))
+            .await?,
             4900
         );
         assert_eq!(
-            trx.get_ranges((k(100).into_bytes(), k(5000).into_bytes()).into(), false)
-                .try_fold(0usize, |count, kvs| future::ok(count + kvs.as_ref().len()))
-                .await?,
+            D::get_ranges(
+                &trx,
+                (k(100).into_bytes(), k(5000).into_bytes()).into(),
+                false
+            )
+            .try_fold(0usize, |count, kvs| future::ok(count + kvs.as_ref().len()))
+            .await?,
             4900
         );
         assert_eq!(
-            trx.get_ranges((k(100).as_bytes()..k(5000).as_bytes()).into(), false)
+            D::get_ranges(&trx, (k(100).as_bytes()..k(5000).as_bytes()).into(), false)
                 .try_fold(0usize, |count, kvs| future::ok(count + kvs.as_ref().len()))
                 .await?,
             4900
         );
         assert_eq!(
-            trx.get_ranges((k(100).as_bytes(), k(5000).as_bytes()).into(), false)
+            D::get_ranges(&trx, (k(100).as_bytes(), k(5000).as_bytes()).into(), false)
                 .try_fold(0usize, |count, kvs| future::ok(count + kvs.as_ref().len()))
                 .await?,
             4900
         );
 
         assert_eq!(
-            trx.get_ranges(
+            D::get_ranges(
+                &trx,
                 (KeySelector::first_greater_or_equal(k(100).into_bytes())
                     ..KeySelector::first_greater_than(k(5000).as_bytes()))
                     .into(),
@@ -207,13 +305,17 @@ async fn test_range_option_async() -> FdbResult<()> {
             4901
         );
         assert_eq!(
-            trx.get_ranges((k(100).into_bytes()..=k(5000).into_bytes()).into(), false)
-                .try_fold(0usize, |count, kvs| future::ok(count + kvs.as_ref().len()))
-                .await?,
+            D::get_ranges(
+                &trx,
+                (k(100).into_bytes()..=k(5000).into_bytes()).into(),
+                false
+            )
+            .try_fold(0usize, |count, kvs| future::ok(count + kvs.as_ref().len()))
+            .await?,
             4901
         );
         assert_eq!(
-            trx.get_ranges((k(100).as_bytes()..=k(5000).as_bytes()).into(), false)
+            D::get_ranges(&trx, (k(100).as_bytes()..=k(5000).as_bytes()).into(), false)
                 .try_fold(0usize, |count, kvs| future::ok(count + kvs.as_ref().len()))
                 .await?,
             4901
@@ -284,6 +386,47 @@ async fn test_get_range_split_points() -> FdbResult<()> {
     Ok(())
 }
 
+#[cfg_api_versions(min = 700)]
+async fn test_parallel_scan() -> FdbResult<()> {
+    const N: u32 = 10000;
+
+    let db = common::database().await?;
+    let trx = db.create_trx()?;
+    let key_begin = "test-parallel-scan-";
+    let key_end = "test-parallel-scan.";
+    let k = |i: u32| format!("{}-{:010}", key_begin, i);
+
+    eprintln!("clearing...");
+    trx.clear_range(key_begin.as_bytes(), key_end.as_bytes());
+
+    eprintln!("inserting...");
+    for i in 0..N {
+        let value = common::random_str(10);
+        trx.set(k(i).as_bytes(), value.as_bytes());
+    }
+    trx.commit().await?;
+
+    let range = key_begin.as_bytes().to_vec()..key_end.as_bytes().to_vec();
+    let mut keys: Vec<Vec<u8>> = db
+        .parallel_scan(range, 1000, 4)
+        .map_ok(|values| {
+            stream::iter(
+                values
+                    .into_iter()
+                    .map(|kv| Ok::<_, FdbError>(kv.key().to_vec())),
+            )
+        })
+        .try_flatten()
+        .try_collect()
+        .await?;
+
+    keys.sort();
+    let expected: Vec<Vec<u8>> = (0..N).map(|i| k(i).into_bytes()).collect();
+    assert_eq!(keys, expected);
+
+    Ok(())
+}
+
 #[cfg_api_versions(min = 710)]
 async fn test_mapped_value() -> FdbResult<()> {
     use foundationdb::tuple::{pack, Subspace};
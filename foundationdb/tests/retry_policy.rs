@@ -0,0 +1,147 @@
+// Copyright 2018 foundationdb-rs developers, https://github.com/Clikengo/foundationdb-rs/graphs/contributors
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+use foundationdb::*;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+mod common;
+
+#[test]
+fn test_retry_policy() {
+    let _guard = unsafe { foundationdb::boot() };
+    futures::executor::block_on(test_max_attempts_async()).expect("failed to run");
+    futures::executor::block_on(test_timeout_async()).expect("failed to run");
+    futures::executor::block_on(test_custom_errors_async()).expect("failed to run");
+    futures::executor::block_on(test_maybe_committed_async()).expect("failed to run");
+}
+
+async fn test_max_attempts_async() -> FdbResult<()> {
+    let db = common::database().await?;
+
+    let attempts = AtomicU32::new(0);
+    let failures = Mutex::new(Vec::new());
+    let policy = RetryPolicy::new()
+        .with_max_attempts(3)
+        .with_backoff(Duration::from_millis(1), Duration::from_millis(10))
+        .on_attempt(|attempt| attempts.store(attempt, Ordering::SeqCst))
+        .on_failure(|failure| {
+            failures
+                .lock()
+                .unwrap()
+                .push((failure.attempt, failure.will_retry))
+        });
+
+    let result: Result<(), _> = db
+        .run_with_policy(&policy, |_trx, _maybe_committed| async {
+            Err(FdbError::from(FdbErrorKind::NotCommitted).into())
+        })
+        .await;
+    match result {
+        Err(FdbBindingError::NonRetryableFdbError(err)) => {
+            assert_eq!(err.kind(), FdbErrorKind::NotCommitted)
+        }
+        _ => panic!("the run should fail after 3 attempts"),
+    }
+    assert_eq!(attempts.load(Ordering::SeqCst), 3);
+    assert_eq!(
+        *failures.lock().unwrap(),
+        vec![(1, true), (2, true), (3, false)]
+    );
+
+    Ok(())
+}
+
+async fn test_timeout_async() -> FdbResult<()> {
+    let db = common::database().await?;
+
+    let attempts = AtomicU32::new(0);
+    let policy = RetryPolicy::new().with_timeout(Duration::from_millis(200));
+    let start = Instant::now();
+    let result: Result<(), _> = db
+        .run_with_policy(&policy, |_trx, _maybe_committed| async {
+            attempts.fetch_add(1, Ordering::SeqCst);
+            Err(FdbError::from(FdbErrorKind::NotCommitted).into())
+        })
+        .await;
+    assert!(result.is_err());
+    assert!(attempts.load(Ordering::SeqCst) > 1);
+    assert!(start.elapsed() < Duration::from_secs(5));
+
+    Ok(())
+}
+
+async fn test_custom_errors_async() -> Result<(), FdbBindingError> {
+    let db = common::database().await?;
+
+    let attempts = AtomicU32::new(0);
+    let policy = RetryPolicy::new().retry_custom_errors(|err| err.to_string() == "busy");
+    let value = db
+        .run_with_policy(&policy, |trx, _maybe_committed| {
+            let attempts = &attempts;
+            async move {
+                trx.set(b"test-retry-policy", b"1");
+                if attempts.fetch_add(1, Ordering::SeqCst) == 0 {
+                    return Err(FdbBindingError::CustomError("busy".into()));
+                }
+                Ok(trx.get(b"test-retry-policy", false).await?)
+            }
+        })
+        .await?;
+    assert_eq!(attempts.load(Ordering::SeqCst), 2);
+    assert_eq!(value.as_deref(), Some(&b"1"[..]));
+
+    // the other custom errors are not retried
+    let attempts = AtomicU32::new(0);
+    let result: Result<(), _> = db
+        .run_with_policy(&policy, |_trx, _maybe_committed| async {
+            attempts.fetch_add(1, Ordering::SeqCst);
+            Err(FdbBindingError::CustomError("invalid".into()))
+        })
+        .await;
+    assert!(matches!(result, Err(FdbBindingError::CustomError(_))));
+    assert_eq!(attempts.load(Ordering::SeqCst), 1);
+
+    Ok(())
+}
+
+async fn test_maybe_committed_async() -> FdbResult<()> {
+    let db = common::database().await?;
+
+    let attempts = AtomicU32::new(0);
+    let policy = RetryPolicy::new().with_maybe_committed(MaybeCommittedPolicy::Fail);
+    let result: Result<(), _> = db
+        .run_with_policy(&policy, |_trx, _maybe_committed| async {
+            attempts.fetch_add(1, Ordering::SeqCst);
+            Err(FdbError::from(FdbErrorKind::CommitUnknownResult).into())
+        })
+        .await;
+    assert!(result.is_err());
+    assert_eq!(attempts.load(Ordering::SeqCst), 1);
+
+    // retried by default, the closure being told about it
+    let attempts = AtomicU32::new(0);
+    db.run_with_policy(&RetryPolicy::new(), |_trx, maybe_committed| {
+        let attempts = &attempts;
+        async move {
+            if attempts.fetch_add(1, Ordering::SeqCst) == 0 {
+                return Err(FdbError::from(FdbErrorKind::CommitUnknownResult).into());
+            }
+            assert!(maybe_committed);
+            Ok(())
+        }
+    })
+    .await
+    .map_err(|err| match err {
+        FdbBindingError::NonRetryableFdbError(err) => err,
+        _ => panic!("unexpected error"),
+    })?;
+    assert_eq!(attempts.load(Ordering::SeqCst), 2);
+
+    Ok(())
+}
@@ -0,0 +1,92 @@
+// Copyright 2018 foundationdb-rs developers, https://github.com/Clikengo/foundationdb-rs/graphs/contributors
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+use foundationdb::options::MutationType;
+use foundationdb::*;
+use std::sync::atomic::{AtomicU32, Ordering};
+
+mod common;
+
+#[test]
+fn test_run_outcome() {
+    let _guard = unsafe { foundationdb::boot() };
+    futures::executor::block_on(test_run_outcome_async()).expect("failed to run");
+}
+
+async fn test_run_outcome_async() -> Result<(), FdbBindingError> {
+    const KEY: &[u8] = b"test-run-outcome";
+
+    let db = common::database().await?;
+
+    let outcome = db
+        .run_with_outcome(|trx, _maybe_committed| async move {
+            trx.set(KEY, b"hello");
+            Ok(42)
+        })
+        .await?;
+    assert_eq!(outcome.value, 42);
+    assert_eq!(outcome.attempts, 1);
+    let committed_version = outcome.committed_version.expect("the commit to be known");
+    assert!(committed_version > 0);
+    let versionstamp = outcome.versionstamp.expect("a versionstamp");
+    assert_eq!(
+        &versionstamp.transaction_version()[..8],
+        &committed_version.to_be_bytes()[..]
+    );
+
+    // the versionstamp is the one written by the versionstamped operations
+    let mut value = Vec::new();
+    value.extend_from_slice(&[0xff; 10]);
+    value.extend_from_slice(&0u32.to_le_bytes());
+    let outcome = db
+        .run_with_outcome(|trx, _maybe_committed| {
+            let value = &value;
+            async move {
+                trx.atomic_op(KEY, value, MutationType::SetVersionstampedValue);
+                Ok(())
+            }
+        })
+        .await?;
+    let trx = db.create_trx()?;
+    let stored = trx.get(KEY, false).await?.expect("the key to be set");
+    assert_eq!(
+        &stored[..],
+        outcome
+            .versionstamp
+            .expect("a versionstamp")
+            .transaction_version()
+    );
+
+    // read-only transactions have no versionstamp
+    let outcome = db
+        .run_with_outcome(|trx, _maybe_committed| async move {
+            Ok(trx.get(KEY, false).await?.is_some())
+        })
+        .await?;
+    assert!(outcome.value);
+    assert_eq!(outcome.committed_version, Some(-1));
+    assert!(outcome.versionstamp.is_none());
+
+    // the retries are counted
+    let attempts = AtomicU32::new(0);
+    let outcome = db
+        .run_with_outcome(|trx, _maybe_committed| {
+            let attempts = &attempts;
+            async move {
+                trx.clear(KEY);
+                if attempts.fetch_add(1, Ordering::SeqCst) == 0 {
+                    return Err(FdbError::from(FdbErrorKind::NotCommitted).into());
+                }
+                Ok(())
+            }
+        })
+        .await?;
+    assert_eq!(outcome.attempts, 2);
+    assert!(outcome.elapsed > std::time::Duration::from_secs(0));
+
+    Ok(())
+}
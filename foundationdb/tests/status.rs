@@ -0,0 +1,33 @@
+// Copyright 2018 foundationdb-rs developers, https://github.com/Clikengo/foundationdb-rs/graphs/contributors
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+#![cfg(feature = "status")]
+
+use foundationdb::FdbBindingError;
+
+mod common;
+
+#[test]
+fn test_status() {
+    let _guard = unsafe { foundationdb::boot() };
+    futures::executor::block_on(test_status_async()).expect("failed to run");
+}
+
+async fn test_status_async() -> Result<(), FdbBindingError> {
+    let db = common::database().await?;
+
+    let status = db.get_status().await?;
+    assert!(status.is_available());
+    assert!(!status.cluster.processes.is_empty());
+    assert!(status
+        .cluster
+        .processes
+        .values()
+        .any(|process| process.has_role("storage")));
+
+    Ok(())
+}
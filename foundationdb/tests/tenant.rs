@@ -0,0 +1,77 @@
+// Copyright 2018 foundationdb-rs developers, https://github.com/Clikengo/foundationdb-rs/graphs/contributors
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+#![cfg(feature = "fdb-7_1")]
+
+use foundationdb::{FdbBindingError, FdbError, FdbErrorKind};
+
+mod common;
+
+#[test]
+fn test_tenant() {
+    let _guard = unsafe { foundationdb::boot() };
+    futures::executor::block_on(test_tenant_async()).expect("failed to run");
+}
+
+async fn test_tenant_async() -> Result<(), FdbBindingError> {
+    const TENANT: &[u8] = b"test-tenant";
+
+    let db = common::database().await?;
+
+    let _ = db.delete_tenant(TENANT).await;
+    match db.create_tenant(TENANT).await {
+        Err(FdbBindingError::NonRetryableFdbError(err))
+            if err.kind() == FdbErrorKind::TenantsDisabled =>
+        {
+            eprintln!("tenants are disabled on this cluster, skipping");
+            return Ok(());
+        }
+        result => result?,
+    }
+
+    match db.create_tenant(TENANT).await {
+        Err(FdbBindingError::NonRetryableFdbError(err)) => {
+            assert_eq!(err.kind(), FdbErrorKind::TenantAlreadyExists)
+        }
+        _ => panic!("creating the tenant twice should fail"),
+    }
+
+    let tenants = db
+        .list_tenants(b"test-tenant", b"test-tenant\xff", 10)
+        .await?;
+    assert_eq!(tenants.len(), 1);
+    assert_eq!(tenants[0].0, TENANT);
+
+    let tenant = db.open_tenant(TENANT)?;
+    tenant
+        .run(|trx, _maybe_committed| async move {
+            trx.set(b"hello", b"world");
+            Ok(())
+        })
+        .await?;
+
+    let trx = tenant.create_trx()?;
+    assert_eq!(
+        trx.get(b"hello", false).await?.as_deref(),
+        Some(&b"world"[..])
+    );
+    // the key is in the key-space of the tenant only
+    let trx = db.create_trx()?;
+    assert!(trx.get(b"hello", false).await?.is_none());
+
+    let trx = tenant.create_trx()?;
+    trx.clear_range(b"", b"\xff");
+    trx.commit().await.map_err(FdbError::from)?;
+
+    db.delete_tenant(TENANT).await?;
+    let tenants = db
+        .list_tenants(b"test-tenant", b"test-tenant\xff", 10)
+        .await?;
+    assert!(tenants.is_empty());
+
+    Ok(())
+}
@@ -0,0 +1,135 @@
+// Copyright 2018 foundationdb-rs developers, https://github.com/Clikengo/foundationdb-rs/graphs/contributors
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+#![cfg(feature = "tracing")]
+
+use foundationdb::*;
+use std::fmt;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use tracing::field::{Field, Visit};
+use tracing::span::{Attributes, Id, Record};
+use tracing::{Event, Metadata, Subscriber};
+
+mod common;
+
+/// Collects the names of the spans and the messages of the events
+#[derive(Clone, Default)]
+struct Collector {
+    next_id: Arc<AtomicU64>,
+    spans: Arc<Mutex<Vec<String>>>,
+    events: Arc<Mutex<Vec<String>>>,
+    trace_ids: Arc<Mutex<Vec<String>>>,
+}
+
+struct Message<'a>(&'a mut Option<String>, &'static str);
+
+impl<'a> Visit for Message<'a> {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        if field.name() == self.1 {
+            *self.0 = Some(value.to_string());
+        }
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        if field.name() == self.1 {
+            *self.0 = Some(format!("{:?}", value));
+        }
+    }
+}
+
+impl Subscriber for Collector {
+    fn enabled(&self, _metadata: &Metadata<'_>) -> bool {
+        true
+    }
+
+    fn new_span(&self, span: &Attributes<'_>) -> Id {
+        self.spans
+            .lock()
+            .unwrap()
+            .push(span.metadata().name().to_string());
+        Id::from_u64(self.next_id.fetch_add(1, Ordering::SeqCst) + 1)
+    }
+
+    fn record(&self, _span: &Id, values: &Record<'_>) {
+        let mut trace_id = None;
+        values.record(&mut Message(&mut trace_id, "trace_id"));
+        if let Some(trace_id) = trace_id {
+            self.trace_ids.lock().unwrap().push(trace_id);
+        }
+    }
+
+    fn record_follows_from(&self, _span: &Id, _follows: &Id) {}
+
+    fn event(&self, event: &Event<'_>) {
+        let mut message = None;
+        event.record(&mut Message(&mut message, "message"));
+        if let Some(message) = message {
+            self.events.lock().unwrap().push(message);
+        }
+    }
+
+    fn enter(&self, _span: &Id) {}
+
+    fn exit(&self, _span: &Id) {}
+}
+
+#[test]
+fn test_tracing() {
+    let _guard = unsafe { foundationdb::boot() };
+    let collector = Collector::default();
+    tracing::subscriber::with_default(collector.clone(), || {
+        futures::executor::block_on(test_tracing_async()).expect("failed to run")
+    });
+
+    let spans = collector.spans.lock().unwrap();
+    assert!(spans.iter().any(|name| name == "run"));
+    assert_eq!(spans.iter().filter(|name| *name == "attempt").count(), 3);
+    let events = collector.events.lock().unwrap();
+    for operation in ["get", "get_range", "commit", "on_error"] {
+        assert!(
+            events.iter().any(|message| message == operation),
+            "no {} event in {:?}",
+            operation,
+            events
+        );
+    }
+    assert_eq!(*collector.trace_ids.lock().unwrap(), vec!["test-trace-id"]);
+}
+
+async fn test_tracing_async() -> Result<(), FdbBindingError> {
+    const KEY: &[u8] = b"test-tracing";
+
+    let db = common::database().await?;
+
+    // a retried attempt
+    let attempts = AtomicU64::new(0);
+    db.run(|trx, _maybe_committed| {
+        let attempts = &attempts;
+        async move {
+            trx.set(KEY, b"1");
+            trx.get(KEY, false).await?;
+            trx.get_range(&RangeOption::from((KEY, &b"test-tracinh"[..])), 1, true)
+                .await?;
+            if attempts.fetch_add(1, Ordering::SeqCst) == 0 {
+                return Err(FdbError::from(FdbErrorKind::NotCommitted).into());
+            }
+            Ok(())
+        }
+    })
+    .await?;
+
+    // the trace id is propagated to the transaction
+    let policy = RetryPolicy::new().propagate_trace_id(|| Some("test-trace-id".to_string()));
+    db.run_with_policy(&policy, |trx, _maybe_committed| async move {
+        trx.clear(KEY);
+        Ok(())
+    })
+    .await?;
+
+    Ok(())
+}
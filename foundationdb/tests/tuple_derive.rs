@@ -0,0 +1,200 @@
+// Copyright 2018 foundationdb-rs developers, https://github.com/Clikengo/foundationdb-rs/graphs/contributors
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+use foundationdb::tuple::{
+    pack, pack_with_versionstamp, unpack, Bytes, TuplePack, TupleUnpack, Versionstamp,
+};
+use std::borrow::Cow;
+use std::fmt::Debug;
+
+#[derive(Debug, PartialEq, TuplePack, TupleUnpack)]
+struct Named {
+    name: String,
+    id: i64,
+    parent: Option<i64>,
+}
+
+#[derive(Debug, PartialEq, TuplePack, TupleUnpack)]
+struct Unnamed(String, i64);
+
+#[derive(Debug, PartialEq, TuplePack, TupleUnpack)]
+struct Unit;
+
+#[derive(Debug, PartialEq, TuplePack, TupleUnpack)]
+struct Attributes {
+    #[tuple(flatten)]
+    named: Named,
+    #[tuple(nested)]
+    unnamed: Unnamed,
+    #[tuple(skip)]
+    cached: Option<String>,
+    #[tuple(flatten)]
+    pair: (bool, i64),
+}
+
+#[derive(Debug, PartialEq, TuplePack, TupleUnpack)]
+struct Borrowed<'a> {
+    name: Cow<'a, str>,
+    data: Bytes<'a>,
+}
+
+#[derive(Debug, PartialEq, TuplePack, TupleUnpack)]
+struct Generic<T> {
+    value: T,
+}
+
+#[derive(Debug, PartialEq, TuplePack, TupleUnpack)]
+enum State {
+    Pending,
+    Running = 5,
+    #[tuple(tag = 10)]
+    Done,
+}
+
+#[derive(Debug, PartialEq, TuplePack, TupleUnpack)]
+enum Event {
+    Created,
+    Renamed(String),
+    #[tuple(tag = 7)]
+    Moved {
+        from: i64,
+        to: i64,
+    },
+    Tagged(#[tuple(flatten)] Unnamed),
+}
+
+#[derive(Debug, PartialEq, TuplePack, TupleUnpack)]
+struct WithVersionstamp {
+    kind: i64,
+    versionstamp: Versionstamp,
+}
+
+fn test_same<'de, T, N>(value: T, native: N, packed: &'de mut Vec<u8>)
+where
+    T: TuplePack + TupleUnpack<'de> + Debug + PartialEq,
+    N: TuplePack,
+{
+    *packed = pack(&value);
+    assert_eq!(Bytes::from(packed.as_slice()), Bytes::from(pack(&native)));
+    assert_eq!(unpack::<T>(packed).unwrap(), value);
+}
+
+#[test]
+fn test_structs() {
+    test_same(
+        Named {
+            name: "foo".to_string(),
+            id: 42,
+            parent: None,
+        },
+        ("foo", 42, None::<i64>),
+        &mut Vec::new(),
+    );
+    test_same(Unnamed("foo".to_string(), -1), ("foo", -1), &mut Vec::new());
+    test_same(Unit, (), &mut Vec::new());
+    test_same((Unit, Unit), ((), ()), &mut Vec::new());
+    test_same(
+        Borrowed {
+            name: Cow::Borrowed("foo"),
+            data: Bytes::from(&b"\x00bar"[..]),
+        },
+        ("foo", Bytes::from(&b"\x00bar"[..])),
+        &mut Vec::new(),
+    );
+    test_same(
+        (Generic { value: 1i64 }, Generic { value: None::<i64> }),
+        ((1,), (None::<i64>,)),
+        &mut Vec::new(),
+    );
+}
+
+#[test]
+fn test_attributes() {
+    let mut packed = Vec::new();
+    test_same(
+        Attributes {
+            named: Named {
+                name: "foo".to_string(),
+                id: 1,
+                parent: Some(2),
+            },
+            unnamed: Unnamed("bar".to_string(), 3),
+            cached: None,
+            pair: (true, 4),
+        },
+        ("foo", 1, Some(2), ("bar", 3), true, 4),
+        &mut packed,
+    );
+
+    // skipped fields are packed as nothing, and unpacked using their default
+    let value = Attributes {
+        named: Named {
+            name: "foo".to_string(),
+            id: 1,
+            parent: None,
+        },
+        unnamed: Unnamed("bar".to_string(), 3),
+        cached: Some("cached".to_string()),
+        pair: (false, 4),
+    };
+    let packed = pack(&value);
+    assert_eq!(packed, pack(&("foo", 1, None::<i64>, ("bar", 3), false, 4)));
+    let unpacked: Attributes = unpack(&packed).unwrap();
+    assert_eq!(unpacked.cached, None);
+}
+
+#[test]
+fn test_enums() {
+    test_same(State::Pending, 0, &mut Vec::new());
+    test_same(State::Running, 5, &mut Vec::new());
+    test_same(State::Done, 10, &mut Vec::new());
+    test_same((State::Running, State::Done), (5, 10), &mut Vec::new());
+
+    test_same(Event::Created, 0, &mut Vec::new());
+    test_same(
+        Event::Renamed("foo".to_string()),
+        (1, "foo"),
+        &mut Vec::new(),
+    );
+    test_same(Event::Moved { from: 1, to: 2 }, (7, 1, 2), &mut Vec::new());
+    test_same(
+        Event::Tagged(Unnamed("foo".to_string(), 3)),
+        (8, "foo", 3),
+        &mut Vec::new(),
+    );
+    test_same(
+        vec![
+            Event::Created,
+            Event::Moved { from: 1, to: 2 },
+            Event::Renamed("foo".to_string()),
+        ],
+        (0, (7, 1, 2), (1, "foo")),
+        &mut Vec::new(),
+    );
+
+    assert!(unpack::<State>(&pack(&3)).is_err());
+    assert!(unpack::<Event>(&pack(&(2, 1))).is_err());
+    assert!(unpack::<(Event,)>(&pack(&(1,))).is_err());
+}
+
+#[test]
+fn test_versionstamp() {
+    let value = WithVersionstamp {
+        kind: 1,
+        versionstamp: Versionstamp::incomplete(0),
+    };
+    assert_eq!(
+        pack_with_versionstamp(&value),
+        pack_with_versionstamp(&(1, Versionstamp::incomplete(0)))
+    );
+
+    let nested = (Unit, Event::Created, (2, value));
+    assert_eq!(
+        pack_with_versionstamp(&nested),
+        pack_with_versionstamp(&((), 0, (2, (1, Versionstamp::incomplete(0)))))
+    );
+}
@@ -6,6 +6,7 @@
 // copied, modified, or distributed except according to those terms.
 
 use foundationdb::*;
+use futures::prelude::*;
 
 mod common;
 
@@ -14,6 +15,7 @@ fn test_watch() {
     let _guard = unsafe { foundationdb::boot() };
     futures::executor::block_on(test_watch_async()).expect("failed to run");
     futures::executor::block_on(test_watch_without_commit_async()).expect("failed to run");
+    futures::executor::block_on(test_watch_stream_async()).expect("failed to run");
 }
 
 async fn test_watch_async() -> FdbResult<()> {
@@ -53,3 +55,33 @@ async fn test_watch_without_commit_async() -> FdbResult<()> {
 
     Ok(())
 }
+
+async fn test_watch_stream_async() -> FdbResult<()> {
+    const KEY: &[u8] = b"test-watch-stream";
+
+    let db = common::database().await?;
+
+    let trx = db.create_trx()?;
+    trx.clear(KEY);
+    trx.commit().await?;
+
+    let changes = db.watch_stream(KEY);
+    futures::pin_mut!(changes);
+    assert_eq!(changes.try_next().await?, Some(None));
+
+    let trx = db.create_trx()?;
+    trx.set(KEY, b"a");
+    trx.commit().await?;
+    assert_eq!(changes.try_next().await?, Some(Some(b"a".to_vec())));
+
+    // writing the same value fires the watch, but is not reported
+    let trx = db.create_trx()?;
+    trx.set(KEY, b"a");
+    trx.commit().await?;
+    let trx = db.create_trx()?;
+    trx.set(KEY, b"b");
+    trx.commit().await?;
+    assert_eq!(changes.try_next().await?, Some(Some(b"b".to_vec())));
+
+    Ok(())
+}